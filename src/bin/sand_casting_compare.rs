@@ -0,0 +1,303 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : bin/sand_casting_compare.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Compares two metrics run directories produced by the SandCasting
+    performance profiler, and flags any performance regressions.
+
+    Exits with a non-zero status if any metric regresses beyond the
+    configured threshold.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    env,
+    path::PathBuf,
+    process,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Module Declarations
+///////////////////////////////////////////////////////////////////////////////
+
+// NOTE: Shared with the profiler, so that the writer and parser cannot drift
+#[path = "../profiler/metrics_format.rs"]
+#[allow(dead_code)]
+mod metrics_format;
+use metrics_format::MetricsRun;
+
+#[path = "../profiler/frame_stats.rs"]
+#[allow(dead_code)]
+mod frame_stats;
+use frame_stats::FrameStatsSummary;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Default regression threshold, as a percentage increase over the baseline
+const DEFAULT_THRESHOLD_PCT:    f64 = 10.0;
+
+/// Default minimum absolute increase (in ms) for a change to count as a regression.
+/// Keeps sub-microsecond phases from tripping the threshold on noise alone.
+const DEFAULT_MIN_DELTA_MS:     f64 = 0.05;
+
+/* Exit Codes */
+const EXIT_USAGE:       i32 = 2;
+const EXIT_LOAD_FAILED: i32 = 1;
+const EXIT_REGRESSION:  i32 = 3;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// A single metric, compared between the two runs (values in ms)
+struct Comparison {
+    name:       String,
+    baseline:   Option<f64>,
+    candidate:  Option<f64>,
+}
+
+struct Thresholds {
+    pct:        f64,
+    min_delta:  f64,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl Comparison {
+    fn new(name: String, baseline: Option<f64>, candidate: Option<f64>) -> Self {
+        Self {name, baseline, candidate}
+    }
+
+    /// Percentage change from baseline to candidate, if both are present and the baseline is non-zero
+    fn delta_pct(&self) -> Option<f64> {
+        match (self.baseline, self.candidate) {
+            (Some(baseline), Some(candidate)) if baseline > 0.0 => Some((candidate - baseline) / baseline * 100.0),
+            _ => None,
+        }
+    }
+
+    /// All compared metrics are times, so an increase is a regression
+    fn is_regression(&self, thresholds: &Thresholds) -> bool {
+        match (self.baseline, self.candidate, self.delta_pct()) {
+            (Some(baseline), Some(candidate), Some(delta_pct)) => {
+                delta_pct > thresholds.pct && (candidate - baseline) > thresholds.min_delta
+            },
+            _ => false,
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Main
+///////////////////////////////////////////////////////////////////////////////
+
+fn main() {
+    // Parse command line arguments
+    let args: Vec<String> = env::args().collect();
+    let run_dirs = positional_args(&args);
+    if run_dirs.len() != 2 {
+        usage();
+        process::exit(EXIT_USAGE);
+    }
+
+    let thresholds = Thresholds {
+        pct:        parse_f64_flag(&args, "-threshold").unwrap_or(DEFAULT_THRESHOLD_PCT),
+        min_delta:  parse_f64_flag(&args, "-min-delta").unwrap_or(DEFAULT_MIN_DELTA_MS),
+    };
+
+    let baseline = load_run(PathBuf::from(run_dirs[0]));
+    let candidate = load_run(PathBuf::from(run_dirs[1]));
+
+    // Compare and report
+    let comparisons = compare_runs(&baseline, &candidate);
+    print_table(&baseline, &candidate, &comparisons, &thresholds);
+
+    let regressions = comparisons.iter().filter(|comparison| comparison.is_regression(&thresholds)).count();
+    if regressions > 0 {
+        println!("\n{} regression(s) exceeded {:.1}% (min. {:.3} ms).", regressions, thresholds.pct, thresholds.min_delta);
+        process::exit(EXIT_REGRESSION);
+    }
+
+    println!("\nNo regressions exceeded {:.1}% (min. {:.3} ms).", thresholds.pct, thresholds.min_delta);
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+fn usage() {
+    println!("Usage: sand_casting_compare <baseline/run/dir> <candidate/run/dir> [-threshold <pct>] [-min-delta <ms>]");
+}
+
+fn parse_f64_flag(args: &[String], flag: &str) -> Option<f64> {
+    let idx = args.iter().position(|arg| arg == flag)?;
+
+    match args.get(idx + 1).map(|value| value.parse::<f64>()) {
+        Some(Ok(value)) => Some(value),
+        _ => {
+            eprintln!("Invalid or missing value for {}", flag);
+            usage();
+            process::exit(EXIT_USAGE);
+        }
+    }
+}
+
+/// Returns the arguments that are neither flags nor flag values
+fn positional_args(args: &[String]) -> Vec<&str> {
+    let mut positional = Vec::new();
+    let mut arg_iter = args.iter().skip(1);
+    while let Some(arg) = arg_iter.next() {
+        if arg.starts_with('-') {
+            // Every flag takes a value
+            arg_iter.next();
+        }
+        else {
+            positional.push(arg.as_str());
+        }
+    }
+
+    positional
+}
+
+/// Loads a run, exiting if it is missing or has no frame deltas to compare
+fn load_run(run_dir: PathBuf) -> MetricsRun {
+    match MetricsRun::load(&run_dir) {
+        Ok(run) if run.frame_deltas.is_empty() => {
+            eprintln!("Metrics run {} has no frame delta samples", run_dir.display());
+            process::exit(EXIT_LOAD_FAILED);
+        },
+        Ok(run) => run,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(EXIT_LOAD_FAILED);
+        }
+    }
+}
+
+/// Builds the list of compared metrics: frame-delta percentiles, stacked phase means and event span means
+fn compare_runs(baseline: &MetricsRun, candidate: &MetricsRun) -> Vec<Comparison> {
+    let mut comparisons = Vec::new();
+
+    // Frame-delta percentiles
+    let base_summary = summarize_frame_deltas(baseline);
+    let cand_summary = summarize_frame_deltas(candidate);
+    let percentiles: [(&str, fn(&FrameStatsSummary) -> f64); 5] = [
+        ("min", |summary| summary.min),
+        ("p50", |summary| summary.p50),
+        ("p95", |summary| summary.p95),
+        ("p99", |summary| summary.p99),
+        ("max", |summary| summary.max),
+    ];
+    for (name, getter) in percentiles.iter() {
+        comparisons.push(Comparison::new(format!("frame_delta {}", name),
+                                         base_summary.as_ref().map(|summary| getter(summary) * 1000.0),
+                                         cand_summary.as_ref().map(|summary| getter(summary) * 1000.0)));
+    }
+
+    // Stacked phases of each kind (draw, update, ...)
+    for ((name, base_records), (_, cand_records)) in baseline.stacks().iter().zip(candidate.stacks().iter()) {
+        let base_phases = metrics_format::stacked_phase_means(base_records);
+        let cand_phases = metrics_format::stacked_phase_means(cand_records);
+        for label in merged_names(base_phases.iter().map(|(label, _)| label), cand_phases.iter().map(|(label, _)| label)) {
+            let lookup = |phases: &[(String, f64)]| phases.iter().find(|(phase, _)| *phase == label).map(|(_, mean)| mean * 1000.0);
+            comparisons.push(Comparison::new(format!("{} {}", name, label), lookup(&base_phases), lookup(&cand_phases)));
+        }
+    }
+
+    // Event spans
+    let base_spans = baseline.event_span_durations();
+    let cand_spans = candidate.event_span_durations();
+    for name in merged_names(base_spans.iter().map(|(name, _)| name), cand_spans.iter().map(|(name, _)| name)) {
+        let lookup = |spans: &[(String, Vec<u64>)]| spans.iter().find(|(span, _)| *span == name).map(|(_, durations)| mean_ms(durations));
+        comparisons.push(Comparison::new(format!("span {}", name), lookup(&base_spans), lookup(&cand_spans)));
+    }
+
+    comparisons
+}
+
+fn summarize_frame_deltas(run: &MetricsRun) -> Option<FrameStatsSummary> {
+    if run.frame_deltas.is_empty() {
+        None
+    }
+    else {
+        Some(FrameStatsSummary::from_samples(&run.frame_delta_values()))
+    }
+}
+
+/// Merges two lists of names, preserving first-seen order
+fn merged_names<'a, A, B>(first: A, second: B) -> Vec<String>
+where
+    A: Iterator<Item = &'a String>,
+    B: Iterator<Item = &'a String>,
+{
+    let mut names: Vec<String> = Vec::new();
+    for name in first.chain(second) {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+
+    names
+}
+
+fn mean_ms(durations: &[u64]) -> f64 {
+    if durations.is_empty() {
+        0.0
+    }
+    else {
+        durations.iter().sum::<u64>() as f64 / durations.len() as f64
+    }
+}
+
+fn print_table(baseline: &MetricsRun, candidate: &MetricsRun, comparisons: &[Comparison], thresholds: &Thresholds) {
+    let name_width = comparisons.iter().map(|comparison| comparison.name.len()).max().unwrap_or(0).max(6);
+
+    println!("Baseline:  {}", baseline.path.display());
+    println!("Candidate: {}\n", candidate.path.display());
+    println!("{:<width$}  {:>12}  {:>12}  {:>9}",
+             "metric", "baseline ms", "candidate ms", "delta", width = name_width);
+    println!("{}", "-".repeat(name_width + 43));
+
+    for comparison in comparisons {
+        let format_value = |value: Option<f64>| match value {
+            Some(value) => format!("{:.3}", value),
+            None => String::from("-"),
+        };
+        let delta = match comparison.delta_pct() {
+            Some(delta_pct) => format!("{:+.1}%", delta_pct),
+            None => String::from("-"),
+        };
+        let flag = if comparison.is_regression(thresholds) { "REGRESSION" } else { "" };
+
+        println!("{:<width$}  {:>12}  {:>12}  {:>9}  {}",
+                 comparison.name,
+                 format_value(comparison.baseline),
+                 format_value(comparison.candidate),
+                 delta,
+                 flag,
+                 width = name_width);
+    }
+}
@@ -0,0 +1,294 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : bin/sand_casting_report/main.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Generates a self-contained HTML or SVG report from a metrics run directory
+    produced by the SandCasting performance profiler.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    env,
+    fmt::Write,
+    fs,
+    path::PathBuf,
+    process,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Module Declarations
+///////////////////////////////////////////////////////////////////////////////
+
+// NOTE: Shared with the profiler, so that the writer and parser cannot drift
+#[path = "../../profiler/metrics_format.rs"]
+#[allow(dead_code)]
+mod metrics_format;
+use metrics_format::MetricsRun;
+
+#[path = "../../profiler/frame_stats.rs"]
+#[allow(dead_code)]
+mod frame_stats;
+use frame_stats::FrameStatsSummary;
+
+mod svg;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+const DEFAULT_HTML_FILENAME:    &str = "report.html";
+const DEFAULT_SVG_FILENAME:     &str = "report.svg";
+
+/// Vertical space allotted to each chart when composing a single SVG
+const SVG_CHART_PITCH:          usize = 330;
+
+/// Vertical space allotted to each summary line when composing a single SVG
+const SVG_LINE_PITCH:           usize = 14;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OutputFormat {
+    Html,
+    Svg,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Main
+///////////////////////////////////////////////////////////////////////////////
+
+fn main() {
+    // Parse command line arguments
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        usage();
+        process::exit(2);
+    }
+
+    let mut run_dir = None;
+    let mut output_path = None;
+    let mut format = OutputFormat::Html;
+    let mut arg_iter = args.iter().skip(1);
+    while let Some(arg) = arg_iter.next() {
+        match arg.as_str() {
+            "-svg" => format = OutputFormat::Svg,
+            "-o" => match arg_iter.next() {
+                Some(path) => output_path = Some(PathBuf::from(path)),
+                None => {
+                    usage();
+                    process::exit(2);
+                }
+            },
+            // The run dir is the only positional argument
+            _ if !arg.starts_with('-') && run_dir.is_none() => run_dir = Some(PathBuf::from(arg)),
+            _ => {
+                eprintln!("Unexpected argument '{}'", arg);
+                usage();
+                process::exit(2);
+            }
+        }
+    }
+
+    let run_dir = match run_dir {
+        Some(run_dir) => run_dir,
+        None => {
+            usage();
+            process::exit(2);
+        }
+    };
+    let output_path = output_path.unwrap_or_else(|| run_dir.join(match format {
+        OutputFormat::Html  => DEFAULT_HTML_FILENAME,
+        OutputFormat::Svg   => DEFAULT_SVG_FILENAME,
+    }));
+
+    // Load and render the run
+    let run = match MetricsRun::load(&run_dir) {
+        Ok(run) => run,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    let report = match format {
+        OutputFormat::Html  => render_html(&run),
+        OutputFormat::Svg   => render_svg(&run),
+    };
+
+    if let Err(e) = fs::write(&output_path, report) {
+        eprintln!("Failed to write report to {}: {}", output_path.display(), e);
+        process::exit(1);
+    }
+
+    println!("Report written to {}", output_path.display());
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+fn usage() {
+    println!("Usage: sand_casting_report <path/to/metrics/run/dir> [-o <output_path>] [-svg]");
+}
+
+/// Uppercases the first character of the given name
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Renders each chart of the report, in display order
+fn render_charts(run: &MetricsRun) -> Vec<String> {
+    let mut charts = vec![
+        svg::line_chart("Average FPS", "FPS", &run.avg_fps, &run.events),
+        svg::line_chart("Frame Delta", "Frame Delta (sec)", &run.frame_deltas, &run.events),
+        svg::histogram("Frame Delta Histogram", "frame delta", &run.frame_delta_values(), svg::HISTOGRAM_BINS),
+        svg::stacked_chart("Stacked Draw Times", &run.stacked_draw_times, &metrics_format::stacked_phase_labels(&run.stacked_draw_times)),
+    ];
+
+    // Only chart the other stacks if they were recorded, since older runs won't have them
+    for (name, records) in run.stacks().iter().skip(1) {
+        if !records.is_empty() {
+            charts.push(svg::stacked_chart(&format!("Stacked {} Times", capitalize(name)),
+                                           records,
+                                           &metrics_format::stacked_phase_labels(records)));
+        }
+    }
+
+    charts
+}
+
+/// Collects the summary statistics of the run as (name, value) rows
+fn summary_rows(run: &MetricsRun) -> Vec<(String, String)> {
+    let mut rows = Vec::new();
+
+    let summary = FrameStatsSummary::from_samples(&run.frame_delta_values());
+    let mean_fps = if run.avg_fps.is_empty() {
+        0.0
+    }
+    else {
+        run.avg_fps.iter().map(|record| record.value).sum::<f64>() / run.avg_fps.len() as f64
+    };
+
+    rows.push((String::from("Duration"),           format!("{:.3} s", run.duration_ms() as f64 / 1000.0)));
+    rows.push((String::from("Frames"),             format!("{}", run.frame_deltas.len())));
+    rows.push((String::from("Mean Avg. FPS"),      format!("{:.1}", mean_fps)));
+    rows.push((String::from("Frame Delta (min)"),  format!("{:.3} ms", summary.min * 1000.0)));
+    rows.push((String::from("Frame Delta (p50)"),  format!("{:.3} ms", summary.p50 * 1000.0)));
+    rows.push((String::from("Frame Delta (p95)"),  format!("{:.3} ms", summary.p95 * 1000.0)));
+    rows.push((String::from("Frame Delta (p99)"),  format!("{:.3} ms", summary.p99 * 1000.0)));
+    rows.push((String::from("Frame Delta (max)"),  format!("{:.3} ms", summary.max * 1000.0)));
+
+    for (name, records) in run.stacks().iter() {
+        for (label, mean) in metrics_format::stacked_phase_means(records) {
+            rows.push((format!("{} Phase '{}' (mean)", capitalize(name), label), format!("{:.3} ms", mean * 1000.0)));
+        }
+    }
+
+    rows.push((String::from("Event Markers"),      format!("{}", run.events.len())));
+    rows.push((String::from("Slow Frames"),        format!("{}", run.slow_frame_count())));
+    rows.push((String::from("Dropped Metrics"),    format!("{}", run.dropped_metric_count())));
+
+    rows
+}
+
+fn render_html(run: &MetricsRun) -> String {
+    let mut html = String::new();
+    let title = format!("SandCasting Performance Profiling Data ({})", run.name());
+
+    writeln!(html, "<!DOCTYPE html>").unwrap();
+    writeln!(html, "<html><head><meta charset=\"utf-8\"><title>{}</title>", svg::escape(&title)).unwrap();
+    writeln!(html, "<style>body {{ font-family: monospace; }} td {{ padding: 0 1em; }} .events {{ max-height: 20em; overflow-y: auto; }}</style>").unwrap();
+    writeln!(html, "</head><body>").unwrap();
+    writeln!(html, "<h1>{}</h1>", svg::escape(&title)).unwrap();
+
+    // Summary statistics
+    writeln!(html, "<h2>Summary</h2><table>").unwrap();
+    for (name, value) in summary_rows(run) {
+        writeln!(html, "<tr><td>{}</td><td>{}</td></tr>", svg::escape(&name), svg::escape(&value)).unwrap();
+    }
+    writeln!(html, "</table>").unwrap();
+
+    // Charts
+    writeln!(html, "<h2>Charts</h2>").unwrap();
+    for chart in render_charts(run) {
+        writeln!(html, "<div>{}</div>", chart).unwrap();
+    }
+
+    // Event markers
+    writeln!(html, "<h2>Event Markers</h2><div class=\"events\"><table>").unwrap();
+    for event in &run.events {
+        writeln!(html,
+                 "<tr><td>{} ms</td><td>{}</td><td>{}</td></tr>",
+                 event.timestamp_ms,
+                 svg::escape(&event.label),
+                 svg::escape(&metrics_format::display_attributes(&event.attributes))).unwrap();
+    }
+    writeln!(html, "</table></div>").unwrap();
+
+    writeln!(html, "</body></html>").unwrap();
+
+    html
+}
+
+fn render_svg(run: &MetricsRun) -> String {
+    let charts = render_charts(run);
+    let rows = summary_rows(run);
+
+    let summary_height = (rows.len() + 2) * SVG_LINE_PITCH;
+    let total_height = summary_height + charts.len() * SVG_CHART_PITCH;
+
+    let mut svg_doc = String::new();
+    writeln!(svg_doc,
+             r#"<svg xmlns="http://www.w3.org/2000/svg" width="960" height="{}" font-family="monospace" font-size="11">"#,
+             total_height).unwrap();
+    writeln!(svg_doc, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+    writeln!(svg_doc,
+             r#"<text x="10" y="{}" font-size="14">SandCasting Performance Profiling Data ({})</text>"#,
+             SVG_LINE_PITCH,
+             svg::escape(&run.name())).unwrap();
+
+    // Summary statistics
+    for (i, (name, value)) in rows.iter().enumerate() {
+        writeln!(svg_doc,
+                 r#"<text x="10" y="{}">{}: {}</text>"#,
+                 (i + 2) * SVG_LINE_PITCH,
+                 svg::escape(name),
+                 svg::escape(value)).unwrap();
+    }
+
+    // Charts, stacked vertically
+    for (i, chart) in charts.iter().enumerate() {
+        writeln!(svg_doc,
+                 r#"<g transform="translate(0, {})">{}</g>"#,
+                 summary_height + i * SVG_CHART_PITCH,
+                 chart).unwrap();
+    }
+
+    writeln!(svg_doc, "</svg>").unwrap();
+
+    svg_doc
+}
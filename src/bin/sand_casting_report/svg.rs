@@ -0,0 +1,299 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : bin/sand_casting_report/svg.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module provides functions to render metrics data as standalone SVG
+    charts.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::fmt::Write;
+
+use crate::metrics_format::{
+    self,
+    EventRecord,
+    F64Record,
+    StackedRecord,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/* Chart Dimensions */
+const CHART_WIDTH:      f64 = 960.0;
+const CHART_HEIGHT:     f64 = 320.0;
+const CHART_MARGIN:     f64 = 50.0;
+const PLOT_WIDTH:       f64 = CHART_WIDTH - 2.0*CHART_MARGIN;
+const PLOT_HEIGHT:      f64 = CHART_HEIGHT - 2.0*CHART_MARGIN;
+
+/// Number of buckets the stacked chart is averaged into
+const STACKED_BUCKETS:  usize = 200;
+
+/// Number of bins in the frame delta histogram
+pub const HISTOGRAM_BINS: usize = 50;
+
+/// Colors cycled through for each stacked phase
+const PHASE_COLORS: [&str; 8] = [
+    "#e6194b",
+    "#f58231",
+    "#ffe119",
+    "#3cb44b",
+    "#42d4f4",
+    "#4363d8",
+    "#911eb4",
+    "#f032e6",
+];
+
+/// Color of event marker lines
+const EVENT_COLOR: &str = "#888888";
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Renders a time-series line chart, with event markers overlaid as vertical lines
+pub fn line_chart(title: &str, y_label: &str, records: &[F64Record], events: &[EventRecord]) -> String {
+    let mut svg = open_chart(title);
+
+    if records.is_empty() {
+        write_empty_notice(&mut svg);
+        return close_chart(svg);
+    }
+
+    let (t_min, t_max) = time_bounds(records.iter().map(|record| record.timestamp_ms));
+    let y_max = records.iter().map(|record| record.value).fold(0.0, f64::max).max(f64::EPSILON);
+
+    write_axes(&mut svg, y_label, t_min, t_max, y_max);
+
+    // Event markers first, so the data line is drawn over them
+    for event in events.iter().filter(|event| event.timestamp_ms >= t_min && event.timestamp_ms <= t_max) {
+        let x = x_pos(event.timestamp_ms, t_min, t_max);
+        writeln!(svg,
+                 r#"<line x1="{x:.1}" y1="{top:.1}" x2="{x:.1}" y2="{bottom:.1}" stroke="{color}" stroke-dasharray="2,2"><title>{label} @ {t}ms{attributes}</title></line>"#,
+                 x = x,
+                 top = CHART_MARGIN,
+                 bottom = CHART_MARGIN + PLOT_HEIGHT,
+                 color = EVENT_COLOR,
+                 label = escape(&event.label),
+                 t = event.timestamp_ms,
+                 attributes = if event.attributes.is_empty() {
+                     String::new()
+                 }
+                 else {
+                     escape(&format!(" ({})", metrics_format::display_attributes(&event.attributes)))
+                 }).unwrap();
+    }
+
+    let points: Vec<String> = records.iter()
+        .map(|record| format!("{:.1},{:.1}", x_pos(record.timestamp_ms, t_min, t_max), y_pos(record.value, y_max)))
+        .collect();
+    writeln!(svg, r#"<polyline fill="none" stroke="{}" stroke-width="1" points="{}"/>"#, PHASE_COLORS[5], points.join(" ")).unwrap();
+
+    close_chart(svg)
+}
+
+/// Renders a histogram of the given values
+pub fn histogram(title: &str, x_label: &str, values: &[f64], bins: usize) -> String {
+    let mut svg = open_chart(title);
+
+    if values.is_empty() || bins == 0 {
+        write_empty_notice(&mut svg);
+        return close_chart(svg);
+    }
+
+    let v_min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let v_max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let bin_width = ((v_max - v_min) / bins as f64).max(f64::EPSILON);
+
+    let mut counts = vec![0_usize; bins];
+    for value in values {
+        let bin = (((value - v_min) / bin_width) as usize).min(bins - 1);
+        counts[bin] += 1;
+    }
+    let max_count = *counts.iter().max().unwrap_or(&1) as f64;
+
+    let bar_width = PLOT_WIDTH / bins as f64;
+    for (i, count) in counts.iter().enumerate() {
+        let bar_height = (*count as f64 / max_count) * PLOT_HEIGHT;
+        writeln!(svg,
+                 r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"><title>{:.3}-{:.3} ms: {}</title></rect>"#,
+                 CHART_MARGIN + i as f64 * bar_width,
+                 CHART_MARGIN + PLOT_HEIGHT - bar_height,
+                 (bar_width - 1.0).max(1.0),
+                 bar_height,
+                 PHASE_COLORS[3],
+                 (v_min + i as f64 * bin_width) * 1000.0,
+                 (v_min + (i + 1) as f64 * bin_width) * 1000.0,
+                 count).unwrap();
+    }
+
+    // Axis labels for the histogram are value ranges, not timestamps
+    write_frame(&mut svg);
+    write_text(&mut svg, CHART_MARGIN, CHART_HEIGHT - CHART_MARGIN / 3.0, "start", &format!("{:.3} ms", v_min * 1000.0));
+    write_text(&mut svg, CHART_MARGIN + PLOT_WIDTH, CHART_HEIGHT - CHART_MARGIN / 3.0, "end", &format!("{:.3} ms", v_max * 1000.0));
+    write_text(&mut svg, CHART_WIDTH / 2.0, CHART_HEIGHT - CHART_MARGIN / 3.0, "middle", x_label);
+    write_text(&mut svg, CHART_MARGIN - 5.0, CHART_MARGIN + 10.0, "end", &format!("{}", max_count));
+
+    close_chart(svg)
+}
+
+/// Renders stacked phase times, averaged into fixed-width time buckets
+pub fn stacked_chart(title: &str, records: &[StackedRecord], labels: &[String]) -> String {
+    let mut svg = open_chart(title);
+
+    if records.is_empty() || labels.is_empty() {
+        write_empty_notice(&mut svg);
+        return close_chart(svg);
+    }
+
+    let (t_min, t_max) = time_bounds(records.iter().map(|record| record.timestamp_ms));
+
+    // Average each phase within each bucket
+    let mut sums = vec![vec![0.0; labels.len()]; STACKED_BUCKETS];
+    let mut counts = vec![0_usize; STACKED_BUCKETS];
+    for record in records {
+        let bucket = bucket_index(record.timestamp_ms, t_min, t_max, STACKED_BUCKETS);
+        counts[bucket] += 1;
+        for (label, time) in &record.phases {
+            if let Some(idx) = labels.iter().position(|known| known == label) {
+                sums[bucket][idx] += time;
+            }
+        }
+    }
+    let means: Vec<Vec<f64>> = sums.iter().zip(counts.iter())
+        .map(|(bucket_sums, count)| bucket_sums.iter().map(|sum| if *count > 0 { sum / *count as f64 } else { 0.0 }).collect())
+        .collect();
+    let y_max = means.iter().map(|bucket| bucket.iter().sum::<f64>()).fold(0.0, f64::max).max(f64::EPSILON);
+
+    write_axes(&mut svg, "Stacked Time (sec)", t_min, t_max, y_max);
+
+    let bar_width = PLOT_WIDTH / STACKED_BUCKETS as f64;
+    for (bucket, phases) in means.iter().enumerate() {
+        let mut y_offset = 0.0;
+        for (idx, time) in phases.iter().enumerate() {
+            if *time <= 0.0 {
+                continue;
+            }
+
+            let bar_height = (time / y_max) * PLOT_HEIGHT;
+            writeln!(svg,
+                     r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"><title>{}: {:.3} ms</title></rect>"#,
+                     CHART_MARGIN + bucket as f64 * bar_width,
+                     CHART_MARGIN + PLOT_HEIGHT - y_offset - bar_height,
+                     bar_width,
+                     bar_height,
+                     PHASE_COLORS[idx % PHASE_COLORS.len()],
+                     escape(&labels[idx]),
+                     time * 1000.0).unwrap();
+            y_offset += bar_height;
+        }
+    }
+
+    // Legend along the top of the chart
+    for (idx, label) in labels.iter().enumerate() {
+        let x = CHART_MARGIN + idx as f64 * (PLOT_WIDTH / labels.len() as f64);
+        writeln!(svg, r#"<rect x="{:.1}" y="{:.1}" width="10" height="10" fill="{}"/>"#,
+                 x, CHART_MARGIN / 2.0, PHASE_COLORS[idx % PHASE_COLORS.len()]).unwrap();
+        write_text(&mut svg, x + 14.0, CHART_MARGIN / 2.0 + 9.0, "start", label);
+    }
+
+    close_chart(svg)
+}
+
+/// Escapes text for inclusion in SVG/HTML
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+fn open_chart(title: &str) -> String {
+    let mut svg = String::new();
+    writeln!(svg,
+             r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="monospace" font-size="11">"#,
+             w = CHART_WIDTH,
+             h = CHART_HEIGHT).unwrap();
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+    write_text(&mut svg, CHART_WIDTH / 2.0, CHART_MARGIN / 3.0 + 5.0, "middle", title);
+
+    svg
+}
+
+fn close_chart(mut svg: String) -> String {
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn write_empty_notice(svg: &mut String) {
+    write_text(svg, CHART_WIDTH / 2.0, CHART_HEIGHT / 2.0, "middle", "No data recorded");
+}
+
+fn write_frame(svg: &mut String) {
+    writeln!(svg,
+             r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="black"/>"#,
+             CHART_MARGIN, CHART_MARGIN, PLOT_WIDTH, PLOT_HEIGHT).unwrap();
+}
+
+fn write_axes(svg: &mut String, y_label: &str, t_min: u64, t_max: u64, y_max: f64) {
+    write_frame(svg);
+
+    // X-axis extents, in seconds
+    write_text(svg, CHART_MARGIN, CHART_HEIGHT - CHART_MARGIN / 3.0, "start", &format!("{:.1}s", t_min as f64 / 1000.0));
+    write_text(svg, CHART_MARGIN + PLOT_WIDTH, CHART_HEIGHT - CHART_MARGIN / 3.0, "end", &format!("{:.1}s", t_max as f64 / 1000.0));
+    write_text(svg, CHART_WIDTH / 2.0, CHART_HEIGHT - CHART_MARGIN / 3.0, "middle", "time");
+
+    // Y-axis extents
+    write_text(svg, CHART_MARGIN - 5.0, CHART_MARGIN + 10.0, "end", &format!("{:.4}", y_max));
+    write_text(svg, CHART_MARGIN - 5.0, CHART_MARGIN + PLOT_HEIGHT, "end", "0");
+    writeln!(svg,
+             r#"<text x="12" y="{y}" transform="rotate(-90 12 {y})" text-anchor="middle">{label}</text>"#,
+             y = CHART_MARGIN + PLOT_HEIGHT / 2.0,
+             label = escape(y_label)).unwrap();
+}
+
+fn write_text(svg: &mut String, x: f64, y: f64, anchor: &str, text: &str) {
+    writeln!(svg, r#"<text x="{:.1}" y="{:.1}" text-anchor="{}">{}</text>"#, x, y, anchor, escape(text)).unwrap();
+}
+
+fn time_bounds<I: Iterator<Item = u64>>(timestamps: I) -> (u64, u64) {
+    let (min, max) = timestamps.fold((u64::MAX, u64::MIN), |(min, max), t| (min.min(t), max.max(t)));
+
+    // Avoid a zero-width time axis
+    (min, max.max(min + 1))
+}
+
+fn x_pos(timestamp_ms: u64, t_min: u64, t_max: u64) -> f64 {
+    CHART_MARGIN + ((timestamp_ms - t_min) as f64 / (t_max - t_min) as f64) * PLOT_WIDTH
+}
+
+fn y_pos(value: f64, y_max: f64) -> f64 {
+    CHART_MARGIN + PLOT_HEIGHT - (value / y_max) * PLOT_HEIGHT
+}
+
+fn bucket_index(timestamp_ms: u64, t_min: u64, t_max: u64, buckets: usize) -> usize {
+    let fraction = (timestamp_ms - t_min) as f64 / (t_max - t_min) as f64;
+
+    ((fraction * buckets as f64) as usize).min(buckets - 1)
+}
@@ -20,20 +20,14 @@ Purpose:
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
 use cast_iron::{
-    element::{
-        Element,
-        Elemental,
-    },
-    mechanics::{
-        resource::{
-            Resource,
-            State,
-        },
-    },
+    element::Element,
+    mechanics::resource::State,
 };
 
 use ggez::graphics as ggez_gfx;
 
+use crate::game_assets::terrain::Terrain;
+
 
 ///////////////////////////////////////////////////////////////////////////////
 //  Constants
@@ -160,12 +154,12 @@ pub fn from_element(elem: Element) -> ggez_gfx::Color {
     }
 }
 
-pub fn from_resource(res: &Resource) -> ggez_gfx::Color {
+pub fn from_resource_state(elem: Element, state: &State) -> ggez_gfx::Color {
     // Determine base color based on element of resource
-    let mut res_color = from_element(res.element());
+    let mut res_color = from_element(elem);
 
     // Adjust alpha based on state
-    match res.state() {
+    match state {
         State::Depleted => res_color.a = 0.000,
         State::Low      => res_color.a = 0.050,
         State::Partial  => res_color.a = 0.100,
@@ -176,3 +170,14 @@ pub fn from_resource(res: &Resource) -> ggez_gfx::Color {
 
     res_color
 }
+
+pub fn from_terrain(terrain: Terrain) -> ggez_gfx::Color {
+    // Keep terrain muted, so that resources and weather drawn over it stand out
+    match terrain {
+        Terrain::Plain      => TRANSPARENT,
+        Terrain::Forest     => ggez_gfx::Color {r: 0.100, g: 0.350, b: 0.100, a: 0.500},
+        Terrain::Water      => ggez_gfx::Color {r: 0.100, g: 0.200, b: 0.450, a: 0.500},
+        Terrain::Sand       => ggez_gfx::Color {r: 0.600, g: 0.550, b: 0.300, a: 0.400},
+        Terrain::Mountain   => ggez_gfx::Color {r: 0.400, g: 0.350, b: 0.300, a: 0.700},
+    }
+}
@@ -33,7 +33,10 @@ use ggez::{
     mint as ggez_mint,
 };
 
-use crate::game_assets::colors;
+use crate::game_assets::{
+    colors,
+    terrain::Terrain,
+};
 
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -54,6 +57,7 @@ pub struct HexGridCell {
     center:     ggez_mint::Point2<f32>,         // Pixel-coords centerpoint
     vertices:   [ggez_mint::Point2<f32>; 6],    // Pixel-coords of vertices
     highlight:  bool,                           // Indicates if cell should be highlighted in world grid
+    terrain:    Terrain,
 }
 
 pub struct HexGridCellError;
@@ -77,7 +81,7 @@ impl HexGridCell {
         vertices[4] = ggez_mint::Point2{ x: center.x - x_offset,   y: center.y + y_offset};
         vertices[5] = ggez_mint::Point2{ x: center.x + x_offset,   y: center.y + y_offset};
 
-        Self {center, vertices, highlight: false}
+        Self {center, vertices, highlight: false, terrain: Terrain::default()}
     }
 
     /// Hex-coords-based constructor
@@ -105,6 +109,10 @@ impl HexGridCell {
         self.highlight
     }
 
+    pub fn terrain(&self) -> Terrain {
+        self.terrain
+    }
+
     
     /*  *  *  *  *  *  *  *\
      *  Mutator Methods   *
@@ -118,6 +126,10 @@ impl HexGridCell {
         self.highlight = !self.highlight;
     }
 
+    pub fn set_terrain(&mut self, terrain: Terrain) {
+        self.terrain = terrain;
+    }
+
 
     /*  *  *  *  *  *  *  *\
      *  Utility Methods   *
@@ -254,7 +266,7 @@ impl HexGridCell {
     \*  *  *  *  *  *  *  */
 
     /// Adds the fill portion of a hex cell to the given Mesh
-    fn add_hex_fill_to_mesh(&self, color: ggez_gfx::Color, mesh_builder: &mut ggez_gfx::MeshBuilder) {
+    pub fn add_hex_fill_to_mesh(&self, color: ggez_gfx::Color, mesh_builder: &mut ggez_gfx::MeshBuilder) {
         mesh_builder.polygon(ggez_gfx::DrawMode::fill(), &self.vertices, color).unwrap();
     }
 
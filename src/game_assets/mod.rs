@@ -20,4 +20,6 @@ Purpose:
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
 pub mod hex_grid_cell;
-pub mod colors;
\ No newline at end of file
+pub mod colors;
+pub mod particle_system;
+pub mod terrain;
\ No newline at end of file
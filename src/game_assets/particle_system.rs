@@ -0,0 +1,173 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_assets/particle_system.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module provides a small particle system: a fixed-size pool of
+    particles, reused as they expire, drawn together in a single sprite batch.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use ggez::{
+    Context as GgEzContext,
+    graphics as ggez_gfx,
+    mint as ggez_mint,
+};
+
+use crate::game_assets::colors;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Fraction of a particle's lifetime, at the end, over which it fades out
+const FADE_OUT_FRACTION: f32 = 0.25;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Initial state of a newly-spawned particle
+#[derive(Debug, Copy, Clone)]
+pub struct ParticleSpec {
+    pub pos:        ggez_mint::Point2<f32>,     // Pixel-coords position
+    pub vel:        ggez_mint::Vector2<f32>,    // Pixels per second
+    pub size:       ggez_mint::Vector2<f32>,    // Pixel width and height
+    pub rotation:   f32,                        // Radians
+    pub spin:       f32,                        // Radians per second
+    pub lifetime:   f32,                        // Seconds
+    pub color:      ggez_gfx::Color,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Particle {
+    spec:   ParticleSpec,
+    age:    f32,
+    alive:  bool,
+}
+
+pub struct ParticleSystem {
+    pool:       Vec<Particle>,                  // Every particle, alive or not
+    free:       Vec<usize>,                     // Indices of dead particles, ready for reuse
+    bounds:     ggez_gfx::Rect,                 // Particles leaving these bounds are killed
+    batch:      ggez_gfx::spritebatch::SpriteBatch,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl ParticleSystem {
+    /// Creates a system holding at most the given number of particles, all confined to the given bounds
+    pub fn new(capacity: usize, bounds: ggez_gfx::Rect, ggez_ctx: &mut GgEzContext) -> Self {
+        // Every particle is a scaled, tinted copy of a single white pixel, so they can all share a batch
+        let pixel = ggez_gfx::Image::solid(ggez_ctx, 1, colors::WHITE).unwrap();
+
+        let dead_particle = Particle {
+            spec: ParticleSpec {
+                pos:        ggez_mint::Point2 {x: 0.0, y: 0.0},
+                vel:        ggez_mint::Vector2 {x: 0.0, y: 0.0},
+                size:       ggez_mint::Vector2 {x: 0.0, y: 0.0},
+                rotation:   0.0,
+                spin:       0.0,
+                lifetime:   0.0,
+                color:      colors::TRANSPARENT,
+            },
+            age:    0.0,
+            alive:  false,
+        };
+
+        Self {
+            pool:   vec![dead_particle; capacity],
+            free:   (0..capacity).rev().collect(),
+            bounds,
+            batch:  ggez_gfx::spritebatch::SpriteBatch::new(pixel),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn live_count(&self) -> usize {
+        self.pool.len() - self.free.len()
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Spawns a particle in a free slot of the pool. Returns false if the pool is exhausted.
+    pub fn spawn(&mut self, spec: ParticleSpec) -> bool {
+        match self.free.pop() {
+            Some(idx) => {
+                self.pool[idx] = Particle {spec, age: 0.0, alive: true};
+                true
+            },
+            None => false,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Moves and ages every live particle by the given time step (in seconds), killing any
+    /// that have expired or left the bounds
+    pub fn update(&mut self, delta_secs: f32) {
+        for (idx, particle) in self.pool.iter_mut().enumerate() {
+            if !particle.alive {
+                continue;
+            }
+
+            particle.age += delta_secs;
+            particle.spec.pos.x += particle.spec.vel.x * delta_secs;
+            particle.spec.pos.y += particle.spec.vel.y * delta_secs;
+            particle.spec.rotation += particle.spec.spin * delta_secs;
+
+            if particle.age >= particle.spec.lifetime || !self.bounds.contains(particle.spec.pos) {
+                particle.alive = false;
+                self.free.push(idx);
+            }
+        }
+    }
+
+    /// Draws every live particle in a single batched draw call
+    pub fn draw(&mut self, ggez_ctx: &mut GgEzContext) {
+        self.batch.clear();
+
+        for particle in self.pool.iter().filter(|particle| particle.alive) {
+            // Fade out towards the end of the particle's lifetime
+            let mut color = particle.spec.color;
+            let remaining_fraction = 1.0 - particle.age / particle.spec.lifetime;
+            color.a *= (remaining_fraction / FADE_OUT_FRACTION).min(1.0);
+
+            self.batch.add(ggez_gfx::DrawParam::new()
+                               .dest(particle.spec.pos)
+                               .offset(ggez_mint::Point2 {x: 0.5, y: 0.5})
+                               .rotation(particle.spec.rotation)
+                               .scale(particle.spec.size)
+                               .color(color));
+        }
+
+        ggez_gfx::draw(ggez_ctx, &self.batch, ggez_gfx::DrawParam::default()).unwrap();
+    }
+}
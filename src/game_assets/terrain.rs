@@ -0,0 +1,138 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_assets/terrain.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module defines the terrain of a hex cell, and how it affects the
+    actors and weather over it: how costly it is to move through, whether it
+    blocks sight, and which elements of weather it strengthens or weakens.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::fmt;
+
+use cast_iron::element::Element;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Weather intensity multipliers over terrain with an affinity for, or against, the weather's element
+const AFFINITY_WEATHER_MODIFIER:    f64 = 1.5;
+const OPPOSED_WEATHER_MODIFIER:     f64 = 0.5;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Terrain {
+    Plain,
+    Forest,
+    Water,
+    Sand,
+    Mountain,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl Terrain {
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the cost of moving into a cell of this terrain, relative to Plain, or None if it is impassable
+    pub fn movement_cost(&self) -> Option<u32> {
+        match self {
+            Terrain::Plain      => Some(1),
+            Terrain::Forest     => Some(2),
+            Terrain::Water      => Some(3),
+            Terrain::Sand       => Some(2),
+            Terrain::Mountain   => None,
+        }
+    }
+
+    /// Returns true if this terrain blocks line of sight through it
+    pub fn blocks_sight(&self) -> bool {
+        match self {
+            Terrain::Forest | Terrain::Mountain => true,
+            _                                   => false,
+        }
+    }
+
+    /// Returns the element this terrain strengthens, if any
+    pub fn affinity(&self) -> Option<Element> {
+        match self {
+            Terrain::Plain      => None,
+            Terrain::Forest     => Some(Element::Wind),
+            Terrain::Water      => Some(Element::Water),
+            Terrain::Sand       => Some(Element::Fire),
+            Terrain::Mountain   => Some(Element::Earth),
+        }
+    }
+
+    /// Returns the element this terrain weakens, if any
+    pub fn opposition(&self) -> Option<Element> {
+        match self {
+            Terrain::Plain      => None,
+            Terrain::Forest     => None,
+            Terrain::Water      => Some(Element::Fire),
+            Terrain::Sand       => Some(Element::Water),
+            Terrain::Mountain   => Some(Element::Wind),
+        }
+    }
+
+    /// Returns the multiplier applied to the intensity of weather of the given element over this terrain
+    pub fn weather_modifier(&self, element: Element) -> f64 {
+        if self.affinity() == Some(element) {
+            AFFINITY_WEATHER_MODIFIER
+        }
+        else if self.opposition() == Some(element) {
+            OPPOSED_WEATHER_MODIFIER
+        }
+        else {
+            1.0
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for Terrain {
+    fn default() -> Self {
+        Terrain::Plain
+    }
+}
+
+impl fmt::Display for Terrain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Terrain::Plain      => write!(f, "Plain"),
+            Terrain::Forest     => write!(f, "Forest"),
+            Terrain::Water      => write!(f, "Water"),
+            Terrain::Sand       => write!(f, "Sand"),
+            Terrain::Mountain   => write!(f, "Mountain"),
+        }
+    }
+}
@@ -0,0 +1,271 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_clock.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Defines the game clock, which tracks simulation time separately from wall
+    time so that the simulation can be paused, slowed, sped up or stepped.
+
+    Wall time is fed in each frame, scaled, and accumulated into fixed-length
+    simulation steps. All simulation code should read the game clock, while
+    the profiler continues to use wall time.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::time::Duration;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Maximum number of fixed steps run for a single frame, so that a long stall
+/// (e.g. dragging the window) doesn't send the simulation racing to catch up
+const MAX_STEPS_PER_ADVANCE:    u32 = 5;
+
+/// Bounds of the time scale
+pub const MIN_TIME_SCALE:       f64 = 0.125;
+pub const MAX_TIME_SCALE:       f64 = 8.0;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameClock {
+    elapsed:        Duration,   // Simulation time, advanced one fixed step at a time
+    fixed_timestep: Duration,   // Length of a single simulation step
+    accumulator:    Duration,   // Scaled wall time not yet consumed by a step
+    time_scale:     f64,        // Simulation seconds per wall second
+    paused:         bool,
+    pending_steps:  u32,        // Single steps requested while paused
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl GameClock {
+    /// Creates a running clock at normal speed, stepping at the given rate (in steps per second)
+    pub fn new(steps_per_sec: u32) -> Self {
+        Self {
+            elapsed:        Duration::default(),
+            fixed_timestep: Duration::from_secs(1) / steps_per_sec.max(1),
+            accumulator:    Duration::default(),
+            time_scale:     1.0,
+            paused:         false,
+            pending_steps:  0,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the simulation time since the start of the game
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn fixed_timestep(&self) -> Duration {
+        self.fixed_timestep
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes the clock, dropping any single steps still pending
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.pending_steps = 0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.paused {
+            self.resume();
+        }
+        else {
+            self.pause();
+        }
+    }
+
+    /// Sets the time scale, clamped to the allowed range
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = time_scale.max(MIN_TIME_SCALE).min(MAX_TIME_SCALE);
+    }
+
+    /// Requests a single fixed step. Only has an effect while paused.
+    pub fn step(&mut self) {
+        if self.paused {
+            self.pending_steps += 1;
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Feeds in the wall time since the last frame, scaled by the time scale.
+    /// Ignored while paused.
+    pub fn advance(&mut self, wall_delta: Duration) {
+        if self.paused {
+            return;
+        }
+
+        self.accumulator += wall_delta.mul_f64(self.time_scale);
+        self.accumulator = self.accumulator.min(self.fixed_timestep * MAX_STEPS_PER_ADVANCE);
+    }
+
+    /// Consumes one fixed step, if one is due, advancing the simulation time.
+    /// Call repeatedly until it returns false, running one simulation update per step.
+    pub fn next_step(&mut self) -> bool {
+        if self.paused {
+            if self.pending_steps == 0 {
+                return false;
+            }
+            self.pending_steps -= 1;
+        }
+        else if self.accumulator >= self.fixed_timestep {
+            self.accumulator -= self.fixed_timestep;
+        }
+        else {
+            return false;
+        }
+
+        self.elapsed += self.fixed_timestep;
+
+        true
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(100);
+
+    /// Runs every step due, returning how many there were
+    fn run_steps(clock: &mut GameClock) -> u32 {
+        let mut steps = 0;
+        while clock.next_step() {
+            steps += 1;
+        }
+
+        steps
+    }
+
+    #[test]
+    fn runs_one_step_per_fixed_timestep() {
+        let mut clock = GameClock::new(10);
+        assert_eq!(clock.fixed_timestep(), STEP);
+
+        // Leftover time carries over to the next advance
+        clock.advance(Duration::from_millis(250));
+        assert_eq!(run_steps(&mut clock), 2);
+        clock.advance(Duration::from_millis(50));
+        assert_eq!(run_steps(&mut clock), 1);
+
+        assert_eq!(clock.elapsed(), STEP * 3);
+    }
+
+    #[test]
+    fn long_stall_is_clamped() {
+        let mut clock = GameClock::new(10);
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(run_steps(&mut clock), MAX_STEPS_PER_ADVANCE);
+
+        // The excess is dropped rather than caught up on later
+        assert_eq!(run_steps(&mut clock), 0);
+        assert_eq!(clock.elapsed(), STEP * MAX_STEPS_PER_ADVANCE);
+    }
+
+    #[test]
+    fn paused_clock_only_runs_requested_steps() {
+        let mut clock = GameClock::new(10);
+        clock.pause();
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(run_steps(&mut clock), 0);
+
+        clock.step();
+        clock.step();
+        assert_eq!(run_steps(&mut clock), 2);
+        assert_eq!(clock.elapsed(), STEP * 2);
+    }
+
+    #[test]
+    fn resuming_drops_pending_steps() {
+        let mut clock = GameClock::new(10);
+        clock.pause();
+        clock.step();
+        clock.toggle_pause();
+
+        assert!(!clock.paused());
+        assert_eq!(run_steps(&mut clock), 0);
+
+        // Steps requested while running are ignored too
+        clock.step();
+        assert_eq!(run_steps(&mut clock), 0);
+    }
+
+    #[test]
+    fn time_scale_speeds_up_and_slows_down_steps() {
+        let mut clock = GameClock::new(10);
+
+        clock.set_time_scale(2.0);
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(run_steps(&mut clock), 2);
+
+        clock.set_time_scale(0.5);
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(run_steps(&mut clock), 0);
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(run_steps(&mut clock), 1);
+    }
+
+    #[test]
+    fn time_scale_is_clamped() {
+        let mut clock = GameClock::new(10);
+
+        clock.set_time_scale(100.0);
+        assert_eq!(clock.time_scale(), MAX_TIME_SCALE);
+        clock.set_time_scale(0.0);
+        assert_eq!(clock.time_scale(), MIN_TIME_SCALE);
+    }
+}
@@ -0,0 +1,610 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/actor_ai.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module provides utility-scored actor AI. Each actor holds a set of
+    behaviors; every time it thinks, each behavior scores how much it wants
+    to act on what the actor perceives, and the highest-scoring one decides.
+
+    Built-in behaviors are Wander, SeekResource, FleeWeather and Attack. Any
+    other type implementing Behavior can be assigned alongside them.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::fmt;
+
+use cast_iron::{
+    coords,
+    element::Element,
+};
+
+use rand::{
+    rngs::StdRng,
+    Rng,
+};
+
+use crate::game_managers::{
+    elemental_reserve::{
+        self,
+        ElementalReserve,
+    },
+    hex_distance,
+    weather_script::parse_element,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Behaviors given to actors unless configured otherwise
+pub const DEFAULT_BEHAVIORS:        &str = "wander,seek,flee,attack";
+
+/// Behavior sets a selected actor can be switched between, for tuning behaviors in isolation
+pub const BEHAVIOR_PRESETS:         [&str; 5] = [DEFAULT_BEHAVIORS, "wander", "wander,seek", "wander,flee", "wander,attack"];
+
+/// Default score of wandering, the fallback when nothing else appeals
+const DEFAULT_WANDER_SCORE:         f64 = 0.1;
+
+/// Highest scores of seeking a resource, while walking to it and once harvesting it
+const MAX_SEEK_SCORE:               f64 = 0.5;
+const MAX_HARVEST_SCORE:            f64 = 0.6;
+
+/// Default fraction of max intensity above which weather is worth fleeing
+const DEFAULT_FLEE_THRESHOLD:       f64 = 0.25;
+
+/// Default range (in cells) within which enemies are pursued
+const DEFAULT_ATTACK_RANGE:         usize = 6;
+
+/// Scores of attacking an adjacent enemy, and of closing on one further away
+const ATTACK_SCORE:                 f64 = 0.8;
+const PURSUE_SCORE:                 f64 = 0.7;
+
+/// Fraction of max health below which actors stop picking fights
+const MIN_ATTACK_HEALTH_FRACTION:   f64 = 0.25;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// What an actor knows about its surroundings when it thinks
+#[derive(Clone, Debug)]
+pub struct Perception {
+    pub position:           coords::Position,
+    pub faction:            usize,
+    pub health_fraction:    f64,
+    pub reserve:            ElementalReserve,
+    pub weather_here:       f64,                            // Regional weather, as a fraction of max intensity
+    pub open_cells:         Vec<(coords::Position, f64)>,   // Adjacent cells the actor can step into, with their weather
+    pub actors:             Vec<SeenActor>,                 // Every other active actor the actor's faction can see
+    pub resources:          Vec<SeenResource>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SeenActor {
+    pub idx:        usize,
+    pub position:   coords::Position,
+    pub faction:    usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SeenResource {
+    pub origin:     coords::Position,
+    pub radius:     usize,
+    pub element:    Element,
+    pub level:      f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Stay,
+    Harvest,                                // From the resource covering the actor's cell
+    MoveTo(coords::Position),               // An adjacent cell
+    MoveToward(coords::Position, usize),    // A distant cell, to be approached within the given distance
+    Attack(usize),                          // Index of the target actor
+}
+
+/// The outcome of an actor thinking, kept for the debug overlay
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decision {
+    pub behavior:   &'static str,
+    pub score:      f64,
+    pub action:     Action,
+    pub detail:     String,
+}
+
+/// Meanders between open cells
+#[derive(Clone, Debug)]
+pub struct Wander {
+    pub score: f64,
+}
+
+/// Heads for the nearest non-depleted resource of the given element (or any, if None) and stays on it
+/// while the actor's reserve of that element has room
+#[derive(Clone, Debug)]
+pub struct SeekResource {
+    pub element: Option<Element>,
+}
+
+/// Steps away from regional weather stronger than the threshold (a fraction of max intensity)
+#[derive(Clone, Debug)]
+pub struct FleeWeather {
+    pub threshold: f64,
+}
+
+/// Pursues and attacks the nearest actor of another faction within range
+#[derive(Clone, Debug)]
+pub struct Attack {
+    pub range: usize,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Declarations
+///////////////////////////////////////////////////////////////////////////////
+
+pub trait Behavior: fmt::Debug {
+    /// Short name shown in logs and the debug overlay
+    fn name(&self) -> &'static str;
+
+    /// Scores (from 0.0 to 1.0) how much the actor wants to act on this behavior right now, and how.
+    /// Returns None if the behavior doesn't apply. Any randomness is drawn from the given RNG.
+    fn evaluate(&mut self, perception: &Perception, rng: &mut StdRng) -> Option<Decision>;
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl Decision {
+    pub fn new(behavior: &'static str, score: f64, action: Action, detail: String) -> Self {
+        Self {
+            behavior,
+            score,
+            action,
+            detail,
+        }
+    }
+
+    /// The decision made when no behavior applies
+    pub fn idle() -> Self {
+        Self::new("Idle", 0.0, Action::Stay, String::new())
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for Wander {
+    fn default() -> Self {
+        Self {score: DEFAULT_WANDER_SCORE}
+    }
+}
+
+impl Default for FleeWeather {
+    fn default() -> Self {
+        Self {threshold: DEFAULT_FLEE_THRESHOLD}
+    }
+}
+
+impl Default for Attack {
+    fn default() -> Self {
+        Self {range: DEFAULT_ATTACK_RANGE}
+    }
+}
+
+impl Behavior for Wander {
+    fn name(&self) -> &'static str {
+        "Wander"
+    }
+
+    fn evaluate(&mut self, perception: &Perception, rng: &mut StdRng) -> Option<Decision> {
+        if perception.open_cells.is_empty() {
+            return None;
+        }
+
+        let (cell, _weather) = perception.open_cells[rng.gen_range(0, perception.open_cells.len())];
+        Some(Decision::new(self.name(), self.score, Action::MoveTo(cell), format!("to {}", cell)))
+    }
+}
+
+impl Behavior for SeekResource {
+    fn name(&self) -> &'static str {
+        "SeekResource"
+    }
+
+    fn evaluate(&mut self, perception: &Perception, _rng: &mut StdRng) -> Option<Decision> {
+        // Find the nearest resource worth harvesting, by distance to its edge
+        let target = perception.resources.iter()
+            .filter(|resource| resource.level > 0.0 &&
+                               self.element.map_or(true, |element| element == resource.element) &&
+                               reserve_need(&perception.reserve, resource.element) > 0.0)
+            .min_by_key(|resource| hex_distance(&perception.position, &resource.origin).saturating_sub(resource.radius))?;
+
+        let need = reserve_need(&perception.reserve, target.element);
+        let element_str = String::from(target.element);
+
+        if hex_distance(&perception.position, &target.origin) <= target.radius {
+            Some(Decision::new(self.name(), MAX_HARVEST_SCORE * need, Action::Harvest, format!("harvesting {}", element_str)))
+        }
+        else {
+            Some(Decision::new(self.name(),
+                               MAX_SEEK_SCORE * need,
+                               Action::MoveToward(target.origin, target.radius),
+                               format!("{} at {}", element_str, target.origin)))
+        }
+    }
+}
+
+impl Behavior for FleeWeather {
+    fn name(&self) -> &'static str {
+        "FleeWeather"
+    }
+
+    fn evaluate(&mut self, perception: &Perception, _rng: &mut StdRng) -> Option<Decision> {
+        if perception.weather_here <= self.threshold {
+            return None;
+        }
+
+        // Step into the calmest adjacent cell, or hunker down if none is calmer
+        let calmest = perception.open_cells.iter()
+            .filter(|(_cell, weather)| *weather < perception.weather_here)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let action = calmest.map_or(Action::Stay, |(cell, _weather)| Action::MoveTo(*cell));
+        Some(Decision::new(self.name(),
+                           perception.weather_here,
+                           action,
+                           format!("from {:.0}% weather", 100.0 * perception.weather_here)))
+    }
+}
+
+impl Behavior for Attack {
+    fn name(&self) -> &'static str {
+        "Attack"
+    }
+
+    fn evaluate(&mut self, perception: &Perception, _rng: &mut StdRng) -> Option<Decision> {
+        if perception.health_fraction < MIN_ATTACK_HEALTH_FRACTION {
+            return None;
+        }
+
+        let (target, distance) = perception.actors.iter()
+            .filter(|actor| actor.faction != perception.faction)
+            .map(|actor| (actor, hex_distance(&perception.position, &actor.position)))
+            .filter(|(_actor, distance)| *distance <= self.range)
+            .min_by_key(|(_actor, distance)| *distance)?;
+
+        if distance <= 1 {
+            Some(Decision::new(self.name(), ATTACK_SCORE, Action::Attack(target.idx), format!("hitting #{}", target.idx)))
+        }
+        else {
+            Some(Decision::new(self.name(), PURSUE_SCORE, Action::MoveToward(target.position, 1), format!("chasing #{}", target.idx)))
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Evaluates every behavior, returning the highest-scoring decision
+pub fn choose(behaviors: &mut [Box<dyn Behavior>], perception: &Perception, rng: &mut StdRng) -> Decision {
+    behaviors.iter_mut()
+        .filter_map(|behavior| behavior.evaluate(perception, rng))
+        .fold(Decision::idle(), |best, decision| if decision.score > best.score { decision } else { best })
+}
+
+/// Parses a comma-separated list of behaviors, e.g. "wander,seek:water,flee,attack".
+/// Seek takes an optional element, flee an optional threshold (percent) and attack an optional range.
+pub fn parse_behaviors(spec: &str) -> Result<Vec<Box<dyn Behavior>>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            let mut parts = token.splitn(2, ':');
+            let name = parts.next().unwrap_or_default().to_lowercase();
+            let arg = parts.next();
+
+            let behavior: Box<dyn Behavior> = match (name.as_str(), arg) {
+                ("wander", None)    => Box::new(Wander::default()),
+                ("seek", None)      => Box::new(SeekResource {element: None}),
+                ("seek", Some(arg)) => Box::new(SeekResource {element: Some(parse_element(arg).ok_or(format!("unknown element '{}'", arg))?)}),
+                ("flee", None)      => Box::new(FleeWeather::default()),
+                ("flee", Some(arg)) => Box::new(FleeWeather {threshold: arg.parse::<f64>().map_err(|e| format!("invalid flee threshold '{}': {}", arg, e))? / 100.0}),
+                ("attack", None)    => Box::new(Attack::default()),
+                ("attack", Some(arg)) => Box::new(Attack {range: arg.parse::<usize>().map_err(|e| format!("invalid attack range '{}': {}", arg, e))?}),
+                _ => return Err(format!("unknown behavior '{}'", token)),
+            };
+
+            Ok(behavior)
+        })
+        .collect()
+}
+
+/// Returns the preset following the given behavior spec, or the first preset if the spec isn't one
+pub fn next_preset(spec: &str) -> &'static str {
+    match BEHAVIOR_PRESETS.iter().position(|preset| *preset == spec) {
+        Some(idx) => BEHAVIOR_PRESETS[(idx + 1) % BEHAVIOR_PRESETS.len()],
+        None => BEHAVIOR_PRESETS[0],
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the room left in a reserve for the given element, as a fraction of its max
+fn reserve_need(reserve: &ElementalReserve, element: Element) -> f64 {
+    1.0 - reserve.amount(element) / elemental_reserve::MAX_RESERVE_PER_ELEMENT
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cast_iron::context::Context as CastIronContext;
+
+    use crate::game_managers::{
+        seeded_rng,
+        test_helpers::{
+            pos,
+            test_ctx,
+        },
+    };
+
+    /// An unhurt actor of faction 0 at the origin, in calm weather, with nothing around it
+    fn perception(ci_ctx: &CastIronContext) -> Perception {
+        Perception {
+            position:           pos(0, 0, 0, ci_ctx),
+            faction:            0,
+            health_fraction:    1.0,
+            reserve:            ElementalReserve::default(),
+            weather_here:       0.0,
+            open_cells:         Vec::new(),
+            actors:             Vec::new(),
+            resources:          Vec::new(),
+        }
+    }
+
+    fn resource(origin: coords::Position, radius: usize, element: Element, level: f64) -> SeenResource {
+        SeenResource {origin, radius, element, level}
+    }
+
+    #[test]
+    fn wander_needs_an_open_cell() {
+        let ci_ctx = test_ctx();
+        let mut rng = seeded_rng(1, 0);
+        let mut seen = perception(&ci_ctx);
+
+        assert_eq!(Wander::default().evaluate(&seen, &mut rng), None);
+
+        let cell = pos(1, -1, 0, &ci_ctx);
+        seen.open_cells.push((cell, 0.0));
+        let decision = Wander::default().evaluate(&seen, &mut rng).unwrap();
+        assert_eq!(decision.action, Action::MoveTo(cell));
+        assert_eq!(decision.score, DEFAULT_WANDER_SCORE);
+    }
+
+    #[test]
+    fn seek_harvests_within_radius_and_moves_toward_otherwise() {
+        let ci_ctx = test_ctx();
+        let mut rng = seeded_rng(1, 0);
+        let mut seen = perception(&ci_ctx);
+        let mut seek = SeekResource {element: None};
+
+        let origin = pos(1, -1, 0, &ci_ctx);
+        seen.resources.push(resource(origin, 1, Element::Water, 1.0));
+        let decision = seek.evaluate(&seen, &mut rng).unwrap();
+        assert_eq!(decision.action, Action::Harvest);
+        assert_eq!(decision.score, MAX_HARVEST_SCORE);
+
+        let origin = pos(4, -4, 0, &ci_ctx);
+        seen.resources[0] = resource(origin, 2, Element::Water, 1.0);
+        let decision = seek.evaluate(&seen, &mut rng).unwrap();
+        assert_eq!(decision.action, Action::MoveToward(origin, 2));
+        assert_eq!(decision.score, MAX_SEEK_SCORE);
+    }
+
+    #[test]
+    fn seek_scales_with_need_and_skips_what_it_cannot_use() {
+        let ci_ctx = test_ctx();
+        let mut rng = seeded_rng(1, 0);
+        let mut seen = perception(&ci_ctx);
+
+        let origin = pos(0, 0, 0, &ci_ctx);
+        seen.resources.push(resource(origin, 0, Element::Fire, 1.0));
+
+        // Half full
+        seen.reserve.add(Element::Fire, elemental_reserve::MAX_RESERVE_PER_ELEMENT / 2.0);
+        let decision = SeekResource {element: None}.evaluate(&seen, &mut rng).unwrap();
+        assert!((decision.score - MAX_HARVEST_SCORE / 2.0).abs() < 1e-9);
+
+        // Wrong element
+        assert_eq!(SeekResource {element: Some(Element::Ice)}.evaluate(&seen, &mut rng), None);
+
+        // Full reserve
+        seen.reserve.add(Element::Fire, elemental_reserve::MAX_RESERVE_PER_ELEMENT);
+        assert_eq!(SeekResource {element: None}.evaluate(&seen, &mut rng), None);
+
+        // Depleted resource
+        let mut seen = perception(&ci_ctx);
+        seen.resources.push(resource(origin, 0, Element::Fire, 0.0));
+        assert_eq!(SeekResource {element: None}.evaluate(&seen, &mut rng), None);
+    }
+
+    #[test]
+    fn seek_prefers_the_nearest_edge() {
+        let ci_ctx = test_ctx();
+        let mut rng = seeded_rng(1, 0);
+        let mut seen = perception(&ci_ctx);
+
+        // The second resource's origin is farther, but its edge is nearer
+        let near_origin = pos(3, -3, 0, &ci_ctx);
+        let wide_origin = pos(-5, 5, 0, &ci_ctx);
+        seen.resources.push(resource(near_origin, 0, Element::Earth, 1.0));
+        seen.resources.push(resource(wide_origin, 4, Element::Earth, 1.0));
+
+        let decision = SeekResource {element: None}.evaluate(&seen, &mut rng).unwrap();
+        assert_eq!(decision.action, Action::MoveToward(wide_origin, 4));
+    }
+
+    #[test]
+    fn flee_applies_only_above_threshold() {
+        let ci_ctx = test_ctx();
+        let mut rng = seeded_rng(1, 0);
+        let mut seen = perception(&ci_ctx);
+        let mut flee = FleeWeather::default();
+
+        seen.weather_here = DEFAULT_FLEE_THRESHOLD;
+        assert_eq!(flee.evaluate(&seen, &mut rng), None);
+
+        seen.weather_here = 0.9;
+        let decision = flee.evaluate(&seen, &mut rng).unwrap();
+        assert_eq!(decision.action, Action::Stay);
+        assert_eq!(decision.score, 0.9);
+    }
+
+    #[test]
+    fn flee_steps_into_the_calmest_cell() {
+        let ci_ctx = test_ctx();
+        let mut rng = seeded_rng(1, 0);
+        let mut seen = perception(&ci_ctx);
+
+        let calm = pos(0, 1, -1, &ci_ctx);
+        seen.weather_here = 0.8;
+        seen.open_cells = vec![(pos(1, -1, 0, &ci_ctx), 0.9), (calm, 0.2), (pos(-1, 1, 0, &ci_ctx), 0.5)];
+
+        let decision = FleeWeather::default().evaluate(&seen, &mut rng).unwrap();
+        assert_eq!(decision.action, Action::MoveTo(calm));
+
+        // Nowhere calmer
+        seen.open_cells = vec![(pos(1, -1, 0, &ci_ctx), 0.9)];
+        let decision = FleeWeather::default().evaluate(&seen, &mut rng).unwrap();
+        assert_eq!(decision.action, Action::Stay);
+    }
+
+    #[test]
+    fn attack_hits_adjacent_and_pursues_distant_enemies() {
+        let ci_ctx = test_ctx();
+        let mut rng = seeded_rng(1, 0);
+        let mut seen = perception(&ci_ctx);
+        let mut attack = Attack::default();
+
+        let far = pos(4, -4, 0, &ci_ctx);
+        seen.actors.push(SeenActor {idx: 3, position: far, faction: 1});
+        let decision = attack.evaluate(&seen, &mut rng).unwrap();
+        assert_eq!(decision.action, Action::MoveToward(far, 1));
+        assert_eq!(decision.score, PURSUE_SCORE);
+
+        seen.actors.push(SeenActor {idx: 5, position: pos(1, 0, -1, &ci_ctx), faction: 2});
+        let decision = attack.evaluate(&seen, &mut rng).unwrap();
+        assert_eq!(decision.action, Action::Attack(5));
+        assert_eq!(decision.score, ATTACK_SCORE);
+    }
+
+    #[test]
+    fn attack_ignores_allies_and_enemies_out_of_range() {
+        let ci_ctx = test_ctx();
+        let mut rng = seeded_rng(1, 0);
+        let mut seen = perception(&ci_ctx);
+
+        seen.actors.push(SeenActor {idx: 1, position: pos(1, -1, 0, &ci_ctx), faction: 0});
+        seen.actors.push(SeenActor {idx: 2, position: pos(7, -7, 0, &ci_ctx), faction: 1});
+        assert_eq!(Attack::default().evaluate(&seen, &mut rng), None);
+
+        let decision = Attack {range: 7}.evaluate(&seen, &mut rng).unwrap();
+        assert_eq!(decision.action, Action::MoveToward(pos(7, -7, 0, &ci_ctx), 1));
+    }
+
+    #[test]
+    fn attack_needs_health() {
+        let ci_ctx = test_ctx();
+        let mut rng = seeded_rng(1, 0);
+        let mut seen = perception(&ci_ctx);
+
+        seen.actors.push(SeenActor {idx: 1, position: pos(1, -1, 0, &ci_ctx), faction: 1});
+        seen.health_fraction = MIN_ATTACK_HEALTH_FRACTION;
+        assert!(Attack::default().evaluate(&seen, &mut rng).is_some());
+
+        seen.health_fraction = MIN_ATTACK_HEALTH_FRACTION - 0.01;
+        assert_eq!(Attack::default().evaluate(&seen, &mut rng), None);
+    }
+
+    #[test]
+    fn choose_takes_the_highest_score() {
+        let ci_ctx = test_ctx();
+        let mut rng = seeded_rng(1, 0);
+        let mut seen = perception(&ci_ctx);
+        let mut behaviors = parse_behaviors(DEFAULT_BEHAVIORS).unwrap();
+
+        // Nothing applies
+        assert_eq!(choose(&mut behaviors, &seen, &mut rng), Decision::idle());
+
+        // Wander beats idling, seek beats wander, attack beats seek
+        seen.open_cells.push((pos(1, -1, 0, &ci_ctx), 0.0));
+        assert_eq!(choose(&mut behaviors, &seen, &mut rng).behavior, "Wander");
+
+        seen.resources.push(resource(pos(0, 0, 0, &ci_ctx), 0, Element::Light, 1.0));
+        assert_eq!(choose(&mut behaviors, &seen, &mut rng).action, Action::Harvest);
+
+        seen.actors.push(SeenActor {idx: 7, position: pos(0, 1, -1, &ci_ctx), faction: 1});
+        assert_eq!(choose(&mut behaviors, &seen, &mut rng).action, Action::Attack(7));
+
+        // Strong enough weather beats everything
+        seen.weather_here = 0.95;
+        assert_eq!(choose(&mut behaviors, &seen, &mut rng).behavior, "FleeWeather");
+    }
+
+    #[test]
+    fn parse_behaviors_reads_arguments() {
+        let behaviors = parse_behaviors(" Wander, seek:water ,flee:50,attack:3,").unwrap();
+        let names: Vec<&str> = behaviors.iter().map(|behavior| behavior.name()).collect();
+        assert_eq!(names, vec!["Wander", "SeekResource", "FleeWeather", "Attack"]);
+
+        assert!(parse_behaviors("").unwrap().is_empty());
+        for preset in BEHAVIOR_PRESETS.iter() {
+            assert!(parse_behaviors(preset).is_ok(), "preset '{}' should parse", preset);
+        }
+    }
+
+    #[test]
+    fn parse_behaviors_rejects_bad_specs() {
+        assert_eq!(parse_behaviors("wander,dance").unwrap_err(), "unknown behavior 'dance'");
+        assert_eq!(parse_behaviors("wander:fast").unwrap_err(), "unknown behavior 'wander:fast'");
+        assert_eq!(parse_behaviors("seek:plasma").unwrap_err(), "unknown element 'plasma'");
+        assert!(parse_behaviors("flee:high").unwrap_err().starts_with("invalid flee threshold 'high'"));
+        assert!(parse_behaviors("attack:-1").unwrap_err().starts_with("invalid attack range '-1'"));
+    }
+
+    #[test]
+    fn next_preset_cycles() {
+        assert_eq!(next_preset(BEHAVIOR_PRESETS[0]), BEHAVIOR_PRESETS[1]);
+        assert_eq!(next_preset(BEHAVIOR_PRESETS[BEHAVIOR_PRESETS.len() - 1]), BEHAVIOR_PRESETS[0]);
+        assert_eq!(next_preset("seek:fire"), BEHAVIOR_PRESETS[0]);
+    }
+}
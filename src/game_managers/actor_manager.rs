@@ -18,10 +18,36 @@ Purpose:
     This module manages all active actors (both PCs and NPCs) in the game, as
     well as providing utility methods for drawing, moving, etc.
 
+    Actors harvest from any resource whose area covers them, either on demand
+    or automatically each step, filling their per-element reserves. Attacks
+    spend from the attacker's dominant reserve, if it can afford to, for bonus
+    damage.
+
+    Each actor also thinks periodically with its assigned AI behaviors, moving
+    about the grid and attacking actors of other factions.
+
+    Actors are drawn in their faction's color with a facing indicator, health
+    bar and name label, and the selected actor is ringed.
+
+    Each faction's visibility is recomputed whenever its actors move, with
+    obstacles blocking sight. Actors only perceive the actors they can see.
+
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
+use std::{
+    fmt,
+    time::Duration,
+};
+
 use cast_iron::{
     actor::Actor,
+    context::Context as CastIronContext,
+    coords,
+    element::{
+        Element,
+        Elemental,
+    },
+    hex_directions,
     Plottable,
 };
 
@@ -36,22 +62,136 @@ use mt_logger::{
     Level,
 };
 
+use rand::{
+    rngs::StdRng,
+    SeedableRng,
+};
+
 use crate::{
     game_assets::{
         colors,
         hex_grid_cell::HexGridCell,
     },
-    game_managers::DrawableMechanic,
+    game_clock::GameClock,
+    game_managers::{
+        DrawableError,
+        DrawableMechanic,
+        actor_ai::{
+            self,
+            Action,
+            Behavior,
+            Decision,
+            Perception,
+            SeenActor,
+            SeenResource,
+        },
+        elemental_reserve::{
+            self,
+            ElementalReserve,
+        },
+        faction::{
+            self,
+            Faction,
+        },
+        hex_distance,
+        neighbours,
+        seeded_rng,
+        obstacle_manager::ObstacleManager,
+        pathfinding,
+        resource_manager::ResourceManager,
+        visibility::{
+            self,
+            VisibilityMap,
+        },
+        weather_manager::WeatherManager,
+        world_grid_manager::WorldGridManager,
+    },
+    profiler::{
+        self,
+        metrics_format::EventAttribute,
+    },
 };
 
 
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Default rate (in levels per second) at which actors automatically harvest
+const DEFAULT_HARVEST_RATE:     f64 = 0.1;
+
+/// Amount (in levels) taken by a single manual harvest
+pub const MANUAL_HARVEST_AMOUNT: f64 = 0.25;
+
+/// Steps in the width of an actor's reserve ring, from empty to a full reserve
+const RESERVE_RING_STEPS:       f64 = 4.0;
+
+/// Game time between an actor's decisions, multiplied by the movement cost of the cell it last stepped into
+const THINK_INTERVAL:           Duration = Duration::from_millis(500);
+
+/// Extra path cost of stepping through a cell another actor is standing in
+const OCCUPIED_CELL_PENALTY:    u32 = 4;
+
+/// Health of an unharmed actor, and the damage done by each attack
+pub const MAX_HEALTH:           f64 = 100.0;
+const ATTACK_DAMAGE:            f64 = 10.0;
+
+/// Reserve spent from the attacker's dominant element to empower an attack, and the extra damage it does
+const EMPOWERED_ATTACK_COST:    f64 = 0.5;
+const EMPOWERED_ATTACK_DAMAGE:  f64 = 10.0;
+
+/// Size of the text in the AI debug overlay
+const AI_OVERLAY_TEXT_SIZE:     f32 = 12.0;
+
+/// Size of actors' name labels
+const NAME_LABEL_TEXT_SIZE:     f32 = 12.0;
+
+/// Health bar dimensions, and its gap above the actor
+const HEALTH_BAR_WIDTH:         f32 = crate::HEX_RADIUS_VERTEX;
+const HEALTH_BAR_HEIGHT:        f32 = 4.0;
+const HEALTH_BAR_GAP:           f32 = 3.0;
+
+/// Fractions of max health below which the health bar turns yellow, then red
+const HEALTH_WARNING_FRACTION:  f64 = 0.5;
+const HEALTH_DANGER_FRACTION:   f64 = 0.25;
+
+/// Salt mixed into the run's seed for the AI's RNG
+const AI_SEED_SALT:             u64 = 0x4143_544f_5253;
+
+
 ///////////////////////////////////////////////////////////////////////////////
 //  Data Structures
 ///////////////////////////////////////////////////////////////////////////////
 
 pub struct ActorManager {
-    actors:     Vec<Actor>,
-    actor_mesh: ggez_gfx::Mesh,
+    profiler:       profiler::Instance,
+    actors:         Vec<Actor>,
+    reserves:       Vec<ElementalReserve>,  // Parallel to actors
+    agents:         Vec<Agent>,             // Parallel to actors
+    auto_harvest:   bool,
+    harvest_rate:   f64,                    // Levels per second
+    default_behaviors: String,              // Behavior spec given to newly-added actors
+    factions:       Vec<Faction>,
+    visibility:     Vec<VisibilityMap>,     // Parallel to factions
+    visibility_dirty: bool,                 // Flag indicating an actor has moved since visibility was computed
+    sight_range:    usize,                  // Cells
+    selected:       Option<usize>,          // Index of the selected actor
+    ai_overlay:     bool,
+    rng:            StdRng,                 // Drives the AI's random choices
+    actor_mesh:     ggez_gfx::Mesh,
+}
+
+/// Per-actor state the game tracks alongside each cast_iron Actor
+#[derive(Debug)]
+struct Agent {
+    position:   coords::Position,           // Current cell, starting at the actor's origin
+    facing:     hex_directions::Side,
+    faction:    usize,                      // Index into the manager's factions
+    health:     f64,
+    behavior_spec: String,                  // Spec the behaviors were parsed from
+    behaviors:  Vec<Box<dyn Behavior>>,
+    decision:   Decision,                   // Most recent decision, for the debug overlay
+    next_think: Duration,                   // Game time of the next decision
 }
 
 #[derive(Debug)]
@@ -64,14 +204,598 @@ pub struct ActorError;
 
 impl ActorManager {
     /// Generic Constructor - creates an empty instance
-    pub fn new(ggez_ctx: &mut GgEzContext) -> Self {
+    pub fn new(profiler_original: &profiler::Instance, ggez_ctx: &mut GgEzContext) -> Self {
         ActorManager {
-            actors:     Vec::new(),
-            actor_mesh: ggez_gfx::Mesh::new_line(ggez_ctx,
-                                                 &[ggez_mint::Point2 {x: 0.0, y: 0.0}, ggez_mint::Point2 {x: 10.0, y: 10.0}],
-                                                crate::DEFAULT_LINE_WIDTH,
-                                                crate::DEFAULT_LINE_COLOR)
-                                                 .unwrap(),
+            profiler:       profiler_original.clone(),
+            actors:         Vec::new(),
+            reserves:       Vec::new(),
+            agents:         Vec::new(),
+            auto_harvest:   false,
+            harvest_rate:   DEFAULT_HARVEST_RATE,
+            default_behaviors: String::from(actor_ai::DEFAULT_BEHAVIORS),
+            factions:       faction::default_factions(),
+            visibility:     vec![VisibilityMap::default(); faction::DEFAULT_FACTIONS.len()],
+            visibility_dirty: false,
+            sight_range:    visibility::DEFAULT_SIGHT_RANGE,
+            selected:       None,
+            ai_overlay:     false,
+            rng:            StdRng::from_entropy(),
+            actor_mesh:     ggez_gfx::Mesh::new_line(ggez_ctx,
+                                                     &[ggez_mint::Point2 {x: 0.0, y: 0.0}, ggez_mint::Point2 {x: 10.0, y: 10.0}],
+                                                    crate::DEFAULT_LINE_WIDTH,
+                                                    crate::DEFAULT_LINE_COLOR)
+                                                     .unwrap(),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the reserve of the actor at the given index
+    pub fn reserve(&self, actor_idx: usize) -> Option<&ElementalReserve> {
+        self.reserves.get(actor_idx)
+    }
+
+    /// Returns the index of the actor standing on the given cell, if any
+    pub fn actor_idx_at(&self, position: &coords::Position) -> Option<usize> {
+        self.agents.iter().position(|agent| agent.position == *position)
+    }
+
+    /// Returns the cell the actor at the given index is standing on
+    pub fn position(&self, actor_idx: usize) -> Option<coords::Position> {
+        self.agents.get(actor_idx).map(|agent| agent.position)
+    }
+
+    /// Returns the index of the faction the actor at the given index belongs to
+    pub fn faction(&self, actor_idx: usize) -> Option<usize> {
+        self.agents.get(actor_idx).map(|agent| agent.faction)
+    }
+
+    pub fn factions(&self) -> &[Faction] {
+        &self.factions
+    }
+
+    pub fn facing(&self, actor_idx: usize) -> Option<hex_directions::Side> {
+        self.agents.get(actor_idx).map(|agent| agent.facing)
+    }
+
+    /// Returns the cells the given faction's actors can see, and have seen
+    pub fn faction_visibility(&self, faction_idx: usize) -> Option<&VisibilityMap> {
+        self.visibility.get(faction_idx)
+    }
+
+    pub fn sight_range(&self) -> usize {
+        self.sight_range
+    }
+
+    /// Returns true if the actor at the given index is standing, and has line of sight to the given cell within range.
+    /// Sight is blocked by obstacles and by terrain such as forests and mountains.
+    pub fn can_see(&self,
+                   actor_idx: usize,
+                   position: &coords::Position,
+                   obstacle_manager: &ObstacleManager,
+                   world_grid_manager: &WorldGridManager,
+                   ci_ctx: &CastIronContext) -> bool {
+        self.agents.get(actor_idx).map_or(false, |agent| {
+            agent.health > 0.0 &&
+            hex_distance(&agent.position, position) <= self.sight_range &&
+            visibility::line_of_sight(&agent.position,
+                                      position,
+                                      |cell| obstacle_manager.blocks(cell) || world_grid_manager.blocks_sight(cell),
+                                      ci_ctx)
+        })
+    }
+
+    /// Returns the index of the selected actor, if any
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Returns the indices of standing actors of the same faction within the given range of an actor, nearest first
+    pub fn allies_within(&self, actor_idx: usize, range: usize) -> Vec<usize> {
+        self.standing_within(actor_idx, range, |faction, other_faction| faction == other_faction)
+    }
+
+    /// Returns the indices of standing actors of other factions within the given range of an actor, nearest first
+    pub fn enemies_within(&self, actor_idx: usize, range: usize) -> Vec<usize> {
+        self.standing_within(actor_idx, range, |faction, other_faction| faction != other_faction)
+    }
+
+    pub fn health(&self, actor_idx: usize) -> Option<f64> {
+        self.agents.get(actor_idx).map(|agent| agent.health)
+    }
+
+    /// Returns the spec of the behaviors assigned to the actor at the given index, e.g. "wander,seek:water"
+    pub fn behavior_spec(&self, actor_idx: usize) -> Option<&str> {
+        self.agents.get(actor_idx).map(|agent| agent.behavior_spec.as_str())
+    }
+
+    /// Returns the names of the behaviors assigned to the actor at the given index
+    pub fn behavior_names(&self, actor_idx: usize) -> Vec<&'static str> {
+        self.agents.get(actor_idx)
+            .map(|agent| agent.behaviors.iter().map(|behavior| behavior.name()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the most recent decision of the actor at the given index
+    pub fn decision(&self, actor_idx: usize) -> Option<&Decision> {
+        self.agents.get(actor_idx).map(|agent| &agent.decision)
+    }
+
+    pub fn ai_overlay_visible(&self) -> bool {
+        self.ai_overlay
+    }
+
+    pub fn auto_harvest(&self) -> bool {
+        self.auto_harvest
+    }
+
+    pub fn harvest_rate(&self) -> f64 {
+        self.harvest_rate
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Reseeds the AI's RNG from the run's seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = seeded_rng(seed, AI_SEED_SALT);
+    }
+
+    pub fn set_auto_harvest(&mut self, auto_harvest: bool) {
+        self.auto_harvest = auto_harvest;
+    }
+
+    pub fn set_harvest_rate(&mut self, harvest_rate: f64) {
+        self.harvest_rate = harvest_rate.max(0.0);
+    }
+
+    /// Sets the behaviors given to actors added from now on, e.g. "wander,seek:water,flee"
+    pub fn set_default_behaviors(&mut self, spec: &str) -> Result<(), String> {
+        actor_ai::parse_behaviors(spec)?;
+        self.default_behaviors = String::from(spec);
+
+        Ok(())
+    }
+
+    /// Replaces the behaviors of the actor at the given index, e.g. with "wander,seek:water,flee".
+    /// The actor rethinks on its next update.
+    pub fn set_behaviors(&mut self, actor_idx: usize, spec: &str) -> Result<(), String> {
+        let behaviors = actor_ai::parse_behaviors(spec)?;
+        let agent = self.agents.get_mut(actor_idx).ok_or(format!("no actor at index {}", actor_idx))?;
+
+        agent.behavior_spec = String::from(spec);
+        agent.behaviors = behaviors;
+        agent.decision = Decision::idle();
+        agent.next_think = Duration::default();
+
+        Ok(())
+    }
+
+    /// Adds the given actor standing on the given cell rather than its origin, e.g. in a spawn zone
+    pub fn spawn_instance(&mut self,
+                          actor: Actor,
+                          position: coords::Position,
+                          ggez_ctx: &mut GgEzContext) -> Result<(), DrawableError> {
+        if self.actor_idx_at(&position).is_some() {
+            return Err(DrawableError::CoordinatesOccupied(position));
+        }
+
+        self.push_instance(actor);
+        self.agents.last_mut().unwrap().position = position;
+        self.update_mesh(ggez_ctx);
+
+        Ok(())
+    }
+
+    /// Moves the actor at the given index into the faction at the given index, if both exist
+    pub fn set_faction(&mut self, actor_idx: usize, faction_idx: usize, ggez_ctx: &mut GgEzContext) {
+        if faction_idx < self.factions.len() {
+            if let Some(agent) = self.agents.get_mut(actor_idx) {
+                agent.faction = faction_idx;
+                self.visibility_dirty = true;
+                self.update_mesh(ggez_ctx);
+            }
+        }
+    }
+
+    /// Selects the actor at the given index, or clears the selection
+    pub fn select(&mut self, actor_idx: Option<usize>, ggez_ctx: &mut GgEzContext) {
+        let actor_idx = actor_idx.filter(|idx| *idx < self.actors.len());
+
+        if actor_idx != self.selected {
+            self.selected = actor_idx;
+            self.update_mesh(ggez_ctx);
+        }
+    }
+
+    pub fn set_sight_range(&mut self, sight_range: usize) {
+        self.sight_range = sight_range;
+        self.visibility_dirty = true;
+    }
+
+    pub fn toggle_ai_overlay(&mut self) {
+        self.ai_overlay = !self.ai_overlay;
+    }
+
+    /// Pays the given cost from an actor's reserve of the given element, e.g. to empower an attack.
+    /// Returns false if the actor can't afford it. Redrawing the actor's reserve ring is left to the caller.
+    pub fn spend_reserve(&mut self, actor_idx: usize, element: Element, cost: f64) -> bool {
+        self.reserves.get_mut(actor_idx).map_or(false, |reserve| reserve.spend(element, cost))
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Harvests up to the given amount for the actor at the given index, from the resource covering it.
+    /// Returns the element and amount harvested, if anything was.
+    pub fn harvest(&mut self,
+                   actor_idx: usize,
+                   amount: f64,
+                   resource_manager: &mut ResourceManager,
+                   ggez_ctx: &mut GgEzContext) -> Option<(Element, f64)> {
+        let harvested = self.harvest_quietly(actor_idx, amount, resource_manager);
+
+        if let Some((element, harvested_amount)) = harvested {
+            self.profiler.mark_event_with_attributes(
+                "ACTOR_HARVEST",
+                || vec![EventAttribute::new("actor", self.actors[actor_idx].name().to_string()),
+                        EventAttribute::new("position", self.agents[actor_idx].position.to_string()),
+                        EventAttribute::new("element", String::from(element)),
+                        EventAttribute::new("amount", format!("{:.3}", harvested_amount)),
+                        EventAttribute::new("reserve", format!("{:.3}", self.reserves[actor_idx].amount(element)))],
+                ggez_ctx).unwrap();
+
+            self.update_mesh(ggez_ctx);
+        }
+
+        harvested
+    }
+
+    /// Lets each actor that is due to think decide what to do, and carries out its decision
+    pub fn update_ai(&mut self,
+                     clock: &GameClock,
+                     resource_manager: &mut ResourceManager,
+                     obstacle_manager: &ObstacleManager,
+                     weather_manager: &WeatherManager,
+                     world_grid_manager: &WorldGridManager,
+                     ci_ctx: &CastIronContext,
+                     ggez_ctx: &mut GgEzContext) {
+        let elapsed_time = clock.elapsed();
+
+        let mut redraw = false;
+        for actor_idx in 0..self.actors.len() {
+            // Downed actors no longer think
+            if self.agents[actor_idx].health <= 0.0 || elapsed_time < self.agents[actor_idx].next_think {
+                continue;
+            }
+            self.agents[actor_idx].next_think = elapsed_time + THINK_INTERVAL;
+
+            let perception = self.perceive(actor_idx, resource_manager, obstacle_manager, weather_manager, world_grid_manager, ci_ctx);
+            let decision = actor_ai::choose(&mut self.agents[actor_idx].behaviors, &perception, &mut self.rng);
+
+            if decision.behavior != self.agents[actor_idx].decision.behavior {
+                mt_log!(Level::Debug, "{} switched from {} to {} ({})",
+                    self.actors[actor_idx].name(),
+                    self.agents[actor_idx].decision.behavior,
+                    decision.behavior,
+                    decision.detail);
+            }
+
+            match decision.action {
+                Action::Stay => {},
+                Action::Harvest => {
+                    // Automatic harvesting already takes care of actors standing on a resource
+                    if !self.auto_harvest {
+                        let ring_before = reserve_ring(&self.reserves[actor_idx]);
+                        let amount = self.harvest_rate * THINK_INTERVAL.as_secs_f64();
+
+                        if self.harvest_quietly(actor_idx, amount, resource_manager).is_some() {
+                            redraw |= reserve_ring(&self.reserves[actor_idx]) != ring_before;
+                        }
+                    }
+                },
+                Action::MoveTo(cell) => {
+                    redraw |= self.step_to(actor_idx, cell, elapsed_time, world_grid_manager);
+                },
+                Action::MoveToward(target, within) => {
+                    if let Some(cell) = self.next_step(actor_idx, &target, within, obstacle_manager, world_grid_manager, ci_ctx) {
+                        redraw |= self.step_to(actor_idx, cell, elapsed_time, world_grid_manager);
+                    }
+                },
+                Action::Attack(target_idx) => {
+                    self.attack(actor_idx, target_idx, ggez_ctx);
+                    redraw = true;
+                },
+            }
+
+            self.agents[actor_idx].decision = decision;
+        }
+
+        if redraw {
+            self.update_mesh(ggez_ctx);
+        }
+    }
+
+    /// Recomputes each faction's visibility from its standing actors, if any have moved.
+    /// Returns true if visibility was recomputed.
+    pub fn update_visibility(&mut self,
+                             obstacle_manager: &ObstacleManager,
+                             world_grid_manager: &WorldGridManager,
+                             ci_ctx: &CastIronContext) -> bool {
+        if !self.visibility_dirty {
+            return false;
+        }
+
+        for (faction_idx, faction_visibility) in self.visibility.iter_mut().enumerate() {
+            let viewers = self.agents.iter()
+                .filter(|agent| agent.faction == faction_idx && agent.health > 0.0)
+                .map(|agent| &agent.position);
+
+            faction_visibility.update(viewers,
+                                      self.sight_range,
+                                      |cell| obstacle_manager.blocks(cell) || world_grid_manager.blocks_sight(cell),
+                                      ci_ctx);
+        }
+
+        self.visibility_dirty = false;
+        true
+    }
+
+    /// Draws each actor's current decision next to it, with a line to where it's headed or who it's attacking.
+    /// The selected actor's label also lists its behaviors, and the allies and enemies within its sight range.
+    pub fn draw_ai_overlay(&self, ggez_ctx: &mut GgEzContext) {
+        if !self.ai_overlay {
+            return;
+        }
+
+        let mut line_builder = ggez_gfx::MeshBuilder::new();
+        let mut has_lines = false;
+
+        for (actor_idx, agent) in self.agents.iter().enumerate() {
+            let center = HexGridCell::new_from_hex_coords(&agent.position, crate::HEX_RADIUS_VERTEX, ggez_ctx).center();
+
+            let target = match agent.decision.action {
+                Action::Stay                        => None,
+                Action::Harvest                     => None,
+                Action::MoveTo(cell)                => Some((cell, colors::YELLOW)),
+                Action::MoveToward(cell, _within)   => Some((cell, colors::YELLOW)),
+                Action::Attack(target_idx)          => self.position(target_idx).map(|cell| (cell, colors::RED)),
+            };
+
+            if let Some((cell, color)) = target {
+                let target_center = HexGridCell::new_from_hex_coords(&cell, crate::HEX_RADIUS_VERTEX, ggez_ctx).center();
+                line_builder.line(&[center, target_center], crate::DEFAULT_LINE_WIDTH, color).unwrap();
+                has_lines = true;
+            }
+
+            let mut label = format!("{} {:.2}\n{}", agent.decision.behavior, agent.decision.score, agent.decision.detail);
+            if self.selected == Some(actor_idx) {
+                label.push_str(&format!("\n[{}]\n{} allies, {} enemies nearby",
+                                        self.behavior_names(actor_idx).join(", "),
+                                        self.allies_within(actor_idx, self.sight_range).len(),
+                                        self.enemies_within(actor_idx, self.sight_range).len()));
+            }
+            let label_text = ggez_gfx::Text::new((label.as_str(), ggez_gfx::Font::default(), AI_OVERLAY_TEXT_SIZE));
+            let label_pos = ggez_mint::Point2 {x: center.x + crate::HEX_RADIUS_VERTEX / 2.0,
+                                               y: center.y - crate::HEX_RADIUS_VERTEX};
+            ggez_gfx::draw(ggez_ctx, &label_text, (label_pos, 0.0, colors::WHITE)).unwrap();
+        }
+
+        // Building an empty mesh fails, so only draw lines if there are some
+        if has_lines {
+            let line_mesh = line_builder.build(ggez_ctx).unwrap();
+            ggez_gfx::draw(ggez_ctx, &line_mesh, ggez_gfx::DrawParam::default()).unwrap();
+        }
+    }
+
+    /// Advances automatic harvesting by one step of the game clock, if enabled
+    pub fn update_harvesting(&mut self,
+                             clock: &GameClock,
+                             resource_manager: &mut ResourceManager,
+                             ggez_ctx: &mut GgEzContext) {
+        if !self.auto_harvest {
+            return;
+        }
+
+        let amount = self.harvest_rate * clock.fixed_timestep().as_secs_f64();
+
+        // Only redraw when an actor's reserve ring would visibly change
+        let mut ring_changed = false;
+        for actor_idx in 0..self.actors.len() {
+            let ring_before = reserve_ring(&self.reserves[actor_idx]);
+
+            if self.harvest_quietly(actor_idx, amount, resource_manager).is_some() {
+                ring_changed |= reserve_ring(&self.reserves[actor_idx]) != ring_before;
+            }
+        }
+
+        if ring_changed {
+            self.update_mesh(ggez_ctx);
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Helper Methods    *
+     *  *  *  *  *  *  *  */
+
+    /// Moves up to the given amount from the resource covering an actor into its reserve,
+    /// without marking or redrawing. Only what the reserve can hold is taken from the resource.
+    fn harvest_quietly(&mut self,
+                       actor_idx: usize,
+                       amount: f64,
+                       resource_manager: &mut ResourceManager) -> Option<(Element, f64)> {
+        let position = self.agents.get(actor_idx)?.position;
+        let element = resource_manager.resource_at(&position)?.element();
+
+        let room = elemental_reserve::MAX_RESERVE_PER_ELEMENT - self.reserves[actor_idx].amount(element);
+        let harvested = resource_manager.harvest(&position, amount.min(room));
+        if harvested <= 0.0 {
+            return None;
+        }
+
+        mt_log!(Level::Trace, "{} harvested {:.3} {} at {}", self.actors[actor_idx].name(), harvested, String::from(element), position);
+        self.reserves[actor_idx].add(element, harvested);
+
+        Some((element, harvested))
+    }
+
+    /// Returns the first cell along the cheapest path from an actor to within the given distance of the target,
+    /// avoiding obstacles and impassable terrain, and steering around other actors where it can
+    fn next_step(&self,
+                 actor_idx: usize,
+                 target: &coords::Position,
+                 within: usize,
+                 obstacle_manager: &ObstacleManager,
+                 world_grid_manager: &WorldGridManager,
+                 ci_ctx: &CastIronContext) -> Option<coords::Position> {
+        let cost = |cell: &coords::Position| -> Option<u32> {
+            if obstacle_manager.blocks(cell) {
+                return None;
+            }
+
+            let terrain_cost = world_grid_manager.movement_cost(cell)?;
+            match self.actor_idx_at(cell) {
+                Some(_other_idx) => Some(terrain_cost + OCCUPIED_CELL_PENALTY),
+                None => Some(terrain_cost),
+            }
+        };
+
+        let path = pathfinding::find_path(&self.agents[actor_idx].position, target, within, cost, ci_ctx)?;
+        path.get(1).copied()
+    }
+
+    /// Steps an actor into an adjacent cell, if it's still open, delaying its next decision by the cell's
+    /// movement cost. Returns true if the actor moved.
+    fn step_to(&mut self,
+               actor_idx: usize,
+               cell: coords::Position,
+               elapsed_time: Duration,
+               world_grid_manager: &WorldGridManager) -> bool {
+        // Another actor may have stepped in since this one perceived the cell as open
+        let movement_cost = match world_grid_manager.movement_cost(&cell) {
+            Some(movement_cost) if self.actor_idx_at(&cell).is_none() => movement_cost,
+            _ => return false,
+        };
+
+        let agent = &mut self.agents[actor_idx];
+        agent.facing = hex_directions::Side::from(agent.position.delta_to(&cell));
+        agent.position = cell;
+        agent.next_think = elapsed_time + THINK_INTERVAL * movement_cost;
+        self.visibility_dirty = true;
+
+        true
+    }
+
+    /// Gathers what the actor at the given index can see of the grid, its neighbours, resources and weather
+    fn perceive(&self,
+                actor_idx: usize,
+                resource_manager: &ResourceManager,
+                obstacle_manager: &ObstacleManager,
+                weather_manager: &WeatherManager,
+                world_grid_manager: &WorldGridManager,
+                ci_ctx: &CastIronContext) -> Perception {
+        let agent = &self.agents[actor_idx];
+
+        // Regional weather, as a fraction of max intensity
+        let weather_fraction = |cell: &coords::Position| -> f64 {
+            weather_manager.weather_at(cell).iter()
+                .map(|cell_weather| cell_weather.intensity_exact() / ci_ctx.max_weather_intensity())
+                .sum::<f64>()
+                .min(1.0)
+        };
+
+        Perception {
+            position:           agent.position,
+            faction:            agent.faction,
+            health_fraction:    agent.health / MAX_HEALTH,
+            reserve:            self.reserves[actor_idx].clone(),
+            weather_here:       weather_fraction(&agent.position),
+            open_cells:         neighbours(&agent.position, ci_ctx).into_iter()
+                                    .filter(|cell| !obstacle_manager.blocks(cell) &&
+                                                   world_grid_manager.movement_cost(cell).is_some() &&
+                                                   self.actor_idx_at(cell).is_none())
+                                    .map(|cell| (cell, weather_fraction(&cell)))
+                                    .collect(),
+            actors:             self.agents.iter()
+                                    .enumerate()
+                                    .filter(|(other_idx, other)| *other_idx != actor_idx && other.health > 0.0)
+                                    .filter(|(_other_idx, other)| self.can_see(actor_idx, &other.position, obstacle_manager, world_grid_manager, ci_ctx))
+                                    .map(|(other_idx, other)| SeenActor {idx: other_idx, position: other.position, faction: other.faction})
+                                    .collect(),
+            resources:          resource_manager.iter_lifecycles()
+                                    .map(|(resource, lifecycle)| SeenResource {origin:  *resource.origin(),
+                                                                               radius:  resource.radius(),
+                                                                               element: resource.element(),
+                                                                               level:   lifecycle.level()})
+                                    .collect(),
+        }
+    }
+
+    /// Returns the indices of standing actors within the given range of an actor, whose factions pass the given
+    /// test against the actor's, nearest first
+    fn standing_within<F>(&self, actor_idx: usize, range: usize, faction_test: F) -> Vec<usize>
+    where F: Fn(usize, usize) -> bool {
+        let agent = match self.agents.get(actor_idx) {
+            Some(agent) => agent,
+            None => return Vec::new(),
+        };
+
+        let mut in_range: Vec<(usize, usize)> = self.agents.iter()
+            .enumerate()
+            .filter(|(other_idx, other)| *other_idx != actor_idx &&
+                                         other.health > 0.0 &&
+                                         faction_test(agent.faction, other.faction))
+            .map(|(other_idx, other)| (other_idx, hex_distance(&agent.position, &other.position)))
+            .filter(|(_other_idx, distance)| *distance <= range)
+            .collect();
+        in_range.sort_by_key(|(_other_idx, distance)| *distance);
+
+        in_range.into_iter().map(|(other_idx, _distance)| other_idx).collect()
+    }
+
+    /// Deals one attack's damage from one actor to another, if the target is a standing enemy still adjacent to it.
+    /// The attack is empowered by the attacker's dominant element, if it can afford to.
+    fn attack(&mut self, attacker_idx: usize, target_idx: usize, ggez_ctx: &mut GgEzContext) {
+        if !self.enemies_within(attacker_idx, 1).contains(&target_idx) {
+            return;
+        }
+        let attacker_pos = self.agents[attacker_idx].position;
+
+        // Empower the attack with the attacker's dominant element, if it can afford to
+        let empowered_by = self.reserves[attacker_idx].dominant()
+            .map(|(element, _amount)| element)
+            .filter(|element| self.spend_reserve(attacker_idx, *element, EMPOWERED_ATTACK_COST));
+        let damage = if empowered_by.is_some() { ATTACK_DAMAGE + EMPOWERED_ATTACK_DAMAGE } else { ATTACK_DAMAGE };
+
+        let target = &mut self.agents[target_idx];
+        target.health = (target.health - damage).max(0.0);
+        let target_health = target.health;
+        let target_pos = target.position;
+
+        // Turn to face the target
+        self.agents[attacker_idx].facing = hex_directions::Side::from(attacker_pos.delta_to(&target_pos));
+
+        self.profiler.mark_event_with_attributes(
+            "ACTOR_ATTACK",
+            || vec![EventAttribute::new("attacker", self.actors[attacker_idx].name().to_string()),
+                    EventAttribute::new("target", self.actors[target_idx].name().to_string()),
+                    EventAttribute::new("damage", format!("{:.1}", damage)),
+                    EventAttribute::new("element", empowered_by.map_or(String::from("None"), String::from)),
+                    EventAttribute::new("health", format!("{:.1}", target_health))],
+            ggez_ctx).unwrap();
+
+        if target_health <= 0.0 {
+            // Downed actors no longer see for their faction
+            self.visibility_dirty = true;
+            mt_log!(Level::Info, "{} was downed by {}", self.actors[target_idx].name(), self.actors[attacker_idx].name());
         }
     }
 }
@@ -90,12 +814,28 @@ impl DrawableMechanic for ActorManager {
         &self.actors
     }
 
+    fn profiler(&self) -> &profiler::Instance {
+        &self.profiler
+    }
+
     fn push_instance(&mut self, instance: Self::Instance) {
         mt_log!(Level::Debug,
             "Adding actor: {} at {} to mesh.",
             instance.name(),
             instance.origin());
 
+        self.reserves.push(ElementalReserve::default());
+        self.visibility_dirty = true;
+        self.agents.push(Agent {
+            position:   *instance.origin(),
+            facing:     hex_directions::Side::North,
+            faction:    self.agents.len() % self.factions.len().max(1),
+            health:     MAX_HEALTH,
+            behavior_spec: self.default_behaviors.clone(),
+            behaviors:  actor_ai::parse_behaviors(&self.default_behaviors).unwrap(),
+            decision:   Decision::idle(),
+            next_think: Duration::default(),
+        });
         self.actors.push(instance);
     }
 
@@ -103,19 +843,122 @@ impl DrawableMechanic for ActorManager {
         &self.actor_mesh
     }
 
+    /// Draws the actor mesh, then each actor's name above it (text can't be part of a mesh)
+    fn draw(&self, ggez_ctx: &mut GgEzContext) {
+        ggez_gfx::draw(ggez_ctx, self.mesh(), ggez_gfx::DrawParam::default()).unwrap();
+
+        for (actor, agent) in self.actors.iter().zip(self.agents.iter()) {
+            let center = HexGridCell::new_from_hex_coords(&agent.position, crate::HEX_RADIUS_VERTEX, ggez_ctx).center();
+
+            let name_text = ggez_gfx::Text::new((actor.name(), ggez_gfx::Font::default(), NAME_LABEL_TEXT_SIZE));
+            let name_width = name_text.width(ggez_ctx) as f32;
+            let name_pos = ggez_mint::Point2 {x: center.x - name_width / 2.0,
+                                              y: center.y + crate::HEX_RADIUS_VERTEX / 2.0 + HEALTH_BAR_GAP};
+            ggez_gfx::draw(ggez_ctx, &name_text, (name_pos, 0.0, colors::WHITE)).unwrap();
+        }
+    }
+
     fn set_mesh(&mut self, mesh: ggez_gfx::Mesh) {
         self.actor_mesh = mesh;
     }
 
-    fn add_instance_to_mesh_builder(instance: &Self::Instance,
+    fn add_instance_to_mesh_builder(&self,
+                                    instance_idx: usize,
+                                    _instance: &Self::Instance,
                                     mesh_builder: &mut ggez_gfx::MeshBuilder,
                                     ggez_ctx: &mut GgEzContext) -> Result<(),Self::ErrorType> {
+        // Actors move away from their origins, so draw them where they currently stand
+        let agent = &self.agents[instance_idx];
+
         // Create a HexGridCell object and add it to the mesh builder
-        let actor_hex = HexGridCell::new_from_hex_coords(instance.origin(),crate::HEX_RADIUS_VERTEX, ggez_ctx);
-        
-        // Draw green circle to represent the actor
-        mesh_builder.circle(ggez_gfx::DrawMode::fill(), actor_hex.center(),crate::HEX_RADIUS_VERTEX/2.0, 1.0, colors::GREEN);
+        let actor_hex = HexGridCell::new_from_hex_coords(&agent.position,crate::HEX_RADIUS_VERTEX, ggez_ctx);
+        let center = actor_hex.center();
+
+        // Draw a circle in the actor's faction color (grey once downed) to represent the actor
+        let body_color = if agent.health > 0.0 {
+            self.factions.get(agent.faction).map_or(colors::GREEN, Faction::color)
+        }
+        else {
+            colors::GREY
+        };
+        mesh_builder.circle(ggez_gfx::DrawMode::fill(), center,crate::HEX_RADIUS_VERTEX/2.0, 1.0, body_color);
+
+        // Point from the center toward the side the actor is facing
+        let (vertex_a, vertex_b) = hex_directions::Side::get_adjacent_vertices(agent.facing);
+        let side_a = actor_hex.vertices()[usize::from(vertex_a)];
+        let side_b = actor_hex.vertices()[usize::from(vertex_b)];
+        let facing_point = ggez_mint::Point2 {x: center.x + ((side_a.x + side_b.x) / 2.0 - center.x) * 0.6,
+                                              y: center.y + ((side_a.y + side_b.y) / 2.0 - center.y) * 0.6};
+        mesh_builder.line(&[center, facing_point], crate::DEFAULT_LINE_WIDTH, colors::WHITE).unwrap();
+
+        // Draw the health bar above the actor, filled in proportion to its health
+        let health_fraction = agent.health / MAX_HEALTH;
+        let bar_left = center.x - HEALTH_BAR_WIDTH / 2.0;
+        let bar_top = center.y - crate::HEX_RADIUS_VERTEX / 2.0 - HEALTH_BAR_GAP - HEALTH_BAR_HEIGHT;
+        let health_color = if health_fraction < HEALTH_DANGER_FRACTION {
+            colors::RED
+        }
+        else if health_fraction < HEALTH_WARNING_FRACTION {
+            colors::YELLOW
+        }
+        else {
+            colors::GREEN
+        };
+
+        mesh_builder.polygon(ggez_gfx::DrawMode::fill(),
+                             &bar_corners(bar_left, bar_top, HEALTH_BAR_WIDTH),
+                             colors::DARKGREY).unwrap();
+        if health_fraction > 0.0 {
+            mesh_builder.polygon(ggez_gfx::DrawMode::fill(),
+                                 &bar_corners(bar_left, bar_top, HEALTH_BAR_WIDTH * health_fraction as f32),
+                                 health_color).unwrap();
+        }
+
+        // Ring the selected actor
+        if Some(instance_idx) == self.selected {
+            mesh_builder.circle(ggez_gfx::DrawMode::stroke(crate::DEFAULT_LINE_WIDTH),
+                                center,
+                                crate::HEX_RADIUS_VERTEX * 0.8,
+                                1.0,
+                                colors::WHITE);
+        }
+
+        // Ring the actor in the color of its largest reserve, thicker the more it holds
+        if let Some((element, steps)) = reserve_ring(&self.reserves[instance_idx]) {
+            mesh_builder.circle(ggez_gfx::DrawMode::stroke(steps as f32),
+                                center,
+                                crate::HEX_RADIUS_VERTEX/2.0 + steps as f32 / 2.0,
+                                1.0,
+                                colors::from_element(element));
+        }
 
         Ok(())
     }
 }
+
+impl fmt::Display for ActorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid actor")
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the element and width (in steps) of the ring drawn for a reserve, if anything is held
+fn reserve_ring(reserve: &ElementalReserve) -> Option<(Element, u32)> {
+    reserve.dominant().map(|(element, amount)| {
+        let fraction = amount / elemental_reserve::MAX_RESERVE_PER_ELEMENT;
+        (element, (fraction * RESERVE_RING_STEPS).ceil() as u32)
+    })
+}
+
+/// Returns the corners of a health bar of the given width, with its top-left corner at the given point
+fn bar_corners(left: f32, top: f32, width: f32) -> [ggez_mint::Point2<f32>; 4] {
+    [ggez_mint::Point2 {x: left,            y: top},
+     ggez_mint::Point2 {x: left + width,    y: top},
+     ggez_mint::Point2 {x: left + width,    y: top + HEALTH_BAR_HEIGHT},
+     ggez_mint::Point2 {x: left,            y: top + HEALTH_BAR_HEIGHT}]
+}
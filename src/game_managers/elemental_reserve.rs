@@ -0,0 +1,127 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/elemental_reserve.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module provides an actor's reserve of harvested resources, held per
+    element, from which ability casts can be paid for.
+
+    Amounts are in the same units as resource levels, i.e. fractions of a
+    full resource.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use cast_iron::element::Element;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Most an actor can hold of any one element
+pub const MAX_RESERVE_PER_ELEMENT: f64 = 3.0;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ElementalReserve {
+    amounts: Vec<(Element, f64)>,   // Only elements that have been harvested at least once
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl ElementalReserve {
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the amount held of the given element
+    pub fn amount(&self, element: Element) -> f64 {
+        self.amounts.iter()
+            .find(|(held_element, _)| *held_element == element)
+            .map_or(0.0, |(_, amount)| *amount)
+    }
+
+    /// Returns the amount held of every element, in the order they were first harvested
+    pub fn amounts(&self) -> &[(Element, f64)] {
+        &self.amounts
+    }
+
+    /// Returns the element held in the greatest amount, and that amount, if anything is held
+    pub fn dominant(&self) -> Option<(Element, f64)> {
+        self.amounts.iter()
+            .filter(|(_, amount)| *amount > 0.0)
+            .fold(None, |dominant: Option<(Element, f64)>, &(element, amount)| {
+                match dominant {
+                    Some((_, dominant_amount)) if dominant_amount >= amount => dominant,
+                    _ => Some((element, amount)),
+                }
+            })
+    }
+
+    /// Returns true if the given cost of the given element can be paid
+    pub fn can_afford(&self, element: Element, cost: f64) -> bool {
+        self.amount(element) >= cost
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Adds up to the given amount of the given element, returning the amount actually stored
+    pub fn add(&mut self, element: Element, amount: f64) -> f64 {
+        let held = self.entry(element);
+        let stored = amount.max(0.0).min(MAX_RESERVE_PER_ELEMENT - *held);
+        *held += stored;
+
+        stored
+    }
+
+    /// Pays the given cost from the given element's reserve, if there is enough.
+    /// Returns false, leaving the reserve untouched, if there isn't.
+    pub fn spend(&mut self, element: Element, cost: f64) -> bool {
+        if !self.can_afford(element, cost) {
+            return false;
+        }
+
+        *self.entry(element) -= cost.max(0.0);
+        true
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Helper Methods    *
+     *  *  *  *  *  *  *  */
+
+    fn entry(&mut self, element: Element) -> &mut f64 {
+        let idx = match self.amounts.iter().position(|(held_element, _)| *held_element == element) {
+            Some(idx) => idx,
+            None => {
+                self.amounts.push((element, 0.0));
+                self.amounts.len() - 1
+            }
+        };
+
+        &mut self.amounts[idx].1
+    }
+}
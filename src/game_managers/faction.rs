@@ -0,0 +1,87 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/faction.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module defines the factions actors belong to. Actors of the same
+    faction are allies, and actors of different factions are enemies.
+
+    Faction colors are kept clear of the element colors, so actors can be
+    told apart from the resources and obstacles around them.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use ggez::graphics as ggez_gfx;
+
+use crate::game_assets::colors;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Factions every game starts with
+pub const DEFAULT_FACTIONS: [(&str, ggez_gfx::Color); 2] = [
+    ("Orange",  colors::ORANGE),
+    ("Violet",  colors::VIOLET),
+];
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Faction {
+    name:   String,
+    color:  ggez_gfx::Color,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl Faction {
+    pub fn new(name: &str, color: ggez_gfx::Color) -> Self {
+        Self {
+            name:   String::from(name),
+            color,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn color(&self) -> ggez_gfx::Color {
+        self.color
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the factions every game starts with
+pub fn default_factions() -> Vec<Faction> {
+    DEFAULT_FACTIONS.iter().map(|(name, color)| Faction::new(name, *color)).collect()
+}
@@ -0,0 +1,768 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/map_generator.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module generates the world map from a seed. Seeded noise over the
+    hex grid gives a heightmap and a moisture map, from which the pipeline's
+    stages run in order:
+        1. Terrain:     water in the lows, mountains on the peaks, and forest
+                        or sand by moisture in between
+        2. Resources:   each element where it makes sense, e.g. Water on low
+                        ground and Earth on the mountains
+        3. Ridges:      obstacles following the high ground
+        4. Spawns:      actors gathered in open zones, far apart
+
+    Each stage has its own settings, and can be left out. Each stage also
+    draws from its own seeded RNG, so the same seed always gives the same map,
+    and leaving a stage out doesn't disturb the stages after it.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::collections::HashMap;
+
+use cast_iron::{
+    actor::Actor,
+    context::Context as CastIronContext,
+    coords,
+    element::Element,
+    mechanics::{
+        obstacle::Obstacle,
+        resource::{
+            Resource,
+            State,
+        },
+    },
+};
+
+use ggez::Context as GgEzContext;
+
+use mt_logger::{
+    mt_log,
+    Level,
+};
+
+use rand::Rng;
+
+use crate::{
+    game_assets::terrain::Terrain,
+    game_managers::{
+        DrawableMechanic,
+        actor_manager::ActorManager,
+        hex_distance,
+        neighbours,
+        obstacle_manager::ObstacleManager,
+        resource_manager::ResourceManager,
+        seeded_rng,
+        world_grid_manager::WorldGridManager,
+    },
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Stages run unless configured otherwise
+pub const DEFAULT_STAGES:   &str = "terrain,resources,ridges,spawns";
+
+/// Fraction of the highest cells a ridge may start from
+const RIDGE_START_FRACTION: f64 = 0.5;
+
+/// Syllables the names of spawned actors are built from
+const NAME_SYLLABLES:       [&str; 12] = ["ka", "lo", "mir", "an", "tes", "ru", "vel", "do", "sha", "en", "ti", "gor"];
+
+/// Salts mixed into the seed, so each layer and stage gets its own noise or RNG
+const HEIGHT_SALT:          u64 = 0x4845_4947_4854;
+const MOISTURE_SALT:        u64 = 0x4d4f_4953_5400;
+const RESOURCES_SALT:       u64 = 0x5245_534f_5552;
+const RIDGES_SALT:          u64 = 0x5249_4447_4553;
+const SPAWNS_SALT:          u64 = 0x5350_4157_4e53;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Settings for a layer of fractal value noise
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoiseSettings {
+    pub scale:          f64,    // Cells across a feature of the coarsest octave
+    pub octaves:        usize,
+    pub persistence:    f64,    // Amplitude of each octave relative to the one before
+}
+
+/// Settings for the terrain stage. Levels are fractions of the map's range of height or moisture.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TerrainSettings {
+    pub height:         NoiseSettings,
+    pub moisture:       NoiseSettings,
+    pub water_level:    f64,    // Height below which cells are Water
+    pub mountain_level: f64,    // Height above which cells are Mountain
+    pub dry_level:      f64,    // Moisture below which cells are Sand
+    pub wet_level:      f64,    // Moisture above which cells are Forest
+}
+
+/// Settings for the resources stage
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceSettings {
+    pub elements:           Vec<Element>,   // One resource of each, in order
+    pub max_radius:         usize,
+    pub min_spacing:        usize,          // Cells between resource origins
+    pub candidate_fraction: f64,            // Fraction of best-suited cells a resource may be placed on
+}
+
+/// Settings for the ridges stage
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RidgeSettings {
+    pub count:          usize,
+    pub min_length:     usize,
+    pub max_length:     usize,
+    pub ridge_level:    f64,    // Height a ridge must start above, and stay above
+}
+
+/// Settings for the spawns stage
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpawnSettings {
+    pub actor_count:    usize,
+    pub zone_count:     usize,  // Actors are dealt into zones in turn, as factions are
+    pub clearance:      usize,  // Cells around a zone's center that must be open
+}
+
+/// The heightmap and moisture map of the grid, each normalized to [0.0, 1.0]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MapLayers {
+    cells:      Vec<coords::Position>,
+    height:     HashMap<coords::Position, f64>,
+    moisture:   HashMap<coords::Position, f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapGenerator {
+    seed:       u64,
+    terrain:    TerrainSettings,
+    resources:  ResourceSettings,
+    ridges:     RidgeSettings,
+    spawns:     SpawnSettings,
+    stages:     Vec<Stage>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Terrain,
+    Resources,
+    Ridges,
+    Spawns,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl MapLayers {
+    /// Samples both layers over every cell of the grid
+    pub fn new(seed: u64, settings: &TerrainSettings, world_grid_manager: &WorldGridManager) -> Self {
+        Self::from_cells(seed, settings, world_grid_manager.cell_positions())
+    }
+
+    /// Samples both layers over the given cells, kept in the given order
+    pub fn from_cells(seed: u64, settings: &TerrainSettings, cells: Vec<coords::Position>) -> Self {
+        let sample = |salt: u64, noise: &NoiseSettings| -> HashMap<coords::Position, f64> {
+            let samples = cells.iter()
+                .map(|cell| {
+                    let (x, y) = plane_coords(cell);
+                    (*cell, fractal_noise(seed ^ salt, noise, x, y))
+                })
+                .collect();
+
+            normalize(samples)
+        };
+
+        Self {
+            height:     sample(HEIGHT_SALT, &settings.height),
+            moisture:   sample(MOISTURE_SALT, &settings.moisture),
+            cells,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    /// Returns every cell of the grid, in a fixed order
+    pub fn cells(&self) -> &[coords::Position] {
+        &self.cells
+    }
+
+    pub fn height_at(&self, position: &coords::Position) -> f64 {
+        self.height.get(position).copied().unwrap_or_default()
+    }
+
+    pub fn moisture_at(&self, position: &coords::Position) -> f64 {
+        self.moisture.get(position).copied().unwrap_or_default()
+    }
+}
+
+impl MapGenerator {
+    /// Creates a generator that runs every stage with its default settings
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            terrain:    TerrainSettings::default(),
+            resources:  ResourceSettings::default(),
+            ridges:     RidgeSettings::default(),
+            spawns:     SpawnSettings::default(),
+            stages:     parse_stages(DEFAULT_STAGES).unwrap(),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn runs(&self, stage: Stage) -> bool {
+        self.stages.contains(&stage)
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Sets which stages run from a comma-separated list, e.g. "terrain,spawns".
+    /// Stages always run in pipeline order, whatever order they're listed in.
+    pub fn set_stages(&mut self, spec: &str) -> Result<(), String> {
+        self.stages = parse_stages(spec)?;
+        Ok(())
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Samples the heightmap and moisture map the stages build on. These are needed even if the terrain stage doesn't run.
+    pub fn layers(&self, world_grid_manager: &WorldGridManager) -> MapLayers {
+        MapLayers::new(self.seed, &self.terrain, world_grid_manager)
+    }
+
+    /// Returns the terrain a cell gets from its height and moisture
+    pub fn terrain_for(&self, layers: &MapLayers, cell: &coords::Position) -> Terrain {
+        let settings = &self.terrain;
+        let (height, moisture) = (layers.height_at(cell), layers.moisture_at(cell));
+
+        if height < settings.water_level {
+            Terrain::Water
+        }
+        else if height > settings.mountain_level {
+            Terrain::Mountain
+        }
+        else if moisture > settings.wet_level {
+            Terrain::Forest
+        }
+        else if moisture < settings.dry_level {
+            Terrain::Sand
+        }
+        else {
+            Terrain::Plain
+        }
+    }
+
+    /// Paints terrain over the grid from its height and moisture. Returns the number of cells that aren't Plain.
+    pub fn generate_terrain(&self,
+                            layers: &MapLayers,
+                            world_grid_manager: &mut WorldGridManager,
+                            ggez_ctx: &mut GgEzContext) -> usize {
+        if !self.runs(Stage::Terrain) {
+            return 0;
+        }
+
+        let terrain_for = |cell: &coords::Position| self.terrain_for(layers, cell);
+
+        let featured = layers.cells().iter().filter(|cell| terrain_for(cell) != Terrain::Plain).count();
+        world_grid_manager.paint_terrain(terrain_for, ggez_ctx);
+
+        mt_log!(Level::Info, "Generated terrain, {} of {} cells featured", featured, layers.cells().len());
+        featured
+    }
+
+    /// Places a resource of each configured element on a cell suited to it. Returns the number placed.
+    pub fn place_resources(&self,
+                           layers: &MapLayers,
+                           resource_manager: &mut ResourceManager,
+                           ci_ctx: &CastIronContext,
+                           ggez_ctx: &mut GgEzContext) -> usize {
+        if !self.runs(Stage::Resources) {
+            return 0;
+        }
+
+        let existing: Vec<coords::Position> = resource_manager.instances().iter().map(|resource| *resource.origin()).collect();
+
+        let mut placed = 0;
+        for (element, origin, radius) in self.plan_resources(layers, &existing) {
+            if resource_manager.add_instance(Resource::new(element, State::Full, origin, radius), ci_ctx, ggez_ctx).is_ok() {
+                placed += 1;
+            }
+        }
+
+        mt_log!(Level::Info, "Placed {} of {} resources", placed, self.resources.elements.len());
+        placed
+    }
+
+    /// Picks the element, origin and radius of each resource to place, keeping clear of the given existing origins
+    pub fn plan_resources(&self, layers: &MapLayers, existing: &[coords::Position]) -> Vec<(Element, coords::Position, usize)> {
+        let settings = &self.resources;
+        let mut rng = seeded_rng(self.seed, RESOURCES_SALT);
+        let mut origins = existing.to_vec();
+
+        let mut planned = Vec::new();
+        for element in &settings.elements {
+            let candidates: Vec<(coords::Position, f64)> = layers.cells().iter()
+                .filter(|cell| origins.iter().all(|origin| hex_distance(origin, cell) >= settings.min_spacing))
+                .map(|cell| (*cell, placement_score(*element, layers.height_at(cell), layers.moisture_at(cell))))
+                .collect();
+
+            let origin = match pick_top(&mut rng, candidates, settings.candidate_fraction) {
+                Some(origin) => origin,
+                None => {
+                    mt_log!(Level::Warning, "No room left for a {} resource", String::from(*element));
+                    continue;
+                }
+            };
+
+            let radius = rng.gen_range(1, settings.max_radius.max(1) + 1);
+            origins.push(origin);
+            planned.push((*element, origin, radius));
+        }
+
+        planned
+    }
+
+    /// Places obstacles along the high ground, each following the ridge downhill from near a peak.
+    /// Returns the number placed.
+    pub fn place_ridges(&self,
+                        layers: &MapLayers,
+                        obstacle_manager: &mut ObstacleManager,
+                        ci_ctx: &CastIronContext,
+                        ggez_ctx: &mut GgEzContext) -> usize {
+        if !self.runs(Stage::Ridges) {
+            return 0;
+        }
+
+        let mut placed = 0;
+        for ridge in self.plan_ridges(layers, |cell| obstacle_manager.blocks(cell), ci_ctx) {
+            let start = ridge[0];
+            match obstacle_manager.add_instance(Obstacle::new(ridge, Element::Earth), ci_ctx, ggez_ctx) {
+                Ok(()) => placed += 1,
+                Err(e) => mt_log!(Level::Debug, "Dropped a ridge from {}: {}", start, e),
+            }
+        }
+
+        mt_log!(Level::Info, "Placed {} of {} ridges", placed, self.ridges.count);
+        placed
+    }
+
+    /// Traces the cells of each ridge to place, avoiding cells the given test blocks and each other
+    pub fn plan_ridges<F>(&self, layers: &MapLayers, blocks: F, ci_ctx: &CastIronContext) -> Vec<Vec<coords::Position>>
+    where F: Fn(&coords::Position) -> bool {
+        let settings = &self.ridges;
+        let mut rng = seeded_rng(self.seed, RIDGES_SALT);
+
+        let mut ridges: Vec<Vec<coords::Position>> = Vec::new();
+        let is_open = |cell: &coords::Position, ridges: &[Vec<coords::Position>]| -> bool {
+            !blocks(cell) && ridges.iter().all(|ridge| !ridge.contains(cell))
+        };
+
+        for _ridge in 0..settings.count {
+            let starts: Vec<(coords::Position, f64)> = layers.cells().iter()
+                .filter(|cell| layers.height_at(cell) > settings.ridge_level && is_open(cell, &ridges))
+                .map(|cell| (*cell, layers.height_at(cell)))
+                .collect();
+
+            let start = match pick_top(&mut rng, starts, RIDGE_START_FRACTION) {
+                Some(start) => start,
+                None => break,
+            };
+
+            // Follow the highest open neighbour that doesn't touch the ridge behind it, so the ridge stays a line
+            let mut ridge = vec![start];
+            while ridge.len() < settings.max_length {
+                let last = *ridge.last().unwrap();
+                let next = neighbours(&last, ci_ctx).into_iter()
+                    .filter(|cell| layers.height_at(cell) > settings.ridge_level && is_open(cell, &ridges))
+                    .filter(|cell| ridge[..ridge.len() - 1].iter().all(|prev| hex_distance(prev, cell) > 1))
+                    .max_by(|a, b| layers.height_at(a).partial_cmp(&layers.height_at(b)).unwrap());
+
+                match next {
+                    Some(next) => ridge.push(next),
+                    None => break,
+                }
+            }
+
+            if ridge.len() < settings.min_length {
+                mt_log!(Level::Debug, "Dropped a ridge from {} only {} cells long", start, ridge.len());
+                continue;
+            }
+
+            ridges.push(ridge);
+        }
+
+        ridges
+    }
+
+    /// Picks open spawn zones far apart from each other, and gathers actors around them. Returns the number spawned.
+    pub fn place_actors(&self,
+                        layers: &MapLayers,
+                        world_grid_manager: &WorldGridManager,
+                        obstacle_manager: &ObstacleManager,
+                        actor_manager: &mut ActorManager,
+                        ggez_ctx: &mut GgEzContext) -> usize {
+        if !self.runs(Stage::Spawns) {
+            return 0;
+        }
+
+        let settings = &self.spawns;
+        let mut rng = seeded_rng(self.seed, SPAWNS_SALT);
+
+        let is_open = |cell: &coords::Position| -> bool {
+            world_grid_manager.movement_cost(cell).is_some() && !obstacle_manager.blocks(cell)
+        };
+
+        // Zone centers are Plain, with nothing in the way for some distance around them
+        let mut centers: Vec<coords::Position> = layers.cells().iter()
+            .filter(|cell| world_grid_manager.terrain_at(cell) == Some(Terrain::Plain) &&
+                           layers.cells().iter()
+                               .filter(|other| hex_distance(cell, other) <= settings.clearance)
+                               .all(|other| is_open(other)))
+            .copied()
+            .collect();
+        if centers.is_empty() {
+            mt_log!(Level::Warning, "No clear spawn zones, falling back to any open cell");
+            centers = layers.cells().iter().filter(|cell| is_open(cell)).copied().collect();
+        }
+        if centers.is_empty() {
+            return 0;
+        }
+
+        // Start from a random zone, then add whichever is furthest from those already chosen
+        let mut zones = vec![centers[rng.gen_range(0, centers.len())]];
+        while zones.len() < settings.zone_count.max(1) {
+            let furthest = centers.iter()
+                .max_by_key(|center| zones.iter().map(|zone| hex_distance(zone, center)).min().unwrap_or_default())
+                .copied()
+                .unwrap();
+            zones.push(furthest);
+        }
+
+        let mut spawned = 0;
+        for actor_num in 0..settings.actor_count {
+            let zone = zones[actor_num % zones.len()];
+            let position = layers.cells().iter()
+                .filter(|cell| is_open(cell) && actor_manager.actor_idx_at(cell).is_none())
+                .min_by_key(|cell| hex_distance(&zone, cell))
+                .copied();
+
+            match position {
+                Some(position) => {
+                    let actor = Actor::new_name_only(&rand_name(&mut rng));
+                    if actor_manager.spawn_instance(actor, position, ggez_ctx).is_ok() {
+                        spawned += 1;
+                    }
+                },
+                None => break,
+            }
+        }
+
+        mt_log!(Level::Info, "Spawned {} of {} actors in {} zones", spawned, settings.actor_count, zones.len());
+        spawned
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            height:         NoiseSettings {scale: 8.0, octaves: 3, persistence: 0.5},
+            moisture:       NoiseSettings {scale: 5.0, octaves: 2, persistence: 0.5},
+            water_level:    0.25,
+            mountain_level: 0.8,
+            dry_level:      0.3,
+            wet_level:      0.65,
+        }
+    }
+}
+
+impl Default for ResourceSettings {
+    fn default() -> Self {
+        Self {
+            elements:           vec![Element::Water, Element::Earth, Element::Fire],
+            max_radius:         2,
+            min_spacing:        4,
+            candidate_fraction: 0.1,
+        }
+    }
+}
+
+impl Default for RidgeSettings {
+    fn default() -> Self {
+        Self {
+            count:          3,
+            min_length:     3,
+            max_length:     6,
+            ridge_level:    0.6,
+        }
+    }
+}
+
+impl Default for SpawnSettings {
+    fn default() -> Self {
+        Self {
+            actor_count:    4,
+            zone_count:     2,
+            clearance:      1,
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Parses a comma-separated list of stage names, returning them in pipeline order
+pub fn parse_stages(spec: &str) -> Result<Vec<Stage>, String> {
+    let mut stages = Vec::new();
+
+    for name in spec.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        let stage = match name.to_lowercase().as_str() {
+            "terrain"   => Stage::Terrain,
+            "resources" => Stage::Resources,
+            "ridges"    => Stage::Ridges,
+            "spawns"    => Stage::Spawns,
+            _ => return Err(format!("Unknown map generation stage '{}'", name)),
+        };
+
+        if !stages.contains(&stage) {
+            stages.push(stage);
+        }
+    }
+
+    stages.sort_by_key(|stage| *stage as usize);
+    Ok(stages)
+}
+
+/// Returns fractal value noise at the given point, in [0.0, 1.0]
+pub fn fractal_noise(seed: u64, settings: &NoiseSettings, x: f64, y: f64) -> f64 {
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut max_total = 0.0;
+    let mut frequency = 1.0 / settings.scale.max(f64::EPSILON);
+
+    for octave in 0..settings.octaves.max(1) {
+        total += value_noise(seed.wrapping_add(octave as u64), x * frequency, y * frequency) * amplitude;
+        max_total += amplitude;
+
+        amplitude *= settings.persistence;
+        frequency *= 2.0;
+    }
+
+    total / max_total
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the center of a hex cell on a plane where adjacent centers are 1.0 apart
+fn plane_coords(position: &coords::Position) -> (f64, f64) {
+    let (q, r) = (position.x() as f64, position.z() as f64);
+
+    (q + r / 2.0, r * 3.0_f64.sqrt() / 2.0)
+}
+
+/// Smoothly interpolates between the seeded values at the corners of the lattice square around a point
+fn value_noise(seed: u64, x: f64, y: f64) -> f64 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (smoothstep(x - x0), smoothstep(y - y0));
+    let (ix, iy) = (x0 as i64, y0 as i64);
+
+    let top = lerp(lattice_value(seed, ix, iy), lattice_value(seed, ix + 1, iy), tx);
+    let bottom = lerp(lattice_value(seed, ix, iy + 1), lattice_value(seed, ix + 1, iy + 1), tx);
+
+    lerp(top, bottom, ty)
+}
+
+/// Returns the seeded value at a lattice point, in [0.0, 1.0)
+fn lattice_value(seed: u64, ix: i64, iy: i64) -> f64 {
+    let hash = mix(seed ^ mix(ix as u64 ^ mix(iy as u64)));
+
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// SplitMix64 finalizer, scattering the bits of its input
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    z ^ (z >> 31)
+}
+
+fn smoothstep(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Stretches samples to cover [0.0, 1.0], so levels mean the same on any size of grid
+fn normalize(mut samples: HashMap<coords::Position, f64>) -> HashMap<coords::Position, f64> {
+    let min = samples.values().copied().fold(f64::INFINITY, f64::min);
+    let max = samples.values().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    for sample in samples.values_mut() {
+        *sample = if range > f64::EPSILON { (*sample - min) / range } else { 0.5 };
+    }
+
+    samples
+}
+
+/// Returns how well a cell of the given height and moisture suits a resource of the given element
+fn placement_score(element: Element, height: f64, moisture: f64) -> f64 {
+    match element {
+        Element::Water  => 1.0 - height,
+        Element::Earth  => height,
+        Element::Fire   => 1.0 - moisture,
+        Element::Wind   => moisture,
+        _               => 0.5,
+    }
+}
+
+/// Builds a name of two or three random syllables, e.g. "Mirdo"
+fn rand_name<R: Rng>(rng: &mut R) -> String {
+    let syllable_count = rng.gen_range(2, 4);
+    let name: String = (0..syllable_count)
+        .map(|_| NAME_SYLLABLES[rng.gen_range(0, NAME_SYLLABLES.len())])
+        .collect();
+
+    let mut chars = name.chars();
+    chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+}
+
+/// Picks a random cell from the best-scoring fraction of the candidates. Ties keep the candidates' order.
+fn pick_top<R: Rng>(rng: &mut R, mut candidates: Vec<(coords::Position, f64)>, fraction: f64) -> Option<coords::Position> {
+    if candidates.is_empty() {
+        return None;
+    }
+
+    candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    let top_count = ((candidates.len() as f64 * fraction).ceil() as usize).max(1).min(candidates.len());
+
+    Some(candidates[rng.gen_range(0, top_count)].0)
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game_managers::test_helpers::{
+        grid_cells,
+        test_ctx,
+    };
+
+    const TEST_SEED: u64 = 0x5eed;
+
+    /// Everything the seeded stages produce, short of drawing it
+    #[derive(Debug, PartialEq)]
+    struct GeneratedMap {
+        layers:     MapLayers,
+        terrain:    Vec<Terrain>,
+        resources:  Vec<(Element, coords::Position, usize)>,
+        ridges:     Vec<Vec<coords::Position>>,
+    }
+
+    fn generate(seed: u64, ci_ctx: &CastIronContext) -> GeneratedMap {
+        let generator = MapGenerator::new(seed);
+        let layers = MapLayers::from_cells(seed, &TerrainSettings::default(), grid_cells(ci_ctx));
+
+        GeneratedMap {
+            terrain:    layers.cells().iter().map(|cell| generator.terrain_for(&layers, cell)).collect(),
+            resources:  generator.plan_resources(&layers, &[]),
+            ridges:     generator.plan_ridges(&layers, |_cell| false, ci_ctx),
+            layers,
+        }
+    }
+
+    #[test]
+    fn same_seed_generates_same_map() {
+        let ci_ctx = test_ctx();
+
+        let first = generate(TEST_SEED, &ci_ctx);
+        let second = generate(TEST_SEED, &ci_ctx);
+
+        assert_eq!(first, second);
+        assert_eq!(first.resources.len(), ResourceSettings::default().elements.len());
+    }
+
+    #[test]
+    fn different_seeds_generate_different_maps() {
+        let ci_ctx = test_ctx();
+
+        assert_ne!(generate(TEST_SEED, &ci_ctx).layers, generate(TEST_SEED + 1, &ci_ctx).layers);
+    }
+
+    #[test]
+    fn same_seed_names_actors_the_same() {
+        let mut first_rng = seeded_rng(TEST_SEED, SPAWNS_SALT);
+        let mut second_rng = seeded_rng(TEST_SEED, SPAWNS_SALT);
+
+        for _actor in 0..SpawnSettings::default().actor_count {
+            assert_eq!(rand_name(&mut first_rng), rand_name(&mut second_rng));
+        }
+    }
+
+    #[test]
+    fn ridges_avoid_blocked_cells_and_each_other() {
+        let ci_ctx = test_ctx();
+        let layers = MapLayers::from_cells(TEST_SEED, &TerrainSettings::default(), grid_cells(&ci_ctx));
+        let generator = MapGenerator::new(TEST_SEED);
+
+        let blocked = generator.plan_ridges(&layers, |_cell| false, &ci_ctx).concat();
+        let ridges = generator.plan_ridges(&layers, |cell| blocked.contains(cell), &ci_ctx);
+
+        let cells = ridges.concat();
+        assert!(cells.iter().all(|cell| !blocked.contains(cell)));
+        assert!(cells.iter().enumerate().all(|(idx, cell)| !cells[idx + 1..].contains(cell)));
+    }
+}
@@ -24,22 +24,52 @@ use cast_iron::{
     Plottable,
     Randomizable,
     coords,
+    hex_directions,
 };
 
+use std::fmt;
+
 use ggez::{
     Context as GgEzContext,
     graphics as ggez_gfx,
 };
 
+use mt_logger::{
+    mt_log,
+    Level,
+};
+
+use rand::{
+    rngs::StdRng,
+    SeedableRng,
+};
+
+use crate::profiler::{
+    self,
+    metrics_format::EventAttribute,
+};
+
 
 ///////////////////////////////////////////////////////////////////////////////
 //  Module Declarations
 ///////////////////////////////////////////////////////////////////////////////
 
+pub mod actor_ai;
 pub mod actor_manager;
+pub mod elemental_reserve;
+pub mod faction;
+pub mod map_generator;
 pub mod obstacle_manager;
+pub mod pathfinding;
+pub mod resource_lifecycle;
 pub mod resource_manager;
+pub mod visibility;
+pub mod weather_effects;
+pub mod weather_events;
 pub mod weather_manager;
+pub mod weather_region;
+pub mod weather_script;
+pub mod weather_transition;
 pub mod world_grid_manager;
 
 
@@ -51,6 +81,7 @@ pub mod world_grid_manager;
 pub enum DrawableError {
     CoordinatesOccupied(coords::Position),
     ReachedMaxRandAttempts,
+    Rejected(String),       // Reason the implementor rejected the instance
 }
 
 
@@ -72,11 +103,14 @@ pub trait DrawableMechanic {
     //OPT: *DESIGN* Figure out how to use this correctly...
     //              Causes a compiler error if returned within an Err() Option type
     /// Implementor-defined type indicating an error
-    type ErrorType: std::fmt::Debug;
+    type ErrorType: fmt::Debug + fmt::Display;
 
     /// Implementor-defined function to return a reference to its mesh
     fn instances(&self) -> &Vec<Self::Instance>;
 
+    /// Implementor-defined function to return a reference to its profiler instance
+    fn profiler(&self) -> &profiler::Instance;
+
     /// Implementor-defined function to return a reference to its mesh
     fn push_instance(&mut self, instance: Self::Instance);
 
@@ -86,8 +120,10 @@ pub trait DrawableMechanic {
     /// Implementor-defined function to set its mesh
     fn set_mesh(&mut self, mesh: ggez_gfx::Mesh);
 
-    /// Implementor-defined function to an instance of itself to a mesh builder
+    /// Implementor-defined function to add an instance of itself, at the given index, to a mesh builder
     fn add_instance_to_mesh_builder(
+        &self,
+        instance_idx: usize,
         instance: &Self::Instance,
         mesh_builder: &mut ggez_gfx::MeshBuilder,
         ggez_ctx: &mut GgEzContext) -> Result<(),Self::ErrorType>;
@@ -97,8 +133,21 @@ pub trait DrawableMechanic {
      *  Defined by Default   *
     \*  *  *  *  *  *  *  *  */
 
-    /// Adds the given instance to the manager
-    fn add_instance(&mut self, new_instance: Self::Instance, ggez_ctx: &mut GgEzContext) -> Result<(), DrawableError> {
+    /// Checks whether the given instance may be added, returning the reason if not.
+    /// Accepts everything unless the implementor overrides it.
+    fn validate_instance(&self, _instance: &Self::Instance, _ci_ctx: &CastIronContext) -> Result<(), Self::ErrorType> {
+        Ok(())
+    }
+
+    /// Adds the given instance to the manager, if the implementor accepts it
+    fn add_instance(&mut self,
+                    new_instance: Self::Instance,
+                    ci_ctx: &CastIronContext,
+                    ggez_ctx: &mut GgEzContext) -> Result<(), DrawableError> {
+        if let Err(reason) = self.validate_instance(&new_instance, ci_ctx) {
+            return Err(DrawableError::Rejected(reason.to_string()));
+        }
+
         // Verify that no instance already exists in the same location
         for existing_instance in self.instances() {
             if new_instance.origin() == existing_instance.origin() {
@@ -117,11 +166,26 @@ pub trait DrawableMechanic {
 
     fn add_rand_instance(&mut self, ci_ctx: &CastIronContext, ggez_ctx: &mut GgEzContext) -> Result<(), DrawableError> {
         // Create a random instance and attempt to add them until we succeed (or fail too many times)
-        for _ in 0..ci_ctx.max_rand_attempts() {
+        for attempt in 0..ci_ctx.max_rand_attempts() {
             let rand_instance = Self::Instance::rand(ci_ctx);
-            if self.add_instance(rand_instance, ggez_ctx).is_ok() {
-                // Successfully added instance
-                return Ok(())
+            let position = *rand_instance.origin();
+
+            match self.add_instance(rand_instance, ci_ctx, ggez_ctx) {
+                Ok(()) => {
+                    // Successfully added instance, let the profiler know where and how many tries it took
+                    self.profiler().mark_event_with_attributes(
+                        "RAND_INSTANCE_ADDED",
+                        || vec![EventAttribute::new("manager", type_label::<Self>()),
+                                EventAttribute::new("position", position.to_string()),
+                                EventAttribute::new("retries", attempt)],
+                        ggez_ctx).unwrap();
+
+                    return Ok(())
+                },
+                // Occupied or rejected instances count as failed attempts
+                Err(e) => {
+                    mt_log!(Level::Debug, "{} failed to add random instance at {} (attempt {}): {}", type_label::<Self>(), position, attempt, e);
+                },
             }
         }
 
@@ -135,21 +199,133 @@ pub trait DrawableMechanic {
     }
 
     /// Updates the mechanic mesh with current instances
-    fn update_mesh(&mut self, ggez_ctx: &mut GgEzContext) {        
+    fn update_mesh(&mut self, ggez_ctx: &mut GgEzContext) {
         // Short-circuit if there are no instances
         if self.instances().is_empty() {
             return;
         }
 
+        // Time the rebuild as a single phase named after the implementor, e.g. "ResourceManager"
+        let mut mesh_timer = self.profiler().start_stack(ggez_ctx);
+        let label = type_label::<Self>();
+
         //OPT: *PERFORMANCE* is this necesary? could be faster if mesh is updated in-place
         // Create a mesh builder for the update
         let mut mesh_builder = ggez_gfx::MeshBuilder::new();
 
         // Iterate through instances, adding to the mesh builder along the way
-        for instance in self.instances() {
-            Self::add_instance_to_mesh_builder(instance, &mut mesh_builder, ggez_ctx).unwrap();
+        for (instance_idx, instance) in self.instances().iter().enumerate() {
+            self.add_instance_to_mesh_builder(instance_idx, instance, &mut mesh_builder, ggez_ctx).unwrap();
         }
 
         self.set_mesh(mesh_builder.build(ggez_ctx).unwrap());
+
+        mesh_timer.mark(label, ggez_ctx);
+        self.profiler().send_mesh_update_time(mesh_timer).unwrap();
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl fmt::Display for DrawableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DrawableError::CoordinatesOccupied(position)    => write!(f, "cell {} is already occupied", position),
+            DrawableError::ReachedMaxRandAttempts           => write!(f, "gave up after too many random attempts"),
+            DrawableError::Rejected(reason)                 => write!(f, "{}", reason),
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the number of cells between two hex positions
+pub fn hex_distance(a: &coords::Position, b: &coords::Position) -> usize {
+    (((a.x() - b.x()).abs() + (a.y() - b.y()).abs() + (a.z() - b.z()).abs()) / 2) as usize
+}
+
+/// Returns an RNG seeded from the run's seed, with a salt giving each of its users a separate stream
+pub fn seeded_rng(seed: u64, salt: u64) -> StdRng {
+    StdRng::seed_from_u64(seed ^ salt)
+}
+
+/// Sorts cells by their coordinates, so that iterating over them is repeatable
+pub fn sort_cells(cells: &mut [coords::Position]) {
+    cells.sort_by_key(|position| (position.x(), position.y()));
+}
+
+/// Returns the (up to six) cells adjacent to the given position, omitting any that fall off the grid
+pub fn neighbours(position: &coords::Position, ci_ctx: &CastIronContext) -> Vec<coords::Position> {
+    let directions: hex_directions::Provider<hex_directions::Side> = hex_directions::Provider::new(hex_directions::Side::North);
+
+    directions.filter_map(|direction| {
+                  let mut neighbour = *position;
+                  neighbour.translate(&coords::Translation::from(direction), ci_ctx).ok()?;
+                  Some(neighbour)
+              })
+              .collect()
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the unqualified name of the given type, e.g. "ResourceManager", for use as a profiler label
+fn type_label<T: ?Sized>() -> &'static str {
+    let type_name = std::any::type_name::<T>();
+
+    type_name.rsplit("::").next().unwrap_or(type_name)
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Test Helpers
+///////////////////////////////////////////////////////////////////////////////
+
+/// Grid fixtures shared by the game managers' unit tests
+#[cfg(test)]
+pub mod test_helpers {
+    use cast_iron::{
+        context::{
+            Context as CastIronContext,
+            ContextBuilder as CastIronContextBuilder,
+        },
+        coords,
+    };
+
+    use super::sort_cells;
+
+    pub const TEST_GRID_RADIUS: usize = 8;
+
+    pub fn test_ctx() -> CastIronContext {
+        CastIronContextBuilder::default()
+            .grid_radius(TEST_GRID_RADIUS)
+            .build()
+    }
+
+    pub fn pos(x: i32, y: i32, z: i32, ci_ctx: &CastIronContext) -> coords::Position {
+        coords::Position::new(x, y, z, ci_ctx).unwrap()
+    }
+
+    /// Returns every cell of the test grid, in the order the world grid manager gives them
+    pub fn grid_cells(ci_ctx: &CastIronContext) -> Vec<coords::Position> {
+        let radius = TEST_GRID_RADIUS as i32;
+        let mut cells = Vec::new();
+
+        for x in -radius ..= radius {
+            for y in (-radius).max(-x - radius) ..= radius.min(-x + radius) {
+                cells.push(pos(x, y, -x - y, ci_ctx));
+            }
+        }
+        sort_cells(&mut cells);
+
+        cells
     }
 }
@@ -18,9 +18,24 @@ Purpose:
     This module manages all active obstacles in the game, as well as providing
     Utility Methods for obstacle drawing, moving, etc.
 
+    Obstacles are validated before they're added: every cell must be on the
+    grid and not already blocked, an obstacle can't cross itself, and it can
+    optionally be required to leave the rest of the grid connected. Cells of
+    impassable terrain count as blocked when checking connectivity.
+
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
+use std::{
+    collections::{
+        HashSet,
+        VecDeque,
+    },
+    fmt,
+};
+
 use cast_iron::{
+    context::Context as CastIronContext,
+    coords,
     element::Elemental,
     hex_directions,
     mechanics::obstacle::Obstacle,
@@ -43,7 +58,12 @@ use crate::{
         colors,
         hex_grid_cell::HexGridCell,
     },
-    game_managers::DrawableMechanic,
+    game_managers::{
+        DrawableMechanic,
+        neighbours,
+        world_grid_manager::WorldGridManager,
+    },
+    profiler,
 };
 
 
@@ -52,12 +72,21 @@ use crate::{
 ///////////////////////////////////////////////////////////////////////////////
 
 pub struct ObstacleManager {
+    profiler:       profiler::Instance,
     obstacles:      Vec<Obstacle>,
     obstacle_mesh:  ggez_gfx::Mesh,
+    keep_connected: bool,                       // Flag indicating obstacles may not cut off any part of the grid
+    impassable:     HashSet<coords::Position>,  // Cells whose terrain can't be crossed
 }
 
-#[derive(Debug)]
-pub struct ObstacleError;
+/// Reasons an obstacle can't be added
+#[derive(Debug, PartialEq)]
+pub enum ObstacleError {
+    OffGrid(coords::Position),
+    SelfIntersecting(coords::Position),
+    Overlapping(coords::Position),
+    Disconnecting,
+}
 
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -66,8 +95,9 @@ pub struct ObstacleError;
 
 impl ObstacleManager {
     /// Generic Constructor - creates an empty instance
-    pub fn new(ctx: &mut GgEzContext) -> Self {
+    pub fn new(profiler_original: &profiler::Instance, ctx: &mut GgEzContext) -> Self {
         ObstacleManager {
+            profiler:       profiler_original.clone(),
             obstacles:      Vec::new(),
             obstacle_mesh:  ggez_gfx::Mesh::new_line(
                                 ctx,
@@ -75,8 +105,43 @@ impl ObstacleManager {
                                crate::DEFAULT_LINE_WIDTH,
                                crate::DEFAULT_LINE_COLOR)
                                 .unwrap(),
+            keep_connected: false,
+            impassable:     HashSet::new(),
         }
     }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    /// Returns true if any obstacle covers the given cell
+    pub fn blocks(&self, position: &coords::Position) -> bool {
+        self.obstacles.iter().any(|obstacle| obstacle.positions().contains(position))
+    }
+
+    pub fn keep_connected(&self) -> bool {
+        self.keep_connected
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Sets whether obstacles that would cut off part of the grid from the rest are rejected
+    pub fn set_keep_connected(&mut self, keep_connected: bool) {
+        self.keep_connected = keep_connected;
+    }
+
+    /// Records which cells have impassable terrain, to be treated as blocked when checking connectivity
+    pub fn set_impassable_terrain(&mut self, world_grid_manager: &WorldGridManager) {
+        self.impassable = world_grid_manager.cell_positions()
+            .into_iter()
+            .filter(|cell| world_grid_manager.movement_cost(cell).is_none())
+            .collect();
+    }
+
 }
 
 
@@ -92,6 +157,10 @@ impl DrawableMechanic for ObstacleManager {
         &self.obstacles
     }
 
+    fn profiler(&self) -> &profiler::Instance {
+        &self.profiler
+    }
+
     fn push_instance(&mut self, instance: Self::Instance) {
         mt_log!(Level::Debug,
             "Adding {} obstacle starting at {} to mesh.",
@@ -105,11 +174,21 @@ impl DrawableMechanic for ObstacleManager {
         &self.obstacle_mesh
     }
 
+    fn validate_instance(&self, instance: &Self::Instance, ci_ctx: &CastIronContext) -> Result<(), Self::ErrorType> {
+        validate_obstacle(instance,
+                          |cell| self.blocks(cell),
+                          |cell| self.impassable.contains(cell),
+                          self.keep_connected,
+                          ci_ctx)
+    }
+
     fn set_mesh(&mut self, mesh: ggez_gfx::Mesh) {
         self.obstacle_mesh = mesh;
     }
 
-    fn add_instance_to_mesh_builder(instance: &Self::Instance,
+    fn add_instance_to_mesh_builder(&self,
+                                    _instance_idx: usize,
+                                    instance: &Self::Instance,
                                     mesh_builder: &mut ggez_gfx::MeshBuilder,
                                     ggez_ctx: &mut GgEzContext) -> Result<(),Self::ErrorType> {
         // Get all positions for current obstacle instance
@@ -143,6 +222,220 @@ impl DrawableMechanic for ObstacleManager {
     }
 }
 
+impl fmt::Display for ObstacleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObstacleError::OffGrid(position)            => write!(f, "cell {} is off the grid", position),
+            ObstacleError::SelfIntersecting(position)   => write!(f, "crosses itself at {}", position),
+            ObstacleError::Overlapping(position)        => write!(f, "cell {} is already blocked", position),
+            ObstacleError::Disconnecting                => write!(f, "would cut off part of the grid"),
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Checks that every cell of a candidate obstacle is on the grid, covered only once and not already blocked by
+/// another obstacle. If required to keep the grid connected, also checks the candidate doesn't cut off any cells,
+/// treating impassable cells as blocked.
+fn validate_obstacle<B, I>(candidate: &Obstacle,
+                           blocked: B,
+                           impassable: I,
+                           keep_connected: bool,
+                           ci_ctx: &CastIronContext) -> Result<(), ObstacleError>
+where B: Fn(&coords::Position) -> bool,
+      I: Fn(&coords::Position) -> bool {
+    let mut covered = HashSet::new();
+
+    for position in candidate.positions() {
+        if coords::Position::new(position.x(), position.y(), position.z(), ci_ctx).is_err() {
+            return Err(ObstacleError::OffGrid(*position));
+        }
+        if !covered.insert(*position) {
+            return Err(ObstacleError::SelfIntersecting(*position));
+        }
+        if blocked(position) {
+            return Err(ObstacleError::Overlapping(*position));
+        }
+    }
+
+    if keep_connected && would_disconnect(candidate, |cell| blocked(cell) || impassable(cell), ci_ctx) {
+        return Err(ObstacleError::Disconnecting);
+    }
 
+    Ok(())
+}
 
+/// Returns true if adding the given obstacle would split an area of unblocked cells in two.
+/// Areas that are already cut off from one another don't count.
+fn would_disconnect<B>(candidate: &Obstacle, blocked: B, ci_ctx: &CastIronContext) -> bool
+where B: Fn(&coords::Position) -> bool {
+    let covers = |cell: &coords::Position| candidate.positions().contains(cell);
+
+    // Flood the whole grid to find its unblocked cells
+    let mut unvisited: HashSet<coords::Position> = flood(candidate.origin(), |_cell| true, ci_ctx)
+        .into_iter()
+        .filter(|cell| !blocked(cell))
+        .collect();
+
+    // Check that each area the candidate covers part of is still connected without it
+    while let Some(start) = unvisited.iter().next().copied() {
+        let area = flood(&start, |cell| !blocked(cell), ci_ctx);
+        unvisited.retain(|cell| !area.contains(cell));
+
+        let remaining: Vec<&coords::Position> = area.iter().filter(|cell| !covers(cell)).collect();
+        if remaining.len() == area.len() {
+            continue;
+        }
 
+        if let Some(first) = remaining.first() {
+            if flood(first, |cell| !blocked(cell) && !covers(cell), ci_ctx).len() < remaining.len() {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Returns every cell reachable from the start through cells that pass the given test, including the start
+fn flood<F>(start: &coords::Position, passable: F, ci_ctx: &CastIronContext) -> HashSet<coords::Position>
+where F: Fn(&coords::Position) -> bool {
+    let mut reached = HashSet::new();
+    let mut frontier = VecDeque::new();
+
+    reached.insert(*start);
+    frontier.push_back(*start);
+
+    while let Some(current) = frontier.pop_front() {
+        for next in neighbours(&current, ci_ctx) {
+            if passable(&next) && reached.insert(next) {
+                frontier.push_back(next);
+            }
+        }
+    }
+
+    reached
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cast_iron::{
+        context::ContextBuilder as CastIronContextBuilder,
+        element::Element,
+    };
+
+    use crate::game_managers::test_helpers::{
+        TEST_GRID_RADIUS,
+        pos,
+        test_ctx,
+    };
+
+    fn obstacle(cells: &[(i32, i32, i32)], ci_ctx: &CastIronContext) -> Obstacle {
+        Obstacle::new(cells.iter().map(|&(x, y, z)| pos(x, y, z, ci_ctx)).collect(), Element::Earth)
+    }
+
+    /// Returns the cells of the line running through the center from edge to edge, x = 0
+    fn center_line() -> Vec<(i32, i32, i32)> {
+        let radius = TEST_GRID_RADIUS as i32;
+        (-radius ..= radius).map(|y| (0, y, -y)).collect()
+    }
+
+    #[test]
+    fn open_obstacle_is_valid() {
+        let ci_ctx = test_ctx();
+        let candidate = obstacle(&[(0, 0, 0), (1, -1, 0), (2, -2, 0)], &ci_ctx);
+
+        assert_eq!(validate_obstacle(&candidate, |_cell| false, |_cell| false, true, &ci_ctx), Ok(()));
+    }
+
+    #[test]
+    fn off_grid_cell_is_rejected() {
+        let ci_ctx = test_ctx();
+        let wide_ctx = CastIronContextBuilder::default()
+            .grid_radius(TEST_GRID_RADIUS + 1)
+            .build();
+        let radius = TEST_GRID_RADIUS as i32;
+
+        let edge = pos(radius, -radius, 0, &ci_ctx);
+        let beyond = pos(radius + 1, -radius - 1, 0, &wide_ctx);
+        let candidate = Obstacle::new(vec![edge, beyond], Element::Earth);
+
+        assert_eq!(validate_obstacle(&candidate, |_cell| false, |_cell| false, false, &ci_ctx),
+                   Err(ObstacleError::OffGrid(beyond)));
+    }
+
+    #[test]
+    fn self_intersecting_obstacle_is_rejected() {
+        let ci_ctx = test_ctx();
+        let candidate = obstacle(&[(0, 0, 0), (1, -1, 0), (1, 0, -1), (0, 0, 0)], &ci_ctx);
+
+        assert_eq!(validate_obstacle(&candidate, |_cell| false, |_cell| false, false, &ci_ctx),
+                   Err(ObstacleError::SelfIntersecting(pos(0, 0, 0, &ci_ctx))));
+    }
+
+    #[test]
+    fn overlapping_obstacle_is_rejected() {
+        let ci_ctx = test_ctx();
+        let taken = pos(1, -1, 0, &ci_ctx);
+        let candidate = obstacle(&[(0, 0, 0), (1, -1, 0)], &ci_ctx);
+
+        assert_eq!(validate_obstacle(&candidate, |cell| *cell == taken, |_cell| false, false, &ci_ctx),
+                   Err(ObstacleError::Overlapping(taken)));
+
+        // Impassable terrain alone isn't an overlap
+        assert_eq!(validate_obstacle(&candidate, |_cell| false, |cell| *cell == taken, false, &ci_ctx), Ok(()));
+    }
+
+    #[test]
+    fn disconnecting_obstacle_is_rejected_only_when_required() {
+        let ci_ctx = test_ctx();
+        let wall = obstacle(&center_line(), &ci_ctx);
+
+        assert_eq!(validate_obstacle(&wall, |_cell| false, |_cell| false, false, &ci_ctx), Ok(()));
+        assert_eq!(validate_obstacle(&wall, |_cell| false, |_cell| false, true, &ci_ctx),
+                   Err(ObstacleError::Disconnecting));
+
+        // Sealing off a corner cell counts too
+        let radius = TEST_GRID_RADIUS as i32;
+        let corner_wall = obstacle(&[(radius - 1, -radius, 1), (radius - 1, -radius + 1, 0), (radius, -radius + 1, -1)], &ci_ctx);
+        assert!(would_disconnect(&corner_wall, |_cell| false, &ci_ctx));
+    }
+
+    #[test]
+    fn impassable_terrain_counts_as_blocked() {
+        let ci_ctx = test_ctx();
+        let center = pos(0, 0, 0, &ci_ctx);
+        let terrain_wall: Vec<coords::Position> = center_line().into_iter()
+            .map(|(x, y, z)| pos(x, y, z, &ci_ctx))
+            .filter(|cell| *cell != center)
+            .collect();
+
+        // Filling the only gap in a wall of impassable terrain splits the grid
+        let plug = obstacle(&[(0, 0, 0)], &ci_ctx);
+        assert!(!would_disconnect(&plug, |_cell| false, &ci_ctx));
+        assert_eq!(validate_obstacle(&plug, |_cell| false, |cell| terrain_wall.contains(cell), true, &ci_ctx),
+                   Err(ObstacleError::Disconnecting));
+    }
+
+    #[test]
+    fn areas_already_apart_are_not_disconnected() {
+        let ci_ctx = test_ctx();
+        let terrain_wall: Vec<coords::Position> = center_line().into_iter()
+            .map(|(x, y, z)| pos(x, y, z, &ci_ctx))
+            .collect();
+
+        let candidate = obstacle(&[(3, -3, 0), (3, -2, -1)], &ci_ctx);
+        assert_eq!(validate_obstacle(&candidate, |_cell| false, |cell| terrain_wall.contains(cell), true, &ci_ctx), Ok(()));
+    }
+}
@@ -0,0 +1,251 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/pathfinding.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module finds the cheapest path between cells of the hex grid, with
+    A* search over a caller-provided cost of entering each cell (e.g. from
+    its terrain), which may mark a cell impassable.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    cmp::Reverse,
+    collections::{
+        BinaryHeap,
+        HashMap,
+    },
+};
+
+use cast_iron::{
+    context::Context as CastIronContext,
+    coords,
+};
+
+use crate::game_managers::{
+    hex_distance,
+    neighbours,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Orders positions by their coordinates, so they can sit in the frontier heap
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct PositionKey(coords::Position);
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl PartialOrd for PositionKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PositionKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.0.x(), self.0.y(), self.0.z()).cmp(&(other.0.x(), other.0.y(), other.0.z()))
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the cheapest path from the start to any cell within the given distance of the target, including both
+/// ends, or None if there is no such path. The cost function returns the cost of entering a cell (at least 1), or
+/// None if it is impassable. The start cell's own cost is never checked.
+pub fn find_path<F>(start: &coords::Position,
+                    target: &coords::Position,
+                    within: usize,
+                    cost: F,
+                    ci_ctx: &CastIronContext) -> Option<Vec<coords::Position>>
+where F: Fn(&coords::Position) -> Option<u32> {
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<coords::Position, coords::Position> = HashMap::new();
+    let mut cost_so_far: HashMap<coords::Position, u32> = HashMap::new();
+
+    // Order the frontier by estimated total cost, breaking ties in favour of cells nearer the target
+    open.push(Reverse((hex_distance(start, target) as u32, hex_distance(start, target), PositionKey(*start))));
+    cost_so_far.insert(*start, 0);
+
+    while let Some(Reverse((_estimate, _distance, PositionKey(current)))) = open.pop() {
+        if hex_distance(&current, target) <= within {
+            return Some(reconstruct_path(&came_from, current));
+        }
+
+        let current_cost = cost_so_far[&current];
+        for next in neighbours(&current, ci_ctx) {
+            let step_cost = match cost(&next) {
+                Some(step_cost) => step_cost.max(1),
+                None => continue,
+            };
+
+            let next_cost = current_cost + step_cost;
+            if cost_so_far.get(&next).map_or(true, |prev_cost| next_cost < *prev_cost) {
+                cost_so_far.insert(next, next_cost);
+                came_from.insert(next, current);
+
+                let distance = hex_distance(&next, target);
+                open.push(Reverse((next_cost + distance as u32, distance, PositionKey(next))));
+            }
+        }
+    }
+
+    None
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Walks back from the end of a path to its start
+fn reconstruct_path(came_from: &HashMap<coords::Position, coords::Position>, end: coords::Position) -> Vec<coords::Position> {
+    let mut path = vec![end];
+    while let Some(prev) = came_from.get(path.last().unwrap()) {
+        path.push(*prev);
+    }
+    path.reverse();
+
+    path
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game_managers::test_helpers::{
+        pos,
+        test_ctx,
+    };
+
+    /// Sums the cost of entering every cell of a path after the first
+    fn path_cost<F>(path: &[coords::Position], cost: F) -> u32
+    where F: Fn(&coords::Position) -> Option<u32> {
+        path.iter().skip(1).map(|cell| cost(cell).unwrap()).sum()
+    }
+
+    /// Returns true if every cell of a path is adjacent to the one before it
+    fn is_contiguous(path: &[coords::Position]) -> bool {
+        path.windows(2).all(|pair| hex_distance(&pair[0], &pair[1]) == 1)
+    }
+
+    #[test]
+    fn open_grid_path_is_straight() {
+        let ci_ctx = test_ctx();
+        let start = pos(0, 0, 0, &ci_ctx);
+        let target = pos(3, -3, 0, &ci_ctx);
+
+        let path = find_path(&start, &target, 0, |_| Some(1), &ci_ctx).unwrap();
+        assert_eq!(path.len(), 4);
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&target));
+        assert!(is_contiguous(&path));
+    }
+
+    #[test]
+    fn start_at_target_is_a_single_cell() {
+        let ci_ctx = test_ctx();
+        let start = pos(1, -1, 0, &ci_ctx);
+
+        assert_eq!(find_path(&start, &start, 0, |_| None, &ci_ctx), Some(vec![start]));
+    }
+
+    #[test]
+    fn within_stops_short_of_target() {
+        let ci_ctx = test_ctx();
+        let start = pos(-3, 3, 0, &ci_ctx);
+        let target = pos(3, -3, 0, &ci_ctx);
+
+        let path = find_path(&start, &target, 2, |_| Some(1), &ci_ctx).unwrap();
+        assert_eq!(hex_distance(path.last().unwrap(), &target), 2);
+        assert_eq!(path.len(), 5);
+
+        // Already close enough
+        let path = find_path(&start, &target, 6, |_| Some(1), &ci_ctx).unwrap();
+        assert_eq!(path, vec![start]);
+    }
+
+    #[test]
+    fn impassable_cells_are_avoided() {
+        let ci_ctx = test_ctx();
+        let start = pos(-2, 2, 0, &ci_ctx);
+        let target = pos(2, -2, 0, &ci_ctx);
+
+        // Wall off the straight line between them, except for its far ends
+        let wall = |cell: &coords::Position| cell.z() == 0 && cell.x().abs() < 2;
+        let cost = |cell: &coords::Position| if wall(cell) { None } else { Some(1) };
+
+        let path = find_path(&start, &target, 0, cost, &ci_ctx).unwrap();
+        assert_eq!(path.last(), Some(&target));
+        assert!(is_contiguous(&path));
+        assert!(path.iter().all(|cell| !wall(cell)));
+        assert!(path.len() > 5);
+    }
+
+    #[test]
+    fn cheaper_detour_beats_costly_shortcut() {
+        let ci_ctx = test_ctx();
+        let start = pos(-2, 2, 0, &ci_ctx);
+        let target = pos(2, -2, 0, &ci_ctx);
+
+        // The straight line is passable, but expensive
+        let cost = |cell: &coords::Position| if cell.z() == 0 && cell.x().abs() < 2 { Some(10) } else { Some(1) };
+
+        let path = find_path(&start, &target, 0, cost, &ci_ctx).unwrap();
+        assert!(path.iter().all(|cell| cost(cell) == Some(1)));
+        assert_eq!(path_cost(&path, cost), (path.len() - 1) as u32);
+        assert!(path_cost(&path, cost) < 10);
+    }
+
+    #[test]
+    fn zero_costs_count_as_one() {
+        let ci_ctx = test_ctx();
+        let start = pos(0, 0, 0, &ci_ctx);
+        let target = pos(0, 3, -3, &ci_ctx);
+
+        let path = find_path(&start, &target, 0, |_| Some(0), &ci_ctx).unwrap();
+        assert_eq!(path.len(), 4);
+    }
+
+    #[test]
+    fn unreachable_target_has_no_path() {
+        let ci_ctx = test_ctx();
+        let start = pos(0, 0, 0, &ci_ctx);
+        let target = pos(3, -3, 0, &ci_ctx);
+
+        // Impassable target
+        assert_eq!(find_path(&start, &target, 0, |cell| if *cell == target { None } else { Some(1) }, &ci_ctx), None);
+
+        // Start surrounded
+        assert_eq!(find_path(&start, &target, 0, |_| None, &ci_ctx), None);
+
+        // An impassable cell is still fine to stop near
+        let path = find_path(&start, &target, 1, |cell| if *cell == target { None } else { Some(1) }, &ci_ctx).unwrap();
+        assert_eq!(hex_distance(path.last().unwrap(), &target), 1);
+    }
+}
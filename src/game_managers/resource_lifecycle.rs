@@ -0,0 +1,315 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/resource_lifecycle.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module tracks the level of a resource over time, as it regenerates,
+    is harvested, and overflows, along with a history of its state changes.
+
+    Levels are fractions of a full resource: 0.0 is Depleted, 1.0 is Full,
+    and anything above 1.0 is Overflow.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::time::Duration;
+
+use cast_iron::mechanics::resource::State;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Level of a full resource
+pub const FULL_LEVEL:           f64 = 1.0;
+
+/// Highest level an overflowing resource can reach
+pub const MAX_OVERFLOW_LEVEL:   f64 = 1.5;
+
+/// Default regeneration rate (in levels per second) of a resource below Full
+pub const DEFAULT_REGEN_RATE:   f64 = 0.05;
+
+// Upper bounds of the levels for each partial state
+const LOW_LEVEL:                f64 = 1.0 / 3.0;
+const PARTIAL_LEVEL:            f64 = 2.0 / 3.0;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// A resource's state at a point in game time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResourceSample {
+    pub at:     Duration,
+    pub level:  f64,
+    pub state:  &'static str,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceLifecycle {
+    level:      f64,
+    spilled:    f64,                    // Total overflow spilled over the resource's lifetime
+    history:    Vec<ResourceSample>,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl ResourceLifecycle {
+    /// Starts a lifecycle at the typical level of the given state
+    pub fn new(state: &State) -> Self {
+        Self {
+            level:      level_for_state(state),
+            spilled:    0.0,
+            history:    Vec::new(),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+
+    pub fn spilled(&self) -> f64 {
+        self.spilled
+    }
+
+    pub fn state(&self) -> State {
+        state_for_level(self.level)
+    }
+
+    /// Returns every recorded state change, oldest first
+    pub fn history(&self) -> &[ResourceSample] {
+        &self.history
+    }
+
+    /// Returns true if the current state differs from the last recorded one (or none has been recorded)
+    pub fn state_changed(&self) -> bool {
+        self.history.last().map_or(true, |sample| sample.state != state_label(&self.state()))
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Raises the level by the given amount, up to the given ceiling, returning the amount actually added
+    pub fn fill(&mut self, amount: f64, ceiling: f64) -> f64 {
+        let room = (ceiling - self.level).max(0.0);
+        if amount >= room {
+            // Land exactly on the ceiling, so a full resource reads as Full rather than High
+            self.level = self.level.max(ceiling);
+            room
+        }
+        else {
+            let added = amount.max(0.0);
+            self.level += added;
+            added
+        }
+    }
+
+    /// Lowers the level by up to the given amount, returning the amount actually removed
+    pub fn drain(&mut self, amount: f64) -> f64 {
+        let removed = amount.max(0.0).min(self.level);
+        self.level -= removed;
+
+        removed
+    }
+
+    /// Lowers the level by up to the given amount of overflow, returning the amount actually spilled
+    pub fn spill(&mut self, amount: f64) -> f64 {
+        let spilled = self.drain(amount);
+        self.spilled += spilled;
+
+        spilled
+    }
+
+    /// Records the current state at the given game time
+    pub fn record(&mut self, at: Duration) -> ResourceSample {
+        let sample = ResourceSample {
+            at,
+            level:  self.level,
+            state:  state_label(&self.state()),
+        };
+        self.history.push(sample);
+
+        sample
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the state of a resource at the given level
+pub fn state_for_level(level: f64) -> State {
+    if level <= 0.0 {
+        State::Depleted
+    }
+    else if level < LOW_LEVEL {
+        State::Low
+    }
+    else if level < PARTIAL_LEVEL {
+        State::Partial
+    }
+    else if level < FULL_LEVEL {
+        State::High
+    }
+    else if level <= FULL_LEVEL {
+        State::Full
+    }
+    else {
+        State::Overflow
+    }
+}
+
+/// Returns a typical level for a resource in the given state
+pub fn level_for_state(state: &State) -> f64 {
+    match state {
+        State::Depleted => 0.0,
+        State::Low      => LOW_LEVEL / 2.0,
+        State::Partial  => (LOW_LEVEL + PARTIAL_LEVEL) / 2.0,
+        State::High     => (PARTIAL_LEVEL + FULL_LEVEL) / 2.0,
+        State::Full     => FULL_LEVEL,
+        State::Overflow => MAX_OVERFLOW_LEVEL,
+    }
+}
+
+/// Returns the name of the given state, for logs and history records
+pub fn state_label(state: &State) -> &'static str {
+    match state {
+        State::Depleted => "Depleted",
+        State::Low      => "Low",
+        State::Partial  => "Partial",
+        State::High     => "High",
+        State::Full     => "Full",
+        State::Overflow => "Overflow",
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_of(lifecycle: &ResourceLifecycle) -> &'static str {
+        state_label(&lifecycle.state())
+    }
+
+    #[test]
+    fn regenerates_through_each_state_up_to_full() {
+        let mut lifecycle = ResourceLifecycle::new(&State::Depleted);
+        assert_eq!(state_of(&lifecycle), "Depleted");
+
+        let mut states = vec![state_of(&lifecycle)];
+        for _step in 0..20 {
+            lifecycle.fill(0.1, FULL_LEVEL);
+            if states.last() != Some(&state_of(&lifecycle)) {
+                states.push(state_of(&lifecycle));
+            }
+        }
+
+        assert_eq!(states, vec!["Depleted", "Low", "Partial", "High", "Full"]);
+
+        // Regeneration alone never overflows
+        assert_eq!(lifecycle.level(), FULL_LEVEL);
+        assert_eq!(lifecycle.fill(0.1, FULL_LEVEL), 0.0);
+    }
+
+    #[test]
+    fn harvest_removes_no_more_than_is_left() {
+        let mut lifecycle = ResourceLifecycle::new(&State::Full);
+
+        assert_eq!(lifecycle.drain(0.25), 0.25);
+        assert_eq!(state_of(&lifecycle), "High");
+
+        assert_eq!(lifecycle.drain(5.0), 0.75);
+        assert_eq!(state_of(&lifecycle), "Depleted");
+        assert_eq!(lifecycle.drain(1.0), 0.0);
+
+        // A negative harvest doesn't add anything
+        assert_eq!(lifecycle.drain(-1.0), 0.0);
+        assert_eq!(lifecycle.level(), 0.0);
+    }
+
+    #[test]
+    fn overflow_is_capped() {
+        let mut lifecycle = ResourceLifecycle::new(&State::Full);
+
+        lifecycle.fill(0.25, MAX_OVERFLOW_LEVEL);
+        assert_eq!(state_of(&lifecycle), "Overflow");
+
+        assert_eq!(lifecycle.fill(1.0, MAX_OVERFLOW_LEVEL), MAX_OVERFLOW_LEVEL - 1.25);
+        assert_eq!(lifecycle.level(), MAX_OVERFLOW_LEVEL);
+
+        // A lower ceiling never pulls the level down
+        assert_eq!(lifecycle.fill(0.1, FULL_LEVEL), 0.0);
+        assert_eq!(lifecycle.level(), MAX_OVERFLOW_LEVEL);
+    }
+
+    #[test]
+    fn spilling_drains_overflow_and_tallies_it() {
+        let mut lifecycle = ResourceLifecycle::new(&State::Overflow);
+
+        assert_eq!(lifecycle.spill(0.25), 0.25);
+        assert_eq!(lifecycle.spill(0.25), 0.25);
+        assert_eq!(state_of(&lifecycle), "Full");
+        assert_eq!(lifecycle.spilled(), 0.5);
+
+        // Harvesting isn't spilling
+        lifecycle.drain(0.5);
+        assert_eq!(lifecycle.spilled(), 0.5);
+    }
+
+    #[test]
+    fn history_records_only_state_changes() {
+        let mut lifecycle = ResourceLifecycle::new(&State::Full);
+        assert!(lifecycle.state_changed());
+
+        lifecycle.record(Duration::from_secs(1));
+        assert!(!lifecycle.state_changed());
+
+        // Moving within a state isn't a change
+        lifecycle.drain(0.01);
+        lifecycle.fill(0.01, FULL_LEVEL);
+        assert!(!lifecycle.state_changed());
+
+        lifecycle.drain(0.5);
+        assert!(lifecycle.state_changed());
+        let sample = lifecycle.record(Duration::from_secs(2));
+
+        assert_eq!(sample, ResourceSample {at: Duration::from_secs(2), level: 0.5, state: "Partial"});
+        assert_eq!(lifecycle.history().len(), 2);
+    }
+
+    #[test]
+    fn levels_for_states_round_trip() {
+        for state in &[State::Depleted, State::Low, State::Partial, State::High, State::Full, State::Overflow] {
+            assert_eq!(state_label(&state_for_level(level_for_state(state))), state_label(state));
+        }
+    }
+}
@@ -18,9 +18,20 @@ Purpose:
     This module manages all active resources in the game, as well as providing
     Utility Methods for resource drawing, moving, etc.
 
+    Resources regenerate toward Full over time and deplete as they are
+    harvested. Weather of a resource's own element pushes it into Overflow,
+    and the excess spills into adjacent resources of the same element.
+
+    Outlines of all resources share one mesh, while each resource's fill is
+    a separate segment, rebuilt only when that resource changes state.
+
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
+use std::fmt;
+
 use cast_iron::{
+    context::Context as CastIronContext,
+    coords,
     element::Elemental,
     mechanics::resource::Resource,
     Plottable,
@@ -42,17 +53,48 @@ use crate::{
         colors,
         hex_grid_cell::HexGridCell,
     },
-    game_managers::DrawableMechanic,
+    game_clock::GameClock,
+    game_managers::{
+        DrawableMechanic,
+        hex_distance,
+        resource_lifecycle::{
+            self,
+            ResourceLifecycle,
+        },
+        weather_manager::WeatherManager,
+    },
+    profiler::{
+        self,
+        metrics_format::EventAttribute,
+    },
 };
 
 
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Regeneration multiplier, at max intensity, from weather of a resource's own element
+const WEATHER_OVERFLOW_FACTOR:  f64 = 4.0;
+
+/// Rate (in levels per second) at which an overflowing resource spills its excess
+const SPILL_RATE:               f64 = 0.1;
+
+/// Phase label for the resource mesh rebuilds sent to the profiler
+const MESH_TIMER_LABEL:         &str = "ResourceManager";
+
+
 ///////////////////////////////////////////////////////////////////////////////
 // Data Structures
 ///////////////////////////////////////////////////////////////////////////////
 
 pub struct ResourceManager {
+    profiler:       profiler::Instance,
     resources:      Vec<Resource>,
-    resource_mesh:  ggez_gfx::Mesh,
+    lifecycles:     Vec<ResourceLifecycle>, // Parallel to resources
+    regen_rate:     f64,                    // Levels per second
+    resource_mesh:  ggez_gfx::Mesh,         // Outlines of every resource
+    fill_meshes:    Vec<ggez_gfx::Mesh>,    // Parallel to resources, colored by each resource's state
 }
 
 #[derive(Debug)]
@@ -65,16 +107,167 @@ pub struct ResourceError;
 
 impl ResourceManager {
     /// Generic Constructor - creates an empty instance
-    pub fn new(ctx: &mut GgEzContext) -> Self {
+    pub fn new(profiler_original: &profiler::Instance, ctx: &mut GgEzContext) -> Self {
         ResourceManager {
+            profiler:       profiler_original.clone(),
             resources:      Vec::new(),
+            lifecycles:     Vec::new(),
+            regen_rate:     resource_lifecycle::DEFAULT_REGEN_RATE,
             resource_mesh:  ggez_gfx::Mesh::new_line(
                             ctx,
                             &[ggez_mint::Point2 {x: 0.0, y: 0.0}, ggez_mint::Point2 {x: 10.0, y: 10.0}],
                            crate::DEFAULT_LINE_WIDTH,
                            crate::DEFAULT_LINE_COLOR)
                             .unwrap(),
+            fill_meshes:    Vec::new(),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the rate (in levels per second) at which resources regenerate toward Full
+    pub fn regen_rate(&self) -> f64 {
+        self.regen_rate
+    }
+
+    /// Returns the resource covering the given cell, if any
+    pub fn resource_at(&self, position: &coords::Position) -> Option<&Resource> {
+        self.index_at(position).map(|idx| &self.resources[idx])
+    }
+
+    /// Returns every resource alongside its lifecycle
+    pub fn iter_lifecycles(&self) -> impl Iterator<Item = (&Resource, &ResourceLifecycle)> {
+        self.resources.iter().zip(self.lifecycles.iter())
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    pub fn set_regen_rate(&mut self, regen_rate: f64) {
+        self.regen_rate = regen_rate.max(0.0);
+    }
+
+    /// Removes up to the given amount (in levels) from the resource covering the given cell,
+    /// returning the amount actually harvested
+    pub fn harvest(&mut self, position: &coords::Position, amount: f64) -> f64 {
+        match self.index_at(position) {
+            Some(idx) => self.lifecycles[idx].drain(amount),
+            None => 0.0,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Advances every resource by one step of the game clock: regenerating, overflowing under
+    /// weather of its own element, and spilling any excess into adjacent resources
+    pub fn update_resources(&mut self,
+                            clock: &GameClock,
+                            weather_manager: &WeatherManager,
+                            ci_ctx: &CastIronContext,
+                            ggez_ctx: &mut GgEzContext) {
+        let elapsed_time = clock.elapsed();
+        let delta_secs = clock.fixed_timestep().as_secs_f64();
+        let global_weather = weather_manager.global_weather_at(elapsed_time, ci_ctx);
+
+        // Regenerate toward Full, and past it while matching weather is present
+        for (resource, lifecycle) in self.resources.iter().zip(self.lifecycles.iter_mut()) {
+            lifecycle.fill(self.regen_rate * delta_secs, resource_lifecycle::FULL_LEVEL);
+
+            let weather_fraction: f64 = global_weather.iter()
+                .chain(weather_manager.weather_at(resource.origin()))
+                .filter(|cell_weather| cell_weather.element() == resource.element())
+                .map(|cell_weather| (cell_weather.intensity_exact() / ci_ctx.max_weather_intensity()).max(0.0).min(1.0))
+                .sum::<f64>()
+                .min(1.0);
+
+            if weather_fraction > 0.0 {
+                lifecycle.fill(self.regen_rate * WEATHER_OVERFLOW_FACTOR * weather_fraction * delta_secs,
+                               resource_lifecycle::MAX_OVERFLOW_LEVEL);
+            }
+        }
+
+        // Spill overflow into adjacent resources of the same element, dissipating whatever they can't hold
+        for idx in 0..self.resources.len() {
+            let excess = self.lifecycles[idx].level() - resource_lifecycle::FULL_LEVEL;
+            if excess <= 0.0 {
+                continue;
+            }
+
+            let spilled = self.lifecycles[idx].spill(excess.min(SPILL_RATE * delta_secs));
+            let neighbours: Vec<usize> = (0..self.resources.len())
+                .filter(|&other| other != idx &&
+                                 self.resources[other].element() == self.resources[idx].element() &&
+                                 are_adjacent(&self.resources[idx], &self.resources[other]))
+                .collect();
+
+            for other in &neighbours {
+                self.lifecycles[*other].fill(spilled / neighbours.len() as f64, resource_lifecycle::FULL_LEVEL);
+            }
+        }
+
+        // Record state changes, and redraw the fill of each resource that changed
+        let mut changed = Vec::new();
+        for (idx, (resource, lifecycle)) in self.resources.iter().zip(self.lifecycles.iter_mut()).enumerate() {
+            if !lifecycle.state_changed() {
+                continue;
+            }
+
+            let prev_state = lifecycle.history().last().map_or("None", |sample| sample.state);
+            let sample = lifecycle.record(elapsed_time);
+            changed.push(idx);
+
+            self.profiler.mark_event_with_attributes(
+                "RESOURCE_STATE",
+                || vec![EventAttribute::new("position", resource.origin().to_string()),
+                        EventAttribute::new("element", String::from(resource.element())),
+                        EventAttribute::new("from", prev_state),
+                        EventAttribute::new("to", sample.state),
+                        EventAttribute::new("level", sample.level),
+                        EventAttribute::new("spilled", lifecycle.spilled())],
+                ggez_ctx).unwrap();
+        }
+
+        if changed.is_empty() {
+            return;
         }
+
+        let mut mesh_timer = self.profiler.start_stack(ggez_ctx);
+        for idx in changed {
+            self.fill_meshes[idx] = self.build_fill_mesh(idx, ggez_ctx);
+        }
+        mesh_timer.mark(MESH_TIMER_LABEL, ggez_ctx);
+        self.profiler.send_mesh_update_time(mesh_timer).unwrap();
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Helper Methods    *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the index of the resource covering the given cell, if any
+    fn index_at(&self, position: &coords::Position) -> Option<usize> {
+        self.resources.iter().position(|resource| hex_distance(resource.origin(), position) <= resource.radius())
+    }
+
+    /// Builds the fill of a single resource, colored by its current state
+    fn build_fill_mesh(&self, resource_idx: usize, ggez_ctx: &mut GgEzContext) -> ggez_gfx::Mesh {
+        let resource = &self.resources[resource_idx];
+        let color = colors::from_resource_state(resource.element(), &self.lifecycles[resource_idx].state());
+
+        let mut mesh_builder = ggez_gfx::MeshBuilder::new();
+        let cur_hex = HexGridCell::new_from_hex_coords(resource.origin(), crate::HEX_RADIUS_VERTEX, ggez_ctx);
+        cur_hex.add_to_mesh(color, colors::TRANSPARENT, &mut mesh_builder);
+        cur_hex.add_radials_to_mesh(color, colors::TRANSPARENT, resource.radius(), true, &mut mesh_builder);
+
+        mesh_builder.build(ggez_ctx).unwrap()
     }
 }
 
@@ -91,12 +284,17 @@ impl DrawableMechanic for ResourceManager {
         &self.resources
     }
 
+    fn profiler(&self) -> &profiler::Instance {
+        &self.profiler
+    }
+
     fn push_instance(&mut self, instance: Self::Instance) {
         mt_log!(Level::Debug,
             "Adding {} resource starting at {} to mesh.",
             String::from(instance.element()),
             instance.origin());
 
+        self.lifecycles.push(ResourceLifecycle::new(&instance.state()));
         self.resources.push(instance);
     }
 
@@ -108,16 +306,19 @@ impl DrawableMechanic for ResourceManager {
         self.resource_mesh = mesh;
     }
 
-    fn add_instance_to_mesh_builder(instance: &Self::Instance,
+    /// Adds the outline of a resource. Its fill is drawn separately, so that it can change without rebuilding every outline.
+    fn add_instance_to_mesh_builder(&self,
+                                    _instance_idx: usize,
+                                    instance: &Self::Instance,
                                     mesh_builder: &mut ggez_gfx::MeshBuilder,
                                     ggez_ctx: &mut GgEzContext) -> Result<(), Self::ErrorType> {
         // Create a HexGridCell object and add it to the mesh builder
         let cur_hex = HexGridCell::new_from_hex_coords(instance.origin(),crate::HEX_RADIUS_VERTEX, ggez_ctx);
-        cur_hex.add_to_mesh(colors::from_resource(instance), colors::WHITE, mesh_builder);
+        cur_hex.add_to_mesh(colors::TRANSPARENT, colors::WHITE, mesh_builder);
 
         // Create radial HexGridCells as necessary
         cur_hex.add_radials_to_mesh(
-            colors::from_resource(instance),
+            colors::TRANSPARENT,
             colors::WHITE,
             instance.radius(),
             true,
@@ -125,4 +326,52 @@ impl DrawableMechanic for ResourceManager {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Rebuilds the outlines, and builds the fill of any resource added since the last update
+    fn update_mesh(&mut self, ggez_ctx: &mut GgEzContext) {
+        if self.resources.is_empty() {
+            return;
+        }
+
+        let mut mesh_timer = self.profiler.start_stack(ggez_ctx);
+
+        let mut mesh_builder = ggez_gfx::MeshBuilder::new();
+        for (resource_idx, resource) in self.resources.iter().enumerate() {
+            self.add_instance_to_mesh_builder(resource_idx, resource, &mut mesh_builder, ggez_ctx).unwrap();
+        }
+        self.resource_mesh = mesh_builder.build(ggez_ctx).unwrap();
+
+        while self.fill_meshes.len() < self.resources.len() {
+            let fill_mesh = self.build_fill_mesh(self.fill_meshes.len(), ggez_ctx);
+            self.fill_meshes.push(fill_mesh);
+        }
+
+        mesh_timer.mark(MESH_TIMER_LABEL, ggez_ctx);
+        self.profiler.send_mesh_update_time(mesh_timer).unwrap();
+    }
+
+    /// Draws each resource's fill, then every outline over them
+    fn draw(&self, ggez_ctx: &mut GgEzContext) {
+        for fill_mesh in &self.fill_meshes {
+            ggez_gfx::draw(ggez_ctx, fill_mesh, ggez_gfx::DrawParam::default()).unwrap();
+        }
+
+        ggez_gfx::draw(ggez_ctx, &self.resource_mesh, ggez_gfx::DrawParam::default()).unwrap();
+    }
+}
+
+impl fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid resource")
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns true if the cells of the two resources touch or overlap
+fn are_adjacent(a: &Resource, b: &Resource) -> bool {
+    hex_distance(a.origin(), b.origin()) <= a.radius() + b.radius() + 1
+}
@@ -0,0 +1,292 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/visibility.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module computes line of sight over the hex grid, and tracks which
+    cells a group of viewers (e.g. a faction) can see now or has seen before.
+
+    Sight runs along hex lines, and is blocked by any cell between the viewer
+    and the target for which the given test returns true. The blocking cell
+    itself is still visible. Nothing here depends on rendering.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::collections::HashSet;
+
+use cast_iron::{
+    context::Context as CastIronContext,
+    coords,
+};
+
+use crate::game_managers::hex_distance;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Default distance (in cells) an actor can see
+pub const DEFAULT_SIGHT_RANGE:  usize = 6;
+
+/// Nudge applied to hex lines, so that lines running exactly along cell edges consistently pick one side
+const LINE_NUDGE:               (f64, f64, f64) = (1e-6, 2e-6, -3e-6);
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// The cells visible to a group of viewers, and every cell they have seen before
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VisibilityMap {
+    visible:    HashSet<coords::Position>,
+    seen:       HashSet<coords::Position>,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl VisibilityMap {
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn is_visible(&self, position: &coords::Position) -> bool {
+        self.visible.contains(position)
+    }
+
+    /// Returns true if the cell is visible now or has been at any point before
+    pub fn was_seen(&self, position: &coords::Position) -> bool {
+        self.seen.contains(position)
+    }
+
+    pub fn visible(&self) -> &HashSet<coords::Position> {
+        &self.visible
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Recomputes the visible cells from the given viewers' positions, adding them to the seen cells
+    pub fn update<'a, I, F>(&mut self, viewers: I, range: usize, blocks: F, ci_ctx: &CastIronContext)
+    where I: IntoIterator<Item = &'a coords::Position>,
+          F: Fn(&coords::Position) -> bool {
+        self.visible.clear();
+
+        for viewer in viewers {
+            self.visible.extend(visible_cells(viewer, range, &blocks, ci_ctx));
+        }
+
+        self.seen.extend(self.visible.iter().copied());
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the cells along the hex line between two positions, inclusive of both ends.
+/// Cells that would fall off the grid are omitted.
+pub fn hex_line(from: &coords::Position, to: &coords::Position, ci_ctx: &CastIronContext) -> Vec<coords::Position> {
+    let distance = hex_distance(from, to);
+    if distance == 0 {
+        return vec![*from];
+    }
+
+    let start = (from.x() as f64 + LINE_NUDGE.0, from.y() as f64 + LINE_NUDGE.1, from.z() as f64 + LINE_NUDGE.2);
+    let end = (to.x() as f64 + LINE_NUDGE.0, to.y() as f64 + LINE_NUDGE.1, to.z() as f64 + LINE_NUDGE.2);
+
+    (0..=distance)
+        .filter_map(|step| {
+            let t = step as f64 / distance as f64;
+            let (x, y, z) = cube_round(start.0 + (end.0 - start.0) * t,
+                                       start.1 + (end.1 - start.1) * t,
+                                       start.2 + (end.2 - start.2) * t);
+
+            coords::Position::new(x, y, z, ci_ctx).ok()
+        })
+        .collect()
+}
+
+/// Returns true if nothing blocks sight between two positions. The end cells themselves never block.
+pub fn line_of_sight<F>(from: &coords::Position, to: &coords::Position, blocks: F, ci_ctx: &CastIronContext) -> bool
+where F: Fn(&coords::Position) -> bool {
+    let line = hex_line(from, to, ci_ctx);
+
+    line.iter()
+        .skip(1)
+        .take(line.len().saturating_sub(2))
+        .all(|cell| !blocks(cell))
+}
+
+/// Returns every cell within range of the viewer that it has line of sight to, including its own
+pub fn visible_cells<F>(viewer: &coords::Position, range: usize, blocks: F, ci_ctx: &CastIronContext) -> HashSet<coords::Position>
+where F: Fn(&coords::Position) -> bool {
+    let range = range as i32;
+    let mut visible = HashSet::new();
+
+    // Walk the cube-coordinate hexagon around the viewer, keeping the x + y + z == 0 constraint
+    for dx in -range ..= range {
+        for dy in (-range).max(-dx - range) ..= range.min(-dx + range) {
+            let dz = -dx - dy;
+            if let Ok(cell) = coords::Position::new(viewer.x() + dx, viewer.y() + dy, viewer.z() + dz, ci_ctx) {
+                if line_of_sight(viewer, &cell, &blocks, ci_ctx) {
+                    visible.insert(cell);
+                }
+            }
+        }
+    }
+
+    visible
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Rounds fractional cube coordinates to the nearest cell, preserving x + y + z == 0
+fn cube_round(x: f64, y: f64, z: f64) -> (i32, i32, i32) {
+    let (mut rx, mut ry, mut rz) = (x.round(), y.round(), z.round());
+    let (dx, dy, dz) = ((rx - x).abs(), (ry - y).abs(), (rz - z).abs());
+
+    // Recompute whichever coordinate rounded furthest from the other two
+    if dx > dy && dx > dz {
+        rx = -ry - rz;
+    }
+    else if dy > dz {
+        ry = -rx - rz;
+    }
+    else {
+        rz = -rx - ry;
+    }
+
+    (rx as i32, ry as i32, rz as i32)
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game_managers::test_helpers::{
+        TEST_GRID_RADIUS,
+        pos,
+        test_ctx,
+    };
+
+    #[test]
+    fn blocker_hides_cells_behind_it() {
+        let ci_ctx = test_ctx();
+        let viewer = pos(0, 0, 0, &ci_ctx);
+        let blocker = pos(1, -1, 0, &ci_ctx);
+
+        let visible = visible_cells(&viewer, 3, |cell| *cell == blocker, &ci_ctx);
+
+        // The blocker itself is still visible...
+        assert!(visible.contains(&blocker));
+        // ...but the cells straight behind it are not
+        assert!(!visible.contains(&pos(2, -2, 0, &ci_ctx)));
+        assert!(!visible.contains(&pos(3, -3, 0, &ci_ctx)));
+        // Cells off to the side are unaffected
+        assert!(visible.contains(&pos(-2, 2, 0, &ci_ctx)));
+        assert!(visible.contains(&pos(0, -3, 3, &ci_ctx)));
+    }
+
+    #[test]
+    fn line_along_cell_edges_picks_one_side() {
+        let ci_ctx = test_ctx();
+        let from = pos(0, 0, 0, &ci_ctx);
+        let to = pos(2, -1, -1, &ci_ctx);
+
+        // The line runs exactly between (1, 0, -1) and (1, -1, 0), and the nudge settles on the former
+        let line = hex_line(&from, &to, &ci_ctx);
+        assert_eq!(line, vec![from, pos(1, 0, -1, &ci_ctx), to]);
+
+        // Each step is to an adjacent cell, in either direction
+        let mut reversed = hex_line(&to, &from, &ci_ctx);
+        reversed.reverse();
+        assert_eq!(reversed, line);
+        assert!(line.windows(2).all(|pair| hex_distance(&pair[0], &pair[1]) == 1));
+
+        // Only the cell on the line can block it
+        let off_line = pos(1, -1, 0, &ci_ctx);
+        let on_line = pos(1, 0, -1, &ci_ctx);
+        assert!(line_of_sight(&from, &to, |cell| *cell == off_line, &ci_ctx));
+        assert!(!line_of_sight(&from, &to, |cell| *cell == on_line, &ci_ctx));
+    }
+
+    #[test]
+    fn sight_is_limited_to_range() {
+        let ci_ctx = test_ctx();
+        let viewer = pos(0, 0, 0, &ci_ctx);
+
+        let visible = visible_cells(&viewer, 2, |_cell| false, &ci_ctx);
+
+        // A hexagon of radius 2 holds 1 + 6 + 12 cells
+        assert_eq!(visible.len(), 19);
+        assert!(visible.iter().all(|cell| hex_distance(&viewer, cell) <= 2));
+        assert!(!visible.contains(&pos(3, -3, 0, &ci_ctx)));
+
+        assert_eq!(visible_cells(&viewer, 0, |_cell| false, &ci_ctx).into_iter().collect::<Vec<_>>(), vec![viewer]);
+    }
+
+    #[test]
+    fn sight_stops_at_grid_edge() {
+        let ci_ctx = test_ctx();
+        let radius = TEST_GRID_RADIUS as i32;
+        let viewer = pos(radius, -radius, 0, &ci_ctx);
+
+        let visible = visible_cells(&viewer, 1, |_cell| false, &ci_ctx);
+
+        // A corner cell has only three neighbours on the grid
+        assert_eq!(visible.len(), 4);
+    }
+
+    #[test]
+    fn seen_cells_persist_after_leaving_sight() {
+        let ci_ctx = test_ctx();
+        let start = pos(-4, 4, 0, &ci_ctx);
+        let end = pos(4, -4, 0, &ci_ctx);
+        let mut visibility = VisibilityMap::default();
+
+        visibility.update(&[start], 1, |_cell| false, &ci_ctx);
+        assert!(visibility.is_visible(&start));
+        assert!(visibility.was_seen(&start));
+
+        // Moving out of range hides the start, but it is remembered
+        visibility.update(&[end], 1, |_cell| false, &ci_ctx);
+        assert!(!visibility.is_visible(&start));
+        assert!(visibility.was_seen(&start));
+        assert!(visibility.is_visible(&end));
+
+        // With no viewers left nothing is visible, but everything seen is still remembered
+        visibility.update(std::iter::empty(), 1, |_cell| false, &ci_ctx);
+        assert!(visibility.visible().is_empty());
+        assert!(visibility.was_seen(&start));
+        assert!(visibility.was_seen(&end));
+    }
+}
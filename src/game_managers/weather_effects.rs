@@ -0,0 +1,273 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/weather_effects.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module provides the visual effects of the global weather over the
+    grid: rain streaks for Water, snow for Ice, drifting leaves for Wind,
+    embers for Fire, flashes for Electric and dimming for Dark.
+
+    The density of each effect follows the intensity of its weather.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::f32::consts::PI;
+
+use cast_iron::element::Element;
+
+use ggez::{
+    Context as GgEzContext,
+    graphics as ggez_gfx,
+    mint as ggez_mint,
+};
+
+use rand::{
+    rngs::StdRng,
+    Rng,
+    SeedableRng,
+};
+
+use crate::{
+    game_assets::{
+        colors,
+        particle_system::{
+            ParticleSpec,
+            ParticleSystem,
+        },
+    },
+    game_managers::{
+        self,
+        weather_region::CellWeather,
+    },
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Maximum number of weather particles alive at once
+const PARTICLE_CAPACITY:        usize = 2048;
+
+/// Particles spawned per second at max intensity, by effect
+const MAX_RAIN_RATE:            f32 = 400.0;
+const MAX_SNOW_RATE:            f32 = 120.0;
+const MAX_LEAF_RATE:            f32 = 40.0;
+const MAX_EMBER_RATE:           f32 = 120.0;
+
+/// Expected lightning flashes per second at max intensity
+const MAX_FLASH_RATE:           f32 = 0.75;
+
+/// Alpha of a lightning flash at max intensity, and how quickly (alpha per second) it fades
+const MAX_FLASH_ALPHA:          f32 = 0.6;
+const FLASH_DECAY_RATE:         f32 = 3.0;
+
+/// Alpha of the darkening overlay at max intensity
+const MAX_DIM_ALPHA:            f32 = 0.6;
+
+/// Salt mixed into the run's seed for the effects' RNG
+const EFFECTS_SEED_SALT:        u64 = 0x4546_4645_4354;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+pub struct WeatherEffects {
+    particles:          ParticleSystem,
+    bounds:             ggez_gfx::Rect,     // Pixel-coords area covered by the effects
+    spawn_debt:         [f32; 4],           // Fractional particles owed to each spawning effect
+    flash_alpha:        f32,
+    dim_alpha:          f32,
+    rng:                StdRng,
+}
+
+/// Particle-spawning effects, indexing the spawn debt
+#[derive(Debug, Copy, Clone)]
+enum Spawner {
+    Rain    = 0,
+    Snow    = 1,
+    Leaves  = 2,
+    Embers  = 3,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl WeatherEffects {
+    /// Creates effects covering the whole window
+    pub fn new(ggez_ctx: &mut GgEzContext) -> Self {
+        let (window_x, window_y) = ggez_gfx::size(ggez_ctx);
+        let bounds = ggez_gfx::Rect::new(0.0, 0.0, window_x, window_y);
+
+        Self {
+            particles:      ParticleSystem::new(PARTICLE_CAPACITY, bounds, ggez_ctx),
+            bounds,
+            spawn_debt:     [0.0; 4],
+            flash_alpha:    0.0,
+            dim_alpha:      0.0,
+            rng:            StdRng::from_entropy(),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn live_particle_count(&self) -> usize {
+        self.particles.live_count()
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Reseeds the effects' RNG from the run's seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = game_managers::seeded_rng(seed, EFFECTS_SEED_SALT);
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Advances the effects by the given time step (in seconds), spawning particles for each weather
+    /// in proportion to its intensity (as a fraction of max)
+    pub fn update(&mut self, weather: &[CellWeather], max_intensity: f64, delta_secs: f32) {
+        self.particles.update(delta_secs);
+        self.flash_alpha = (self.flash_alpha - FLASH_DECAY_RATE * delta_secs).max(0.0);
+        self.dim_alpha = 0.0;
+
+        for cell_weather in weather {
+            let fraction = (cell_weather.intensity_exact() / max_intensity).max(0.0).min(1.0) as f32;
+
+            match cell_weather.element() {
+                Element::Water      => self.spawn(Spawner::Rain, MAX_RAIN_RATE * fraction * delta_secs),
+                Element::Ice        => self.spawn(Spawner::Snow, MAX_SNOW_RATE * fraction * delta_secs),
+                Element::Wind       => self.spawn(Spawner::Leaves, MAX_LEAF_RATE * fraction * delta_secs),
+                Element::Fire       => self.spawn(Spawner::Embers, MAX_EMBER_RATE * fraction * delta_secs),
+                Element::Electric   => {
+                    if self.rng.gen::<f32>() < MAX_FLASH_RATE * fraction * delta_secs {
+                        self.flash_alpha = self.flash_alpha.max(MAX_FLASH_ALPHA * fraction);
+                    }
+                },
+                Element::Dark       => self.dim_alpha += MAX_DIM_ALPHA * fraction,
+                //OPT: *DESIGN* Earth and Light don't have effects yet
+                _ => {}
+            }
+        }
+
+        self.dim_alpha = self.dim_alpha.min(MAX_DIM_ALPHA);
+    }
+
+    pub fn draw(&mut self, ggez_ctx: &mut GgEzContext) {
+        // Draw particles in a single batch
+        self.particles.draw(ggez_ctx);
+
+        // Draw full-screen dimming and flashes over the particles
+        if self.dim_alpha > 0.0 {
+            let mut dim_color = colors::BLACK;
+            dim_color.a = self.dim_alpha;
+            self.draw_screen_tint(dim_color, ggez_ctx);
+        }
+
+        if self.flash_alpha > 0.0 {
+            let mut flash_color = colors::WHITE;
+            flash_color.a = self.flash_alpha;
+            self.draw_screen_tint(flash_color, ggez_ctx);
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Helper Methods    *
+     *  *  *  *  *  *  *  */
+
+    /// Spawns the given (possibly fractional) number of particles, carrying any remainder over to the next update
+    fn spawn(&mut self, spawner: Spawner, count: f32) {
+        let rng = &mut self.rng;
+
+        self.spawn_debt[spawner as usize] += count;
+        while self.spawn_debt[spawner as usize] >= 1.0 {
+            self.spawn_debt[spawner as usize] -= 1.0;
+
+            let spec = match spawner {
+                // Fast, slightly slanted streaks falling from the top
+                Spawner::Rain => {
+                    let vel = ggez_mint::Vector2 {x: -40.0, y: rng.gen_range(550.0, 650.0)};
+                    ParticleSpec {
+                        pos:        ggez_mint::Point2 {x: rng.gen_range(self.bounds.x, self.bounds.right()), y: self.bounds.y},
+                        vel,
+                        size:       ggez_mint::Vector2 {x: 1.5, y: 14.0},
+                        rotation:   -vel.x.atan2(vel.y),
+                        spin:       0.0,
+                        lifetime:   2.0,
+                        color:      ggez_gfx::Color {a: 0.6, ..colors::BLUE},
+                    }
+                },
+                // Slow, wandering flakes falling from the top
+                Spawner::Snow => ParticleSpec {
+                    pos:        ggez_mint::Point2 {x: rng.gen_range(self.bounds.x, self.bounds.right()), y: self.bounds.y},
+                    vel:        ggez_mint::Vector2 {x: rng.gen_range(-20.0, 20.0), y: rng.gen_range(60.0, 100.0)},
+                    size:       ggez_mint::Vector2 {x: 3.0, y: 3.0},
+                    rotation:   0.0,
+                    spin:       rng.gen_range(-1.0, 1.0),
+                    lifetime:   12.0,
+                    color:      ggez_gfx::Color {a: 0.8, ..colors::WHITE},
+                },
+                // Tumbling leaves blown in from the left
+                Spawner::Leaves => ParticleSpec {
+                    pos:        ggez_mint::Point2 {x: self.bounds.x, y: rng.gen_range(self.bounds.y, self.bounds.bottom())},
+                    vel:        ggez_mint::Vector2 {x: rng.gen_range(200.0, 300.0), y: rng.gen_range(-20.0, 20.0)},
+                    size:       ggez_mint::Vector2 {x: 6.0, y: 3.0},
+                    rotation:   rng.gen_range(0.0, 2.0 * PI),
+                    spin:       rng.gen_range(-3.0, 3.0),
+                    lifetime:   6.0,
+                    color:      if rng.gen::<bool>() { colors::GREEN } else { colors::BROWN },
+                },
+                // Glowing embers rising from the bottom
+                Spawner::Embers => ParticleSpec {
+                    pos:        ggez_mint::Point2 {x: rng.gen_range(self.bounds.x, self.bounds.right()), y: self.bounds.bottom() - 1.0},
+                    vel:        ggez_mint::Vector2 {x: rng.gen_range(-15.0, 15.0), y: rng.gen_range(-140.0, -80.0)},
+                    size:       ggez_mint::Vector2 {x: 2.5, y: 2.5},
+                    rotation:   0.0,
+                    spin:       0.0,
+                    lifetime:   4.0,
+                    color:      if rng.gen::<bool>() { colors::ORANGE } else { colors::RED },
+                },
+            };
+
+            // Once the pool is exhausted, drop the rest of this update's particles
+            if !self.particles.spawn(spec) {
+                self.spawn_debt[spawner as usize] = 0.0;
+            }
+        }
+    }
+
+    /// Tints the whole covered area with the given color
+    fn draw_screen_tint(&self, color: ggez_gfx::Color, ggez_ctx: &mut GgEzContext) {
+        let tint_mesh = ggez_gfx::Mesh::new_rectangle(ggez_ctx,
+                                                      ggez_gfx::DrawMode::fill(),
+                                                      self.bounds,
+                                                      color).unwrap();
+
+        ggez_gfx::draw(ggez_ctx, &tint_mesh, ggez_gfx::DrawParam::default()).unwrap();
+    }
+}
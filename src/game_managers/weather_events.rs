@@ -0,0 +1,141 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/weather_events.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module defines the events published by the Weather Manager as the
+    global weather changes, and the observer trait for subscribing to them.
+
+    Closures and channel senders can both be used as observers, so that
+    gameplay code, scripts and tests can react to weather without polling.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    fmt,
+    sync::mpsc,
+    time::Duration,
+};
+
+use cast_iron::element::Element;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Where a weather event came from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeatherSource {
+    Random,
+    Script,
+}
+
+/// A change in the global weather, stamped with the game time it occurred
+#[derive(Clone, Debug, PartialEq)]
+pub enum WeatherEvent {
+    /// A new weather event has taken effect
+    Started {
+        at:         Duration,
+        element:    Element,
+        duration:   Duration,
+        source:     WeatherSource,
+    },
+    /// The displayed intensity of the active weather has changed
+    IntensityChanged {
+        at:                 Duration,
+        element:            Element,
+        intensity:          String,
+        intensity_exact:    f64,
+    },
+    /// The active weather has run its course, and is being replaced
+    Ended {
+        at:         Duration,
+        element:    Element,
+    },
+}
+
+/// Handle to a subscribed observer, used to unsubscribe it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObserverId(pub(crate) usize);
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Declarations
+///////////////////////////////////////////////////////////////////////////////
+
+/// A subscriber to weather events, e.g. a manager whose mechanics depend on the weather
+pub trait WeatherObserver {
+    /// Handles a single weather event
+    fn on_weather_event(&mut self, event: &WeatherEvent);
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl WeatherEvent {
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the game time at which the event occurred
+    pub fn at(&self) -> Duration {
+        match self {
+            WeatherEvent::Started {at, ..}          => *at,
+            WeatherEvent::IntensityChanged {at, ..} => *at,
+            WeatherEvent::Ended {at, ..}            => *at,
+        }
+    }
+
+    /// Returns the element of the weather the event describes
+    pub fn element(&self) -> Element {
+        match self {
+            WeatherEvent::Started {element, ..}             => *element,
+            WeatherEvent::IntensityChanged {element, ..}    => *element,
+            WeatherEvent::Ended {element, ..}               => *element,
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl<F: FnMut(&WeatherEvent)> WeatherObserver for F {
+    fn on_weather_event(&mut self, event: &WeatherEvent) {
+        self(event)
+    }
+}
+
+/// Forwards events to a channel, for consumers on other threads or that would rather drain a queue.
+/// Events sent after the receiver hangs up are discarded.
+impl WeatherObserver for mpsc::Sender<WeatherEvent> {
+    fn on_weather_event(&mut self, event: &WeatherEvent) {
+        let _ = self.send(event.clone());
+    }
+}
+
+impl fmt::Display for WeatherSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeatherSource::Random   => write!(f, "random"),
+            WeatherSource::Script   => write!(f, "script"),
+        }
+    }
+}
@@ -16,12 +16,18 @@ Copyright (C) 2020 CJ McAllister
 
 Purpose:
     This module manages weather effects over the course of the game, including
-    but not limited to generating random weather events.
+    but not limited to generating random weather events, playing back
+    scripted weather timelines, forecasting upcoming weather, crossfading
+    between weather events, drawing weather effects over the grid, and
+    tracking regional weather events as they drift across the hex grid.
+
+    Changes to the global weather are published to subscribed observers.
 
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
 use cast_iron::{
     context::Context as CastIronContext,
+    coords,
     element::{
         Element,
         Elemental,
@@ -30,11 +36,18 @@ use cast_iron::{
     Randomizable,
 };
 
+use std::{
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+    time::Duration,
+};
+
 use ggez::{
     Context as GgEzContext,
     graphics as ggez_gfx,
     mint as ggez_mint,
-    timer as ggez_timer,
 };
 
 use mt_logger::{
@@ -42,9 +55,45 @@ use mt_logger::{
     Level,
 };
 
+use rand::{
+    rngs::StdRng,
+    SeedableRng,
+};
+
 use crate::{
-    game_assets::colors,
-    profiler,
+    game_assets::{
+        colors,
+        hex_grid_cell::HexGridCell,
+    },
+    game_clock::GameClock,
+    game_managers::{
+        self,
+        weather_effects::WeatherEffects,
+        weather_events::{
+            ObserverId,
+            WeatherEvent,
+            WeatherObserver,
+            WeatherSource,
+        },
+        weather_region::{
+            CellWeather,
+            WeatherRegion,
+        },
+        weather_script::{
+            ScriptEnd,
+            ScriptedEvent,
+            WeatherScript,
+        },
+        weather_transition::{
+            self,
+            Transition,
+        },
+        world_grid_manager::WorldGridManager,
+    },
+    profiler::{
+        self,
+        metrics_format::EventAttribute,
+    },
 };
 
 
@@ -60,6 +109,36 @@ const HUD_OUTLINE_LINE_COLOR:   ggez_gfx::Color = colors::MAGENTA;
 // Offset of text from HUD frame
 const HUD_TEXT_OFFSET:          f32 = 5.0;
 
+/// Number of upcoming weather events held in the forecast queue
+const FORECAST_LENGTH:          usize = 5;
+
+// Layout of the forecast strip
+const FORECAST_SLOT_SIZE:       f32 = 16.0;
+const FORECAST_SLOT_PITCH:      f32 = 48.0;
+const FORECAST_TEXT_SIZE:       f32 = 12.0;
+
+/// Span of game time covered by the intensity history graph
+const HISTORY_WINDOW:           Duration = Duration::from_secs(60);
+
+/// Interval between samples of the intensity history graph
+const HISTORY_SAMPLE_INTERVAL:  Duration = Duration::from_millis(250);
+
+// Layout of the intensity history graph
+const HISTORY_GRAPH_HEIGHT:     f32 = 60.0;
+const HISTORY_GRAPH_COLOR:      ggez_gfx::Color = colors::GREEN;
+
+/// Number of randomly-generated regional events kept active at once
+const MAX_RAND_REGIONAL_EVENTS: usize = 2;
+
+/// Alpha level of a regional weather overlay cell at max intensity
+const REGIONAL_OVERLAY_MAX_ALPHA:   f32 = 0.5;
+
+/// Number of alpha levels a regional weather overlay cell steps through, so small intensity changes don't rebuild the overlay
+const REGIONAL_OVERLAY_ALPHA_STEPS: u8 = 16;
+
+/// Salt mixed into the run's seed for the regional weather RNG
+const REGIONAL_SEED_SALT:       u64 = 0x5245_4749_4f4e;
+
 
 ///////////////////////////////////////////////////////////////////////////////
 // Data Structures
@@ -67,12 +146,47 @@ const HUD_TEXT_OFFSET:          f32 = 5.0;
 
 pub struct WeatherManager {
     profiler:       profiler::Instance,
-    active_weather: weather::Event,
+    active_weather: ActiveWeather,
     timeout_ms:     u128,
-    prev_intensity: weather::Intensity,
+    prev_intensity: String,
+    script:         Option<WeatherScript>,
+    script_idx:     usize,
+    forecast:       VecDeque<PendingWeather>,
+    history:        VecDeque<(Duration, f64)>,
+    crossfade:      Duration,
+    outgoing:       Option<(ActiveWeather, Transition)>,
+    effects:        WeatherEffects,
+    last_update:    Duration,
+    observers:      Vec<(ObserverId, Box<dyn WeatherObserver>)>,
+    next_observer:  usize,
+    rng:            StdRng,
+    regional:       Vec<RegionalEvent>,
+    cell_weather:   HashMap<coords::Position, Vec<CellWeather>>,
+    overlay_tints:  HashMap<coords::Position, (Element, u8)>,   // Element and alpha step each overlay cell was drawn with
+    overlay_mesh:   ggez_gfx::Mesh,
     hud_elements:   HudElements
 }
 
+/// Weather event confined to a (possibly drifting) region of the grid
+struct RegionalEvent {
+    weather:    ActiveWeather,
+    region:     WeatherRegion,
+    start:      Duration,
+}
+
+/// Forecast weather event, yet to start
+enum PendingWeather {
+    Random(weather::Event),
+    Scripted(ScriptedEvent),
+}
+
+/// Weather event currently in effect, either randomly-generated or from a script
+enum ActiveWeather {
+    Random(weather::Event),
+    /// Scripted event, and the game time at which it started
+    Scripted(ScriptedEvent, Duration),
+}
+
 struct HudElements {
     pub frame_pos:      ggez_mint::Point2<f32>,
     pub frame_size:     f32,
@@ -85,6 +199,11 @@ struct HudElements {
     pub text_int_pos:   ggez_mint::Point2<f32>,
     pub text_int_str:   String,
     pub text_int_obj:   ggez_gfx::Text,
+    pub forecast_pos:   ggez_mint::Point2<f32>,
+    pub forecast_mesh:  ggez_gfx::Mesh,
+    pub forecast_text:  Vec<(ggez_mint::Point2<f32>, ggez_gfx::Text)>,
+    pub history_pos:    ggez_mint::Point2<f32>,
+    pub history_mesh:   ggez_gfx::Mesh,
 }
 
 
@@ -104,9 +223,24 @@ impl WeatherManager {
 
         WeatherManager {
             profiler:       profiler_clone,
-            active_weather, 
+            active_weather: ActiveWeather::Random(active_weather),
             timeout_ms,
-            prev_intensity: weather::Intensity::default(),
+            prev_intensity: String::default(),
+            script:         None,
+            script_idx:     0,
+            forecast:       VecDeque::with_capacity(FORECAST_LENGTH),
+            history:        VecDeque::new(),
+            crossfade:      weather_transition::DEFAULT_CROSSFADE,
+            outgoing:       None,
+            effects:        WeatherEffects::new(ggez_ctx),
+            last_update:    Duration::default(),
+            observers:      Vec::new(),
+            next_observer:  0,
+            rng:            StdRng::from_entropy(),
+            regional:       Vec::new(),
+            cell_weather:   HashMap::new(),
+            overlay_tints:  HashMap::new(),
+            overlay_mesh:   empty_mesh(ggez_ctx),
             hud_elements:   HudElements::default(ci_ctx, ggez_ctx),
         }
     }
@@ -120,37 +254,172 @@ impl WeatherManager {
 
         WeatherManager {
             profiler:       profiler_clone,
-            active_weather: weather::Event::default(),
+            active_weather: ActiveWeather::Random(weather::Event::default()),
             timeout_ms:     u128::default(),
-            prev_intensity: weather::Intensity::default(),
+            prev_intensity: String::default(),
+            script:         None,
+            script_idx:     0,
+            forecast:       VecDeque::with_capacity(FORECAST_LENGTH),
+            history:        VecDeque::new(),
+            crossfade:      weather_transition::DEFAULT_CROSSFADE,
+            outgoing:       None,
+            effects:        WeatherEffects::new(ggez_ctx),
+            last_update:    Duration::default(),
+            observers:      Vec::new(),
+            next_observer:  0,
+            rng:            StdRng::from_entropy(),
+            regional:       Vec::new(),
+            cell_weather:   HashMap::new(),
+            overlay_tints:  HashMap::new(),
+            overlay_mesh:   empty_mesh(ggez_ctx),
             hud_elements:   HudElements::default(ci_ctx, ggez_ctx),
         }
     }
 
 
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the weather affecting the given cell, strongest first
+    pub fn weather_at(&self, position: &coords::Position) -> &[CellWeather] {
+        self.cell_weather.get(position).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Returns the element and duration of each upcoming weather event, soonest first
+    pub fn forecast(&self) -> Vec<(Element, Duration)> {
+        self.forecast.iter().map(|pending| (pending.element(), pending.duration())).collect()
+    }
+
+    /// Returns the length of the crossfade between weather events
+    pub fn crossfade(&self) -> Duration {
+        self.crossfade
+    }
+
+    /// Returns the global weather at the given game time, with each event's intensity scaled by its
+    /// weight in any crossfade in progress. Outgoing weather (if any) comes first.
+    pub fn global_weather_at(&self, elapsed_time: Duration, ci_ctx: &CastIronContext) -> Vec<CellWeather> {
+        let incoming_intensity = self.active_weather.intensity_exact(elapsed_time, ci_ctx);
+
+        match &self.outgoing {
+            Some((outgoing, transition)) => {
+                let incoming_weight = transition.incoming_weight(elapsed_time);
+                // Hold the outgoing event at its final intensity while it fades out
+                let outgoing_intensity = outgoing.intensity_exact(transition.start(), ci_ctx);
+
+                vec![CellWeather::new(outgoing.element(), outgoing_intensity * (1.0 - incoming_weight)),
+                     CellWeather::new(self.active_weather.element(), incoming_intensity * incoming_weight)]
+            },
+            None => vec![CellWeather::new(self.active_weather.element(), incoming_intensity)],
+        }
+    }
+
+    /// Returns the global intensity at the given game time, blended across any crossfade in progress
+    pub fn intensity_exact_at(&self, elapsed_time: Duration, ci_ctx: &CastIronContext) -> f64 {
+        self.global_weather_at(elapsed_time, ci_ctx).iter().map(|cell_weather| cell_weather.intensity_exact()).sum()
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Subscribes an observer to all future weather events, returning a handle for unsubscribing it
+    pub fn subscribe(&mut self, observer: Box<dyn WeatherObserver>) -> ObserverId {
+        let observer_id = ObserverId(self.next_observer);
+        self.next_observer += 1;
+
+        self.observers.push((observer_id, observer));
+
+        observer_id
+    }
+
+    /// Unsubscribes the given observer, returning it if it was subscribed
+    pub fn unsubscribe(&mut self, observer_id: ObserverId) -> Option<Box<dyn WeatherObserver>> {
+        let idx = self.observers.iter().position(|(id, _observer)| *id == observer_id)?;
+
+        Some(self.observers.remove(idx).1)
+    }
+
+    /// Reseeds the RNGs behind regional weather and weather effects from the run's seed.
+    /// Random global weather comes from cast_iron, which can't be seeded.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = game_managers::seeded_rng(seed, REGIONAL_SEED_SALT);
+        self.effects.set_seed(seed);
+    }
+
+    /// Sets the length of the crossfade between weather events. A zero duration switches instantly.
+    pub fn set_crossfade(&mut self, crossfade: Duration) {
+        self.crossfade = crossfade;
+    }
+
+    /// Plays back the given script from its first event, replacing the active weather on the next update
+    pub fn play_script(&mut self, script: WeatherScript) {
+        self.script = Some(script);
+        self.script_idx = 0;
+        self.forecast.clear();
+        self.timeout_ms = 0;
+    }
+
+
     /*  *  *  *  *  *  *  *
      *  Utility Methods   *
      *  *  *  *  *  *  *  */
 
-    /// Updates the active weather if the current effect has timed out
-    pub fn update_weather(&mut self, ci_ctx: &CastIronContext, ggez_ctx: &mut GgEzContext) {
-        //OPT: *PERFORMANCE* Would it be faster to use 2 usizes for seconds and milli/nanoseconds?
-        let elapsed_time = ggez_timer::time_since_start(ggez_ctx);
+    /// Updates the active weather if the current effect has timed out, as of the game clock's time
+    pub fn update_weather(&mut self,
+                          clock: &GameClock,
+                          world_grid_manager: &WorldGridManager,
+                          ci_ctx: &CastIronContext,
+                          ggez_ctx: &mut GgEzContext) {
+        self.update_weather_at(clock.elapsed(), world_grid_manager, ci_ctx, ggez_ctx);
+    }
+
+    /// Updates the active weather as of the given game time
+    pub fn update_weather_at(&mut self,
+                             elapsed_time: Duration,
+                             world_grid_manager: &WorldGridManager,
+                             ci_ctx: &CastIronContext,
+                             ggez_ctx: &mut GgEzContext) {
         let mut new_weather_generated = false;
 
-        // If current weather has timed out, randomly generate a new weather pattern
+        // Finish off any completed crossfade
+        if self.outgoing.as_ref().map_or(false, |(_outgoing, transition)| transition.is_complete(elapsed_time)) {
+            self.outgoing = None;
+        }
+
+        // If current weather has timed out, start the next forecast weather pattern
         if elapsed_time.as_millis() >= self.timeout_ms {
             // Send WEATHER_GEN event marker to profiler
-            self.profiler.mark_event(String::from("WEATHER_GEN_START"), ggez_ctx).unwrap();
-
-            self.active_weather = weather::Event::rand(ci_ctx).starting_at(elapsed_time);
+            self.profiler.mark_event("WEATHER_GEN_START", ggez_ctx).unwrap();
+
+            let incoming = self.next_forecast_event(ci_ctx).start(elapsed_time);
+            let outgoing = std::mem::replace(&mut self.active_weather, incoming);
+            let had_weather = self.timeout_ms != 0;
+
+            if had_weather {
+                self.publish(WeatherEvent::Ended {
+                    at:         elapsed_time,
+                    element:    outgoing.element(),
+                });
+            }
+
+            // Crossfade from the outgoing weather, unless there was none (first update, or a script was just loaded).
+            // The fade never outlasts the incoming event, so that it completes before the next event replaces it.
+            self.outgoing = if had_weather && self.crossfade > Duration::default() {
+                Some((outgoing, Transition::new(elapsed_time, self.crossfade.min(self.active_weather.duration()))))
+            }
+            else {
+                None
+            };
 
             // Log weather change
             mt_log!(Level::Info,
-                "GameTime: {:.3}s: Weather changed to Elem: {:?}, Duration: {:.3}s",
+                "GameTime: {:.3}s: Weather changed to Elem: {:?}, Duration: {:.3}s, Source: {}",
                 elapsed_time.as_secs_f64(),
                 self.active_weather.element(),
-                self.active_weather.duration().as_secs_f64()
+                self.active_weather.duration().as_secs_f64(),
+                self.active_weather.source()
             );
 
             // Set the timeout to the duration of the new weather pattern
@@ -158,39 +427,362 @@ impl WeatherManager {
 
             new_weather_generated = true;
             
-            // Send WEATHER_GEN event marker to profiler
-            self.profiler.mark_event(String::from("WEATHER_GEN_STOP"), ggez_ctx).unwrap();
+            // Send WEATHER_GEN event marker to profiler, describing the new weather
+            self.profiler.mark_event_with_attributes(
+                "WEATHER_GEN_STOP",
+                || vec![EventAttribute::new("element", String::from(self.active_weather.element())),
+                        EventAttribute::new("duration_s", self.active_weather.duration().as_secs_f64()),
+                        EventAttribute::new("source", self.active_weather.source().to_string())],
+                ggez_ctx).unwrap();
+
+            self.publish(WeatherEvent::Started {
+                at:         elapsed_time,
+                element:    self.active_weather.element(),
+                duration:   self.active_weather.duration(),
+                source:     self.active_weather.source(),
+            });
+
+            // Update forecast strip with the new upcoming weather
+            let forecast = self.forecast();
+            self.hud_elements.update_forecast_elements(&forecast, ggez_ctx);
         }
 
         // Check for change in weather event
-        let cur_intensity = self.active_weather.intensity(elapsed_time.as_secs_f64());
-        if self.prev_intensity != cur_intensity || new_weather_generated {
-            // Send WEATHER_GEN event marker to profiler
-            self.profiler.mark_event(String::from("WEATHER_CHANGE_START"), ggez_ctx).unwrap();
+        let cur_intensity = self.active_weather.intensity_label(elapsed_time, ci_ctx);
+        let intensity_changed = self.prev_intensity != cur_intensity;
+        if intensity_changed || new_weather_generated {
+            // Send WEATHER_CHANGE event marker to profiler
+            self.profiler.mark_event("WEATHER_CHANGE_START", ggez_ctx).unwrap();
 
             // Update HUD content with new alpha level
-            let mut content_color = colors::from_element(self.active_weather.element());
-            content_color.a = cur_intensity.to_alpha();
+            let content_color = self.content_color(elapsed_time, ci_ctx);
             self.hud_elements.update_content_mesh(content_color, ggez_ctx);
 
             // Update intensity text
-            self.hud_elements.update_text_elements(self.active_weather.element(), cur_intensity);
+            self.hud_elements.update_text_elements(self.active_weather.element(), &cur_intensity);
 
-            // Update previous-state values
-            self.prev_intensity = self.active_weather.intensity(elapsed_time.as_secs_f64());
+            if intensity_changed {
+                self.publish(WeatherEvent::IntensityChanged {
+                    at:                 elapsed_time,
+                    element:            self.active_weather.element(),
+                    intensity:          cur_intensity.clone(),
+                    intensity_exact:    self.active_weather.intensity_exact(elapsed_time, ci_ctx),
+                });
+            }
 
-            // Send WEATHER_GEN event marker to profiler
-            self.profiler.mark_event(String::from("WEATHER_CHANGE_STOP"), ggez_ctx).unwrap();
+            // Update previous-state values
+            self.prev_intensity = cur_intensity;
+
+            // Send WEATHER_CHANGE event marker to profiler, describing the new intensity and the particles drawn for it
+            self.profiler.mark_event_with_attributes(
+                "WEATHER_CHANGE_STOP",
+                || vec![EventAttribute::new("intensity", self.hud_elements.text_int_str.clone()),
+                        EventAttribute::new("particles", self.effects.live_particle_count())],
+                ggez_ctx).unwrap();
+        }
+        else if self.outgoing.is_some() {
+            // Keep blending the HUD content through the crossfade
+            let content_color = self.content_color(elapsed_time, ci_ctx);
+            self.hud_elements.update_content_mesh(content_color, ggez_ctx);
         }
 
         // Update intensity bar
-        self.hud_elements.update_int_bar_mesh(self.active_weather.intensity_exact(elapsed_time.as_secs_f64()), ci_ctx, ggez_ctx);
+        let cur_intensity_exact = self.intensity_exact_at(elapsed_time, ci_ctx);
+        self.hud_elements.update_int_bar_mesh(cur_intensity_exact, ci_ctx, ggez_ctx);
+
+        // Sample intensity history, dropping samples that have scrolled off the graph
+        let sample_due = self.history.back().map_or(true, |(sample_time, _)| elapsed_time >= *sample_time + HISTORY_SAMPLE_INTERVAL);
+        if sample_due {
+            self.history.push_back((elapsed_time, cur_intensity_exact));
+            while self.history.front().map_or(false, |(sample_time, _)| *sample_time + HISTORY_WINDOW < elapsed_time) {
+                self.history.pop_front();
+            }
+
+            self.hud_elements.update_history_mesh(&self.history, elapsed_time, ci_ctx, ggez_ctx);
+        }
+
+        // Advance weather effects by the game time since the last update
+        let delta_time = elapsed_time.checked_sub(self.last_update).unwrap_or_default();
+        self.last_update = elapsed_time;
+
+        let global_weather = self.global_weather_at(elapsed_time, ci_ctx);
+        self.effects.update(&global_weather, ci_ctx.max_weather_intensity(), delta_time.as_secs_f32());
+
+        // Update weather over regions of the grid
+        self.update_regional_weather(elapsed_time, world_grid_manager, ci_ctx, ggez_ctx);
     }
 
     pub fn draw(&self, ggez_ctx: &mut GgEzContext) {
         // Draw HUD elements
         self.hud_elements.draw(ggez_ctx);
     }
+
+    /// Draws the particle and screen effects of the global weather
+    pub fn draw_effects(&mut self, ggez_ctx: &mut GgEzContext) {
+        self.effects.draw(ggez_ctx);
+    }
+
+    /// Draws the tinted overlay of cells affected by regional weather
+    pub fn draw_regional_overlay(&self, ggez_ctx: &mut GgEzContext) {
+        ggez_gfx::draw(ggez_ctx, &self.overlay_mesh, ggez_gfx::DrawParam::default()).unwrap();
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Helper Methods    *
+     *  *  *  *  *  *  *  */
+
+    /// Expires and spawns regional events, then recomputes per-cell weather, rebuilding the overlay mesh if its tints changed
+    fn update_regional_weather(&mut self,
+                               elapsed_time: Duration,
+                               world_grid_manager: &WorldGridManager,
+                               ci_ctx: &CastIronContext,
+                               ggez_ctx: &mut GgEzContext) {
+        // Drop any events that have run their course
+        self.regional.retain(|regional_event| elapsed_time < regional_event.start + regional_event.weather.duration());
+
+        // Keep the grid stocked with random regional events
+        while self.regional.iter().filter(|regional_event| matches!(regional_event.weather, ActiveWeather::Random(_))).count() < MAX_RAND_REGIONAL_EVENTS {
+            let regional_event = RegionalEvent {
+                weather:    ActiveWeather::Random(weather::Event::rand(ci_ctx).starting_at(elapsed_time)),
+                region:     WeatherRegion::rand(world_grid_manager.radial_size(), &mut self.rng, ci_ctx),
+                start:      elapsed_time,
+            };
+
+            mt_log!(Level::Debug,
+                "GameTime: {:.3}s: Regional weather started with Elem: {:?}, Duration: {:.3}s, Region: {:?}",
+                elapsed_time.as_secs_f64(),
+                regional_event.weather.element(),
+                regional_event.weather.duration().as_secs_f64(),
+                regional_event.region
+            );
+
+            self.profiler.mark_event_with_attributes(
+                "REGIONAL_WEATHER_GEN",
+                || vec![EventAttribute::new("element", String::from(regional_event.weather.element())),
+                        EventAttribute::new("duration_s", regional_event.weather.duration().as_secs_f64())],
+                ggez_ctx).unwrap();
+
+            self.regional.push(regional_event);
+        }
+
+        // Collect the weather over each affected cell, strengthened or weakened by the terrain beneath it
+        self.cell_weather.clear();
+        for regional_event in &self.regional {
+            let element = regional_event.weather.element();
+            let intensity = regional_event.weather.intensity_exact(elapsed_time, ci_ctx);
+            let time_into_event = elapsed_time.checked_sub(regional_event.start).unwrap_or_default();
+
+            for cell in regional_event.region.cells_at(time_into_event, ci_ctx) {
+                let cell_intensity = (intensity * world_grid_manager.weather_modifier(&cell, element)).min(ci_ctx.max_weather_intensity());
+                self.cell_weather.entry(cell).or_insert_with(Vec::new).push(CellWeather::new(element, cell_intensity));
+            }
+        }
+
+        for cell_weather in self.cell_weather.values_mut() {
+            cell_weather.sort_by(|a, b| b.intensity_exact().partial_cmp(&a.intensity_exact()).unwrap());
+        }
+
+        // Tint each affected cell by its strongest weather, only rebuilding the overlay if a tint has changed
+        let overlay_tints: HashMap<coords::Position, (Element, u8)> = self.cell_weather.iter()
+            .map(|(position, cell_weather)| {
+                let strongest = cell_weather[0];
+                let alpha_step = (strongest.intensity_exact() / ci_ctx.max_weather_intensity() * REGIONAL_OVERLAY_ALPHA_STEPS as f64).round() as u8;
+
+                (*position, (strongest.element(), alpha_step))
+            })
+            .collect();
+
+        if overlay_tints == self.overlay_tints {
+            return;
+        }
+
+        let mut overlay_mesh_builder = ggez_gfx::MeshBuilder::new();
+        let dummy_line = [ggez_mint::Point2 {x: 0.0, y: 0.0}, ggez_mint::Point2 {x: 1.0, y: 1.0}];
+        overlay_mesh_builder.line(&dummy_line, 1.0, colors::TRANSPARENT).unwrap();
+
+        for (position, (element, alpha_step)) in &overlay_tints {
+            let mut tint = colors::from_element(*element);
+            tint.a = *alpha_step as f32 / REGIONAL_OVERLAY_ALPHA_STEPS as f32 * REGIONAL_OVERLAY_MAX_ALPHA;
+
+            let hex_cell = HexGridCell::new_from_hex_coords(position, crate::HEX_RADIUS_VERTEX, ggez_ctx);
+            hex_cell.add_to_mesh(tint, colors::TRANSPARENT, &mut overlay_mesh_builder);
+        }
+
+        self.overlay_mesh = overlay_mesh_builder.build(ggez_ctx).unwrap();
+        self.overlay_tints = overlay_tints;
+    }
+
+    /// Hands the given event to every observer, in order of subscription
+    fn publish(&mut self, event: WeatherEvent) {
+        for (_id, observer) in self.observers.iter_mut() {
+            observer.on_weather_event(&event);
+        }
+    }
+
+    /// Returns the HUD content color at the given game time, blended across any crossfade in progress
+    fn content_color(&self, elapsed_time: Duration, ci_ctx: &CastIronContext) -> ggez_gfx::Color {
+        let mut incoming_color = colors::from_element(self.active_weather.element());
+        incoming_color.a = self.active_weather.alpha(elapsed_time, ci_ctx);
+
+        let (outgoing, transition) = match &self.outgoing {
+            Some(outgoing) => outgoing,
+            None => return incoming_color,
+        };
+
+        let mut outgoing_color = colors::from_element(outgoing.element());
+        outgoing_color.a = outgoing.alpha(transition.start(), ci_ctx);
+
+        weather_transition::blend_color(outgoing_color, incoming_color, transition.incoming_weight(elapsed_time))
+    }
+
+    /// Pops the next event from the forecast, topping the forecast back up behind it
+    fn next_forecast_event(&mut self, ci_ctx: &CastIronContext) -> PendingWeather {
+        self.fill_forecast(ci_ctx);
+        let next_event = self.forecast.pop_front().expect("Forecast cannot be empty after filling");
+        self.fill_forecast(ci_ctx);
+
+        next_event
+    }
+
+    /// Queues upcoming events until the forecast is full, taking from the script first
+    fn fill_forecast(&mut self, ci_ctx: &CastIronContext) {
+        while self.forecast.len() < FORECAST_LENGTH {
+            let pending = match self.next_scripted_event() {
+                Some(scripted_event) => PendingWeather::Scripted(scripted_event),
+                None => PendingWeather::Random(weather::Event::rand(ci_ctx)),
+            };
+
+            self.forecast.push_back(pending);
+        }
+    }
+
+    /// Advances the script (if any) to its next event, looping or dropping the script once it runs out
+    fn next_scripted_event(&mut self) -> Option<ScriptedEvent> {
+        let (event_count, end) = match &self.script {
+            Some(script) => (script.events().len(), script.end()),
+            None => return None,
+        };
+
+        if self.script_idx >= event_count {
+            match end {
+                ScriptEnd::Loop => {
+                    mt_log!(Level::Info, "Weather script fully forecast, looping");
+                    self.script_idx = 0;
+                },
+                ScriptEnd::ThenRandom => {
+                    mt_log!(Level::Info, "Weather script fully forecast, falling back to random weather");
+                    self.script = None;
+                    return None;
+                },
+            }
+        }
+
+        let scripted_event = self.script.as_ref().map(|script| script.events()[self.script_idx].clone());
+        self.script_idx += 1;
+
+        scripted_event
+    }
+}
+
+
+impl PendingWeather {
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    fn element(&self) -> Element {
+        match self {
+            PendingWeather::Random(event)   => event.element(),
+            PendingWeather::Scripted(event) => event.element(),
+        }
+    }
+
+    fn duration(&self) -> Duration {
+        match self {
+            PendingWeather::Random(event)   => event.duration(),
+            PendingWeather::Scripted(event) => event.duration(),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Puts the event into effect at the given game time
+    fn start(self, elapsed_time: Duration) -> ActiveWeather {
+        match self {
+            PendingWeather::Random(event)   => ActiveWeather::Random(event.starting_at(elapsed_time)),
+            PendingWeather::Scripted(event) => ActiveWeather::Scripted(event, elapsed_time),
+        }
+    }
+}
+
+
+impl ActiveWeather {
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    fn element(&self) -> Element {
+        match self {
+            ActiveWeather::Random(event)            => event.element(),
+            ActiveWeather::Scripted(event, _start)  => event.element(),
+        }
+    }
+
+    fn duration(&self) -> Duration {
+        match self {
+            ActiveWeather::Random(event)            => event.duration(),
+            ActiveWeather::Scripted(event, _start)  => event.duration(),
+        }
+    }
+
+    /// Describes where the event came from, for logs and event markers
+    fn source(&self) -> WeatherSource {
+        match self {
+            ActiveWeather::Random(_event)           => WeatherSource::Random,
+            ActiveWeather::Scripted(_event, _start) => WeatherSource::Script,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the absolute intensity (0 to the max weather intensity) at the given game time
+    fn intensity_exact(&self, elapsed_time: Duration, ci_ctx: &CastIronContext) -> f64 {
+        match self {
+            ActiveWeather::Random(event) => event.intensity_exact(elapsed_time.as_secs_f64()),
+            ActiveWeather::Scripted(event, start) => {
+                event.intensity_fraction(elapsed_time.checked_sub(*start).unwrap_or_default()) * ci_ctx.max_weather_intensity()
+            },
+        }
+    }
+
+    /// Returns the HUD text describing the intensity at the given game time
+    fn intensity_label(&self, elapsed_time: Duration, ci_ctx: &CastIronContext) -> String {
+        match self {
+            ActiveWeather::Random(event) => String::from(event.intensity(elapsed_time.as_secs_f64())),
+            ActiveWeather::Scripted(_event, _start) => {
+                format!("{:.0}%", 100.0 * self.intensity_exact(elapsed_time, ci_ctx) / ci_ctx.max_weather_intensity())
+            },
+        }
+    }
+
+    /// Returns the alpha level of the HUD content at the given game time
+    fn alpha(&self, elapsed_time: Duration, ci_ctx: &CastIronContext) -> f32 {
+        match self {
+            ActiveWeather::Random(event) => event.intensity(elapsed_time.as_secs_f64()).to_alpha(),
+            ActiveWeather::Scripted(_event, _start) => {
+                (self.intensity_exact(elapsed_time, ci_ctx) / ci_ctx.max_weather_intensity()) as f32
+            },
+        }
+    }
 }
 
 
@@ -236,13 +828,21 @@ impl HudElements {
                                                y: calc_frame_pos.y + calc_frame_size + HUD_TEXT_OFFSET},
             text_int_str:   String::default(),
             text_int_obj:   ggez_gfx::Text::default(),
+            forecast_pos:   ggez_mint::Point2{ x: calc_frame_pos.x,
+                                               y: calc_frame_pos.y + calc_frame_size + crate::DEFAULT_TEXT_SIZE + 2.0 * HUD_TEXT_OFFSET},
+            forecast_mesh:  empty_mesh(ggez_ctx),
+            forecast_text:  Vec::new(),
+            history_pos:    ggez_mint::Point2{ x: calc_frame_pos.x,
+                                               y: calc_frame_pos.y + calc_frame_size + crate::DEFAULT_TEXT_SIZE + 2.0 * HUD_TEXT_OFFSET
+                                                  + FORECAST_SLOT_SIZE + FORECAST_TEXT_SIZE + 2.0 * HUD_TEXT_OFFSET},
+            history_mesh:   empty_mesh(ggez_ctx),
         };
 
         // Do first 'updates' of the meshes so we have valid meshes from first use
         hud_elements.update_frame_mesh(ggez_ctx);
         hud_elements.update_content_mesh(colors::TRANSPARENT, ggez_ctx);
         hud_elements.update_int_bar_mesh(f64::default(), ci_ctx, ggez_ctx);
-        hud_elements.update_text_elements(Element::default(), weather::Intensity::default());
+        hud_elements.update_text_elements(Element::default(), &String::from(weather::Intensity::default()));
 
         hud_elements
     }
@@ -266,6 +866,13 @@ impl HudElements {
 
         // Draw intensity bar
         ggez_gfx::draw(ggez_ctx, &self.int_bar_mesh, ggez_gfx::DrawParam::default()).unwrap();
+
+        // Draw forecast strip and intensity history graph
+        ggez_gfx::draw(ggez_ctx, &self.forecast_mesh, ggez_gfx::DrawParam::default()).unwrap();
+        for (text_pos, text_obj) in &self.forecast_text {
+            ggez_gfx::draw(ggez_ctx, text_obj, (*text_pos, 0.0, colors::GREEN)).unwrap();
+        }
+        ggez_gfx::draw(ggez_ctx, &self.history_mesh, ggez_gfx::DrawParam::default()).unwrap();
     }
 
     /// Updates the frame mesh for the HUD (just a square outline for now)
@@ -319,8 +926,71 @@ impl HudElements {
                                                      .unwrap();
     }
 
+    /// Updates the forecast strip: one colored slot per upcoming event, labelled with its duration
+    fn update_forecast_elements(&mut self, forecast: &[(Element, Duration)], ggez_ctx: &mut GgEzContext) {
+        // Need a mesh builder with a dummy line to avoid an empty mesh
+        let mut forecast_mesh_builder = ggez_gfx::MeshBuilder::new();
+        let dummy_line = [ggez_mint::Point2 {x: 0.0, y: 0.0}, ggez_mint::Point2 {x: 1.0, y: 1.0}];
+        forecast_mesh_builder.line(&dummy_line, 1.0, colors::TRANSPARENT).unwrap();
+
+        self.forecast_text.clear();
+        for (i, (element, duration)) in forecast.iter().enumerate() {
+            let slot_x = self.forecast_pos.x + i as f32 * FORECAST_SLOT_PITCH;
+            let slot_y = self.forecast_pos.y;
+
+            let slot_corners = [ggez_mint::Point2 {x: slot_x,                        y: slot_y},
+                                ggez_mint::Point2 {x: slot_x + FORECAST_SLOT_SIZE,   y: slot_y},
+                                ggez_mint::Point2 {x: slot_x + FORECAST_SLOT_SIZE,   y: slot_y + FORECAST_SLOT_SIZE},
+                                ggez_mint::Point2 {x: slot_x,                        y: slot_y + FORECAST_SLOT_SIZE}];
+            forecast_mesh_builder.polygon(ggez_gfx::DrawMode::fill(), &slot_corners, colors::from_element(*element)).unwrap();
+            forecast_mesh_builder.polygon(ggez_gfx::DrawMode::stroke(1.0), &slot_corners, HUD_OUTLINE_LINE_COLOR).unwrap();
+
+            let duration_str = format!("{:.1}s", duration.as_secs_f64());
+            self.forecast_text.push((ggez_mint::Point2 {x: slot_x, y: slot_y + FORECAST_SLOT_SIZE + HUD_TEXT_OFFSET / 2.0},
+                                     ggez_gfx::Text::new((duration_str.as_str(),
+                                                          ggez_gfx::Font::default(),
+                                                          FORECAST_TEXT_SIZE))));
+        }
+
+        self.forecast_mesh = forecast_mesh_builder.build(ggez_ctx).unwrap();
+    }
+
+    /// Updates the scrolling graph of exact intensity over the history window, with the newest sample at the right edge
+    fn update_history_mesh(&mut self, history: &VecDeque<(Duration, f64)>, elapsed_time: Duration, ci_ctx: &CastIronContext, ggez_ctx: &mut GgEzContext) {
+        let graph_width = FORECAST_LENGTH as f32 * FORECAST_SLOT_PITCH;
+        let graph_x = self.history_pos.x;
+        let graph_y = self.history_pos.y;
+
+        // Need a mesh builder with a dummy line to avoid an empty mesh
+        let mut history_mesh_builder = ggez_gfx::MeshBuilder::new();
+        let dummy_line = [ggez_mint::Point2 {x: 0.0, y: 0.0}, ggez_mint::Point2 {x: 1.0, y: 1.0}];
+        history_mesh_builder.line(&dummy_line, 1.0, colors::TRANSPARENT).unwrap();
+
+        // Outline the graph area
+        let graph_corners = [ggez_mint::Point2 {x: graph_x,                 y: graph_y},
+                             ggez_mint::Point2 {x: graph_x + graph_width,   y: graph_y},
+                             ggez_mint::Point2 {x: graph_x + graph_width,   y: graph_y + HISTORY_GRAPH_HEIGHT},
+                             ggez_mint::Point2 {x: graph_x,                 y: graph_y + HISTORY_GRAPH_HEIGHT}];
+        history_mesh_builder.polygon(ggez_gfx::DrawMode::stroke(1.0), &graph_corners, HUD_OUTLINE_LINE_COLOR).unwrap();
+
+        // Plot the samples, scaled to the max intensity
+        let graph_points: Vec<ggez_mint::Point2<f32>> = history.iter().map(|(sample_time, exact_intensity)| {
+            let age = elapsed_time.checked_sub(*sample_time).unwrap_or_default().as_secs_f32() / HISTORY_WINDOW.as_secs_f32();
+            let fraction = (*exact_intensity / ci_ctx.max_weather_intensity()) as f32;
+
+            ggez_mint::Point2 {x: graph_x + graph_width * (1.0 - age.min(1.0)),
+                               y: graph_y + HISTORY_GRAPH_HEIGHT * (1.0 - fraction.max(0.0).min(1.0))}
+        }).collect();
+
+        if graph_points.len() >= 2 {
+            history_mesh_builder.line(&graph_points, 1.0, HISTORY_GRAPH_COLOR).unwrap();
+        }
+
+        self.history_mesh = history_mesh_builder.build(ggez_ctx).unwrap();
+    }
+
     /// Updates text elements of the HUD
-    fn update_text_elements(&mut self, element: Element, intensity: weather::Intensity) {
+    fn update_text_elements(&mut self, element: Element, intensity: &str) {
         // Update element text
         self.text_elem_str = String::from(element);
         self.text_elem_obj = ggez_gfx::Text::new((self.text_elem_str.as_str(),
@@ -334,3 +1004,17 @@ impl HudElements {
                                                 crate::DEFAULT_TEXT_SIZE));
     }
 }
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Builds a mesh holding only a transparent dummy line, since ggez cannot build an empty mesh
+fn empty_mesh(ggez_ctx: &mut GgEzContext) -> ggez_gfx::Mesh {
+    ggez_gfx::MeshBuilder::new()
+        .line(&[ggez_mint::Point2 {x: 0.0, y: 0.0}, ggez_mint::Point2 {x: 1.0, y: 1.0}], 1.0, colors::TRANSPARENT)
+        .unwrap()
+        .build(ggez_ctx)
+        .unwrap()
+}
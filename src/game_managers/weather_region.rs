@@ -0,0 +1,413 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/weather_region.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module will provide data structures and functions for describing the
+    spatial footprint of regional weather events on the hex grid, and how that
+    footprint drifts across the grid over the course of the event.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::time::Duration;
+
+use cast_iron::{
+    context::Context as CastIronContext,
+    coords,
+    element::Element,
+    hex_directions,
+};
+
+use rand::Rng;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Maximum radius (in cells) of a randomly-generated region
+const MAX_RAND_REGION_RADIUS:   usize = 3;
+
+/// Maximum length (in cells) of a randomly-generated front
+const MAX_RAND_FRONT_LENGTH:    usize = 7;
+
+/// Fraction of cells within its radius that a randomly-generated patch covers
+const RAND_PATCH_DENSITY:       f64 = 0.5;
+
+/// Maximum drift speed (in cells per second) of a randomly-generated region
+const MAX_RAND_DRIFT_SPEED:     f64 = 1.0;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Set of cells covered by a regional weather event, before any drift
+#[derive(Clone, Debug, PartialEq)]
+pub enum Footprint {
+    /// An explicit set of cells
+    Cells(Vec<coords::Position>),
+    /// All cells within the given radius (in cells) of a center cell
+    Radius(coords::Position, usize),
+    /// A line of the given length (in cells), starting at a cell and extending in a direction
+    Front(coords::Position, hex_directions::Side, usize),
+}
+
+/// Steady movement of a footprint across the grid
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Drift {
+    direction:      hex_directions::Side,
+    cells_per_sec:  f64,
+}
+
+/// Spatial extent of a regional weather event over its lifetime
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeatherRegion {
+    footprint:  Footprint,
+    drift:      Option<Drift>,
+}
+
+/// Weather affecting a single cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellWeather {
+    element:            Element,
+    intensity_exact:    f64,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl Footprint {
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the cells covered by the footprint, omitting any that fall off the grid
+    pub fn cells(&self, ci_ctx: &CastIronContext) -> Vec<coords::Position> {
+        match self {
+            Footprint::Cells(cells) => cells.clone(),
+            Footprint::Radius(center, radius) => {
+                let radius = *radius as i32;
+                let mut cells = Vec::new();
+
+                // Walk the cube-coordinate hexagon around the center, keeping the x + y + z == 0 constraint
+                for dx in -radius ..= radius {
+                    for dy in (-radius).max(-dx - radius) ..= radius.min(-dx + radius) {
+                        let dz = -dx - dy;
+                        if let Ok(cell) = coords::Position::new(center.x() + dx, center.y() + dy, center.z() + dz, ci_ctx) {
+                            cells.push(cell);
+                        }
+                    }
+                }
+
+                cells
+            },
+            Footprint::Front(start, direction, length) => {
+                let mut cells = vec![*start];
+                let mut cur_cell = *start;
+
+                for _step in 1..*length {
+                    if cur_cell.translate(&coords::Translation::from(*direction), ci_ctx).is_err() {
+                        break;
+                    }
+                    cells.push(cur_cell);
+                }
+
+                cells
+            },
+        }
+    }
+}
+
+
+impl Drift {
+    /// Fully-qualified constructor
+    pub fn new(direction: hex_directions::Side, cells_per_sec: f64) -> Self {
+        Self {
+            direction,
+            cells_per_sec,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn direction(&self) -> hex_directions::Side {
+        self.direction
+    }
+
+    pub fn cells_per_sec(&self) -> f64 {
+        self.cells_per_sec
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the number of whole cells drifted the given time into the event
+    pub fn steps(&self, time_into_event: Duration) -> usize {
+        (time_into_event.as_secs_f64() * self.cells_per_sec).max(0.0) as usize
+    }
+}
+
+
+impl WeatherRegion {
+    /// Fully-qualified constructor
+    pub fn new(footprint: Footprint, drift: Option<Drift>) -> Self {
+        Self {
+            footprint,
+            drift,
+        }
+    }
+
+    /// Returns a region of random shape, size and position within the given grid radius, possibly drifting
+    pub fn rand<R: Rng>(grid_radius: usize, rng: &mut R, ci_ctx: &CastIronContext) -> Self {
+        // Pick a random center on the grid, retrying until the cube-coordinate constraint is satisfied
+        let grid_radius = grid_radius as i32;
+        let center = loop {
+            let x = rng.gen_range(-grid_radius, grid_radius + 1);
+            let y = rng.gen_range(-grid_radius, grid_radius + 1);
+            if let Ok(center) = coords::Position::new(x, y, -x - y, ci_ctx) {
+                break center;
+            }
+        };
+
+        let footprint = match rng.gen_range(0, 3) {
+            0 => Footprint::Radius(center, rng.gen_range(1, MAX_RAND_REGION_RADIUS + 1)),
+            1 => Footprint::Front(center, rand_side(rng), rng.gen_range(2, MAX_RAND_FRONT_LENGTH + 1)),
+            // A patchy scattering of cells around the center, which is always covered
+            _ => {
+                let area = Footprint::Radius(center, rng.gen_range(1, MAX_RAND_REGION_RADIUS + 1));
+                Footprint::Cells(area.cells(ci_ctx)
+                                     .into_iter()
+                                     .filter(|cell| *cell == center || rng.gen::<f64>() < RAND_PATCH_DENSITY)
+                                     .collect())
+            },
+        };
+
+        // Half of random regions stay put
+        let drift = if rng.gen::<bool>() {
+            Some(Drift::new(rand_side(rng), rng.gen_range(0.1, MAX_RAND_DRIFT_SPEED)))
+        }
+        else {
+            None
+        };
+
+        Self::new(footprint, drift)
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn footprint(&self) -> &Footprint {
+        &self.footprint
+    }
+
+    pub fn drift(&self) -> Option<Drift> {
+        self.drift
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the cells covered the given time into the event, after drifting.
+    /// Cells that drift off the grid are omitted.
+    pub fn cells_at(&self, time_into_event: Duration, ci_ctx: &CastIronContext) -> Vec<coords::Position> {
+        let cells = self.footprint.cells(ci_ctx);
+
+        let drift = match self.drift {
+            Some(drift) => drift,
+            None => return cells,
+        };
+
+        let translation = coords::Translation::from(drift.direction());
+        let steps = drift.steps(time_into_event);
+
+        cells.into_iter()
+             .filter_map(|mut cell| {
+                 for _step in 0..steps {
+                     cell.translate(&translation, ci_ctx).ok()?;
+                 }
+                 Some(cell)
+             })
+             .collect()
+    }
+}
+
+
+impl CellWeather {
+    /// Fully-qualified constructor
+    pub fn new(element: Element, intensity_exact: f64) -> Self {
+        Self {
+            element,
+            intensity_exact,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn element(&self) -> Element {
+        self.element
+    }
+
+    /// Absolute intensity, from 0 to the context's max weather intensity
+    pub fn intensity_exact(&self) -> f64 {
+        self.intensity_exact
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns one of the six sides of a hex cell at random
+fn rand_side<R: Rng>(rng: &mut R) -> hex_directions::Side {
+    let mut directions: hex_directions::Provider<hex_directions::Side> = hex_directions::Provider::new(hex_directions::Side::North);
+    directions.nth(rng.gen_range(0, 6)).unwrap_or(hex_directions::Side::North)
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    use crate::game_managers::{
+        hex_distance,
+        seeded_rng,
+        test_helpers::{
+            TEST_GRID_RADIUS,
+            pos,
+            test_ctx,
+        },
+    };
+
+    fn offset(from: &coords::Position, to: &coords::Position) -> (i32, i32, i32) {
+        (to.x() - from.x(), to.y() - from.y(), to.z() - from.z())
+    }
+
+    #[test]
+    fn cells_footprint_is_taken_as_given() {
+        let ci_ctx = test_ctx();
+        let cells = vec![pos(0, 0, 0, &ci_ctx), pos(3, -1, -2, &ci_ctx)];
+
+        assert_eq!(Footprint::Cells(cells.clone()).cells(&ci_ctx), cells);
+    }
+
+    #[test]
+    fn radius_footprint_is_a_hexagon_clipped_to_the_grid() {
+        let ci_ctx = test_ctx();
+        let center = pos(0, 0, 0, &ci_ctx);
+
+        // A hexagon of radius r holds 1 + 3r(r + 1) cells
+        let cells = Footprint::Radius(center, 2).cells(&ci_ctx);
+        assert_eq!(cells.len(), 19);
+        assert!(cells.iter().all(|cell| hex_distance(&center, cell) <= 2));
+
+        // Only three of a corner cell's neighbours are on the grid
+        let radius = TEST_GRID_RADIUS as i32;
+        let corner = pos(radius, -radius, 0, &ci_ctx);
+        assert_eq!(Footprint::Radius(corner, 1).cells(&ci_ctx).len(), 4);
+    }
+
+    #[test]
+    fn front_footprint_is_a_straight_line_clipped_to_the_grid() {
+        let ci_ctx = test_ctx();
+        let start = pos(0, 0, 0, &ci_ctx);
+
+        let cells = Footprint::Front(start, hex_directions::Side::North, 4).cells(&ci_ctx);
+        assert_eq!(cells.len(), 4);
+        assert_eq!(cells[0], start);
+        assert!(cells.windows(2).all(|pair| hex_distance(&pair[0], &pair[1]) == 1));
+        assert_eq!(hex_distance(&start, &cells[3]), 3);
+
+        // A front longer than the room left on the grid stops at the edge
+        let long_cells = Footprint::Front(start, hex_directions::Side::North, 3 * TEST_GRID_RADIUS).cells(&ci_ctx);
+        assert_eq!(long_cells.len(), TEST_GRID_RADIUS + 1);
+    }
+
+    #[test]
+    fn drift_moves_whole_cells_over_time() {
+        let ci_ctx = test_ctx();
+        let center = pos(0, 0, 0, &ci_ctx);
+        let region = WeatherRegion::new(Footprint::Radius(center, 1), Some(Drift::new(hex_directions::Side::North, 0.5)));
+
+        // Nothing moves until a whole cell has been drifted
+        assert_eq!(region.cells_at(Duration::from_millis(1999), &ci_ctx), region.footprint().cells(&ci_ctx));
+
+        // After 4 seconds at half a cell per second, every cell has moved 2 cells the same way
+        let start_cells = region.footprint().cells(&ci_ctx);
+        let drifted_cells = region.cells_at(Duration::from_secs(4), &ci_ctx);
+        assert_eq!(drifted_cells.len(), start_cells.len());
+        for (start_cell, drifted_cell) in start_cells.iter().zip(drifted_cells.iter()) {
+            assert_eq!(hex_distance(start_cell, drifted_cell), 2);
+            assert_eq!(offset(start_cell, drifted_cell), offset(&start_cells[0], &drifted_cells[0]));
+        }
+    }
+
+    #[test]
+    fn cells_drifting_off_the_grid_are_dropped() {
+        let ci_ctx = test_ctx();
+        let center = pos(0, 0, 0, &ci_ctx);
+        let region = WeatherRegion::new(Footprint::Radius(center, 1), Some(Drift::new(hex_directions::Side::North, 1.0)));
+
+        // Drifting further than the grid radius pushes part of the hexagon off the grid, then all of it
+        let edge_cells = region.cells_at(Duration::from_secs(TEST_GRID_RADIUS as u64), &ci_ctx);
+        assert!(!edge_cells.is_empty() && edge_cells.len() < 7);
+        assert!(region.cells_at(Duration::from_secs(2 * TEST_GRID_RADIUS as u64 + 2), &ci_ctx).is_empty());
+    }
+
+    #[test]
+    fn still_region_never_moves() {
+        let ci_ctx = test_ctx();
+        let region = WeatherRegion::new(Footprint::Radius(pos(1, 1, -2, &ci_ctx), 1), None);
+
+        assert_eq!(region.cells_at(Duration::from_secs(60), &ci_ctx), region.footprint().cells(&ci_ctx));
+    }
+
+    #[test]
+    fn random_regions_start_on_the_grid() {
+        let ci_ctx = test_ctx();
+        let mut rng = seeded_rng(7, 0);
+
+        for _region_idx in 0..100 {
+            let region = WeatherRegion::rand(TEST_GRID_RADIUS, &mut rng, &ci_ctx);
+            let cells = region.cells_at(Duration::from_secs(0), &ci_ctx);
+
+            assert!(!cells.is_empty());
+            // No footprint covers a cell twice
+            assert_eq!(cells.iter().collect::<HashSet<_>>().len(), cells.len());
+        }
+    }
+}
@@ -0,0 +1,453 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/weather_script.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module will provide data structures and functions for loading
+    scripted weather timelines, to be played back by the Weather Manager.
+
+    A script is a plain-text file with one event per line, played in order:
+
+        <element> <duration_secs> constant <intensity>
+        <element> <duration_secs> linear <start_intensity> <end_intensity>
+        <element> <duration_secs> peak <intensity>
+        <element> <duration_secs> keyframes <progress>=<intensity> ...
+
+    Intensities are fractions (0.0 - 1.0) of the context's max weather
+    intensity, and keyframe progress is a fraction (0.0 - 1.0) of the event's
+    duration. A final 'then loop' or 'then random' line determines what
+    happens when the script runs out, defaulting to random weather.
+    Blank lines and anything following a '#' are ignored.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    error::Error,
+    fmt,
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::Duration,
+};
+
+use cast_iron::element::Element;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Starts a comment, running to the end of the line
+const COMMENT_DELIMITER:        char = '#';
+
+/// Separates the progress of a keyframe from its intensity
+const KEYFRAME_DELIMITER:       char = '=';
+
+/// Longest a single scripted event may last (a day), well within what a Duration can hold
+const MAX_EVENT_DURATION_SECS:  f64 = 86_400.0;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Shape of a scripted event's intensity over its duration.
+/// All intensities are fractions of the max weather intensity.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IntensityProfile {
+    /// Holds the same intensity throughout
+    Constant(f64),
+    /// Moves linearly from the first intensity to the second
+    Linear(f64, f64),
+    /// Rises linearly from nothing to the given intensity at the midpoint, then falls back
+    Peak(f64),
+    /// Interpolates linearly between (progress, intensity) points, sorted by progress
+    Keyframes(Vec<(f64, f64)>),
+}
+
+/// A single event of a weather script
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptedEvent {
+    element:    Element,
+    duration:   Duration,
+    profile:    IntensityProfile,
+}
+
+/// What to do once the last event of a script has finished
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScriptEnd {
+    /// Start again from the first event
+    Loop,
+    /// Fall back to randomly-generated weather
+    ThenRandom,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeatherScript {
+    events: Vec<ScriptedEvent>,
+    end:    ScriptEnd,
+}
+
+#[derive(Debug)]
+pub enum WeatherScriptError {
+    Io(PathBuf, io::Error),
+    Parse(usize, String),
+    NoEvents,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl IntensityProfile {
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the intensity (as a fraction of max) at the given progress (0.0 - 1.0) through the event
+    pub fn sample(&self, progress: f64) -> f64 {
+        let progress = progress.max(0.0).min(1.0);
+
+        let intensity = match self {
+            IntensityProfile::Constant(intensity) => *intensity,
+            IntensityProfile::Linear(start, end) => start + (end - start) * progress,
+            IntensityProfile::Peak(peak) => peak * (1.0 - (2.0 * progress - 1.0).abs()),
+            IntensityProfile::Keyframes(keyframes) => {
+                match keyframes.iter().position(|(key_progress, _)| *key_progress >= progress) {
+                    // Before the first keyframe, hold its intensity
+                    Some(0) => keyframes[0].1,
+                    Some(idx) => {
+                        let (prev_progress, prev_intensity) = keyframes[idx - 1];
+                        let (next_progress, next_intensity) = keyframes[idx];
+                        let fraction = (progress - prev_progress) / (next_progress - prev_progress);
+
+                        prev_intensity + (next_intensity - prev_intensity) * fraction
+                    },
+                    // After the last keyframe, hold its intensity
+                    None => keyframes.last().map_or(0.0, |(_, intensity)| *intensity),
+                }
+            },
+        };
+
+        intensity.max(0.0).min(1.0)
+    }
+}
+
+
+impl ScriptedEvent {
+    /// Fully-qualified constructor
+    pub fn new(element: Element, duration: Duration, profile: IntensityProfile) -> Self {
+        Self {
+            element,
+            duration,
+            profile,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn element(&self) -> Element {
+        self.element
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn profile(&self) -> &IntensityProfile {
+        &self.profile
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the intensity (as a fraction of max) the given time into the event
+    pub fn intensity_fraction(&self, time_into_event: Duration) -> f64 {
+        self.profile.sample(time_into_event.as_secs_f64() / self.duration.as_secs_f64())
+    }
+}
+
+
+impl WeatherScript {
+    /// Fully-qualified constructor
+    pub fn new(events: Vec<ScriptedEvent>, end: ScriptEnd) -> Result<Self, WeatherScriptError> {
+        if events.is_empty() {
+            return Err(WeatherScriptError::NoEvents);
+        }
+
+        Ok(Self {events, end})
+    }
+
+    /// Loads a script from the given file
+    pub fn load(path: &Path) -> Result<Self, WeatherScriptError> {
+        let data = fs::read_to_string(path).map_err(|e| WeatherScriptError::Io(path.to_path_buf(), e))?;
+
+        Self::parse(&data)
+    }
+
+    /// Parses a script from its text
+    pub fn parse(data: &str) -> Result<Self, WeatherScriptError> {
+        let mut events = Vec::new();
+        let mut end = None;
+
+        for (line_idx, raw_line) in data.lines().enumerate() {
+            let line_num = line_idx + 1;
+
+            // Strip comments and skip blank lines
+            let line = raw_line.split(COMMENT_DELIMITER).next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+
+            // Nothing may follow the end directive
+            if end.is_some() {
+                return Err(WeatherScriptError::Parse(line_num, String::from("nothing may follow a 'then' directive")));
+            }
+
+            if tokens[0] == "then" {
+                end = match tokens.get(1..) {
+                    Some(["loop"]) => Some(ScriptEnd::Loop),
+                    Some(["random"]) => Some(ScriptEnd::ThenRandom),
+                    _ => return Err(WeatherScriptError::Parse(line_num, String::from("expected 'then loop' or 'then random'"))),
+                };
+            }
+            else {
+                events.push(parse_event(&tokens).map_err(|reason| WeatherScriptError::Parse(line_num, reason))?);
+            }
+        }
+
+        Self::new(events, end.unwrap_or(ScriptEnd::ThenRandom))
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn events(&self) -> &[ScriptedEvent] {
+        &self.events
+    }
+
+    pub fn end(&self) -> ScriptEnd {
+        self.end
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Parses an event line, already split into tokens
+fn parse_event(tokens: &[&str]) -> Result<ScriptedEvent, String> {
+    if tokens.len() < 3 {
+        return Err(String::from("expected '<element> <duration_secs> <profile> ...'"));
+    }
+
+    let element = parse_element(tokens[0]).ok_or_else(|| format!("unknown element '{}'", tokens[0]))?;
+
+    let duration_secs = tokens[1].parse::<f64>().map_err(|_e| format!("invalid duration '{}'", tokens[1]))?;
+    if !duration_secs.is_finite() || duration_secs <= 0.0 || duration_secs > MAX_EVENT_DURATION_SECS {
+        return Err(format!("duration must be positive and at most {} seconds, got '{}'", MAX_EVENT_DURATION_SECS, tokens[1]));
+    }
+
+    let args = &tokens[3..];
+    let profile = match (tokens[2], args) {
+        ("constant", [intensity]) => IntensityProfile::Constant(parse_intensity(intensity)?),
+        ("linear", [start, end]) => IntensityProfile::Linear(parse_intensity(start)?, parse_intensity(end)?),
+        ("peak", [intensity]) => IntensityProfile::Peak(parse_intensity(intensity)?),
+        ("keyframes", keyframe_args) if !keyframe_args.is_empty() => {
+            let mut keyframes = Vec::new();
+            for keyframe in keyframe_args {
+                let mut parts = keyframe.splitn(2, KEYFRAME_DELIMITER);
+                let progress = parts.next().unwrap_or_default();
+                let intensity = parts.next().ok_or_else(|| format!("keyframe '{}' should be <progress>=<intensity>", keyframe))?;
+
+                keyframes.push((parse_intensity(progress)?, parse_intensity(intensity)?));
+            }
+
+            // Keyframes may be listed in any order
+            keyframes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+            // ...but two at the same progress leave nothing to interpolate between
+            if let Some(pair) = keyframes.windows(2).find(|pair| pair[1].0 <= pair[0].0) {
+                return Err(format!("more than one keyframe at progress {}", pair[0].0));
+            }
+
+            IntensityProfile::Keyframes(keyframes)
+        },
+        ("constant", _) | ("peak", _) => return Err(format!("'{}' takes one intensity", tokens[2])),
+        ("linear", _) => return Err(String::from("'linear' takes a start and end intensity")),
+        ("keyframes", _) => return Err(String::from("'keyframes' takes at least one <progress>=<intensity>")),
+        (profile, _) => return Err(format!("unknown intensity profile '{}'", profile)),
+    };
+
+    Ok(ScriptedEvent::new(element, Duration::from_secs_f64(duration_secs), profile))
+}
+
+/// Parses a fraction from 0.0 to 1.0
+fn parse_intensity(token: &str) -> Result<f64, String> {
+    match token.parse::<f64>() {
+        Ok(value) if (0.0..=1.0).contains(&value) => Ok(value),
+        _ => Err(format!("expected a value from 0.0 to 1.0, got '{}'", token)),
+    }
+}
+
+/// Parses an element from its (case-insensitive) name
+pub fn parse_element(token: &str) -> Option<Element> {
+    match token.to_lowercase().as_str() {
+        "fire"      => Some(Element::Fire),
+        "ice"       => Some(Element::Ice),
+        "wind"      => Some(Element::Wind),
+        "water"     => Some(Element::Water),
+        "electric"  => Some(Element::Electric),
+        "earth"     => Some(Element::Earth),
+        "light"     => Some(Element::Light),
+        "dark"      => Some(Element::Dark),
+        _           => None,
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Error for WeatherScriptError {}
+
+impl fmt::Display for WeatherScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeatherScriptError::Io(path, e) => {
+                write!(f, "Failed to read weather script {}: {}", path.display(), e)
+            },
+            WeatherScriptError::Parse(line_num, reason) => {
+                write!(f, "Weather script line {}: {}", line_num, reason)
+            },
+            WeatherScriptError::NoEvents => {
+                write!(f, "Weather script contains no events")
+            },
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the line number of the parse error for the given script, if it failed to parse
+    fn error_line(data: &str) -> Option<usize> {
+        match WeatherScript::parse(data) {
+            Err(WeatherScriptError::Parse(line_num, _reason)) => Some(line_num),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn parses_each_profile_and_end() {
+        let script = WeatherScript::parse("# Comment line\n\
+                                           fire 10 constant 0.5\n\
+                                           \n\
+                                           ice 2.5 linear 0.0 1.0  # trailing comment\n\
+                                           wind 4 peak 1\n\
+                                           water 8 keyframes 1.0=0.2 0.0=0.8\n\
+                                           then loop\n").unwrap();
+
+        assert_eq!(script.end(), ScriptEnd::Loop);
+        assert_eq!(script.events(), &[
+            ScriptedEvent::new(Element::Fire, Duration::from_secs(10), IntensityProfile::Constant(0.5)),
+            ScriptedEvent::new(Element::Ice, Duration::from_millis(2500), IntensityProfile::Linear(0.0, 1.0)),
+            ScriptedEvent::new(Element::Wind, Duration::from_secs(4), IntensityProfile::Peak(1.0)),
+            // Keyframes are sorted by progress
+            ScriptedEvent::new(Element::Water, Duration::from_secs(8), IntensityProfile::Keyframes(vec![(0.0, 0.8), (1.0, 0.2)])),
+        ][..]);
+    }
+
+    #[test]
+    fn end_defaults_to_random() {
+        assert_eq!(WeatherScript::parse("dark 1 constant 1").unwrap().end(), ScriptEnd::ThenRandom);
+    }
+
+    #[test]
+    fn rejects_bad_lines_with_their_line_number() {
+        let bad_lines = [
+            "fire 10",
+            "plasma 10 constant 0.5",
+            "fire ten constant 0.5",
+            "fire 0 constant 0.5",
+            "fire -1 constant 0.5",
+            "fire inf constant 0.5",
+            "fire NaN constant 0.5",
+            "fire 1e30 constant 0.5",
+            "fire 10 constant 1.5",
+            "fire 10 constant 0.5 0.6",
+            "fire 10 linear 0.5",
+            "fire 10 keyframes",
+            "fire 10 keyframes 0.5",
+            "fire 10 sawtooth 0.5",
+            "then",
+            "then repeat",
+        ];
+
+        for bad_line in bad_lines.iter() {
+            assert_eq!(error_line(&format!("# Header\nice 1 constant 1\n{}", bad_line)), Some(3), "{}", bad_line);
+        }
+    }
+
+    #[test]
+    fn rejects_duplicate_keyframe_progress() {
+        assert_eq!(error_line("fire 10 keyframes 0.5=0.2 0.0=0.0 0.5=0.8"), Some(1));
+        assert!(WeatherScript::parse("fire 10 keyframes 0.5=0.2").is_ok());
+    }
+
+    #[test]
+    fn rejects_lines_after_end() {
+        assert_eq!(error_line("fire 1 constant 1\nthen loop\nice 1 constant 1"), Some(3));
+    }
+
+    #[test]
+    fn rejects_empty_script() {
+        match WeatherScript::parse("# Nothing but comments\n\nthen loop\n") {
+            Err(WeatherScriptError::NoEvents) => {},
+            other => panic!("expected NoEvents, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keyframes_interpolate_and_hold_at_ends() {
+        let profile = IntensityProfile::Keyframes(vec![(0.25, 0.2), (0.75, 0.6)]);
+
+        assert_eq!(profile.sample(0.0), 0.2);
+        assert!((profile.sample(0.5) - 0.4).abs() < 1e-9);
+        assert_eq!(profile.sample(1.0), 0.6);
+    }
+}
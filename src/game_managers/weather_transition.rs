@@ -0,0 +1,259 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/weather_transition.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module will provide the timing and blending math for crossfading
+    between an outgoing and an incoming weather event.
+
+    Everything here is a pure function of the game time passed in, so that a
+    transition can be stepped through with a deterministic clock.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::time::Duration;
+
+use ggez::graphics as ggez_gfx;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Default length of the crossfade between weather events
+pub const DEFAULT_CROSSFADE:    Duration = Duration::from_secs(1);
+
+/// Longest crossfade that may be configured (in seconds)
+pub const MAX_CROSSFADE_SECS:   f64 = 60.0;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// A crossfade in progress, from the game time it started
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transition {
+    start:      Duration,
+    duration:   Duration,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl Transition {
+    /// Fully-qualified constructor
+    pub fn new(start: Duration, duration: Duration) -> Self {
+        Self {
+            start,
+            duration,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn start(&self) -> Duration {
+        self.start
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the linear progress (0.0 - 1.0) through the transition at the given game time
+    pub fn progress(&self, elapsed_time: Duration) -> f64 {
+        if self.duration == Duration::default() {
+            return 1.0;
+        }
+
+        let time_into_transition = elapsed_time.checked_sub(self.start).unwrap_or_default();
+
+        (time_into_transition.as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+    }
+
+    /// Returns the weight (0.0 - 1.0) of the incoming event at the given game time.
+    /// Eases in and out, so that the blend has no sudden jumps at either end.
+    pub fn incoming_weight(&self, elapsed_time: Duration) -> f64 {
+        let progress = self.progress(elapsed_time);
+
+        progress * progress * (3.0 - 2.0 * progress)
+    }
+
+    /// Returns true once the incoming event has fully taken over
+    pub fn is_complete(&self, elapsed_time: Duration) -> bool {
+        self.progress(elapsed_time) >= 1.0
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Blends linearly between two values by the weight of the second
+pub fn blend(outgoing: f64, incoming: f64, incoming_weight: f64) -> f64 {
+    outgoing + (incoming - outgoing) * incoming_weight
+}
+
+/// Blends each channel of two colors by the weight of the second
+pub fn blend_color(outgoing: ggez_gfx::Color, incoming: ggez_gfx::Color, incoming_weight: f64) -> ggez_gfx::Color {
+    let blend_channel = |from: f32, to: f32| blend(from as f64, to as f64, incoming_weight) as f32;
+
+    ggez_gfx::Color::new(blend_channel(outgoing.r, incoming.r),
+                         blend_channel(outgoing.g, incoming.g),
+                         blend_channel(outgoing.b, incoming.b),
+                         blend_channel(outgoing.a, incoming.a))
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game_clock::GameClock;
+
+    /// Steps per second of the test clock, giving 100ms steps
+    const STEPS_PER_SEC:    u32 = 10;
+
+    const OUTGOING_INTENSITY:   f64 = 0.8;
+    const INCOMING_INTENSITY:   f64 = 0.2;
+
+    fn outgoing_color() -> ggez_gfx::Color {
+        ggez_gfx::Color::new(1.0, 0.0, 0.0, 0.8)
+    }
+
+    fn incoming_color() -> ggez_gfx::Color {
+        ggez_gfx::Color::new(0.0, 0.0, 1.0, 0.2)
+    }
+
+    /// Returns a paused clock, so that it only moves when stepped
+    fn paused_clock() -> GameClock {
+        let mut clock = GameClock::new(STEPS_PER_SEC);
+        clock.pause();
+
+        clock
+    }
+
+    fn step_clock(clock: &mut GameClock, steps: u32) {
+        for _ in 0..steps {
+            clock.step();
+            assert!(clock.next_step());
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "expected {}, got {}", expected, actual);
+    }
+
+    fn assert_color_close(actual: ggez_gfx::Color, expected: ggez_gfx::Color) {
+        assert_close(actual.r as f64, expected.r as f64);
+        assert_close(actual.g as f64, expected.g as f64);
+        assert_close(actual.b as f64, expected.b as f64);
+        assert_close(actual.a as f64, expected.a as f64);
+    }
+
+    #[test]
+    fn crossfade_starts_on_outgoing() {
+        let clock = paused_clock();
+        let transition = Transition::new(clock.elapsed(), DEFAULT_CROSSFADE);
+
+        let weight = transition.incoming_weight(clock.elapsed());
+        assert_close(weight, 0.0);
+        assert_close(blend(OUTGOING_INTENSITY, INCOMING_INTENSITY, weight), OUTGOING_INTENSITY);
+        assert_color_close(blend_color(outgoing_color(), incoming_color(), weight), outgoing_color());
+        assert!(!transition.is_complete(clock.elapsed()));
+    }
+
+    #[test]
+    fn crossfade_is_even_halfway_through() {
+        let mut clock = paused_clock();
+        let transition = Transition::new(clock.elapsed(), DEFAULT_CROSSFADE);
+
+        step_clock(&mut clock, STEPS_PER_SEC / 2);
+
+        let weight = transition.incoming_weight(clock.elapsed());
+        assert_close(transition.progress(clock.elapsed()), 0.5);
+        assert_close(weight, 0.5);
+        assert_close(blend(OUTGOING_INTENSITY, INCOMING_INTENSITY, weight), 0.5);
+        assert_color_close(blend_color(outgoing_color(), incoming_color(), weight), ggez_gfx::Color::new(0.5, 0.0, 0.5, 0.5));
+        assert!(!transition.is_complete(clock.elapsed()));
+    }
+
+    #[test]
+    fn crossfade_eases_at_the_ends() {
+        let mut clock = paused_clock();
+        let transition = Transition::new(clock.elapsed(), DEFAULT_CROSSFADE);
+
+        // A tenth of the way through, the incoming event has less than a tenth of the weight
+        step_clock(&mut clock, 1);
+        let weight = transition.incoming_weight(clock.elapsed());
+        assert_close(transition.progress(clock.elapsed()), 0.1);
+        assert!(weight > 0.0 && weight < 0.1);
+    }
+
+    #[test]
+    fn crossfade_ends_on_incoming() {
+        let mut clock = paused_clock();
+        let transition = Transition::new(clock.elapsed(), DEFAULT_CROSSFADE);
+
+        step_clock(&mut clock, STEPS_PER_SEC);
+
+        let weight = transition.incoming_weight(clock.elapsed());
+        assert_close(weight, 1.0);
+        assert_close(blend(OUTGOING_INTENSITY, INCOMING_INTENSITY, weight), INCOMING_INTENSITY);
+        assert_color_close(blend_color(outgoing_color(), incoming_color(), weight), incoming_color());
+        assert!(transition.is_complete(clock.elapsed()));
+
+        // ...and stays there
+        step_clock(&mut clock, STEPS_PER_SEC);
+        assert_close(transition.incoming_weight(clock.elapsed()), 1.0);
+    }
+
+    #[test]
+    fn crossfade_starting_mid_game() {
+        let mut clock = paused_clock();
+        step_clock(&mut clock, 3 * STEPS_PER_SEC);
+        let transition = Transition::new(clock.elapsed(), DEFAULT_CROSSFADE);
+
+        assert_close(transition.incoming_weight(clock.elapsed()), 0.0);
+        step_clock(&mut clock, STEPS_PER_SEC / 2);
+        assert_close(transition.incoming_weight(clock.elapsed()), 0.5);
+        step_clock(&mut clock, STEPS_PER_SEC / 2);
+        assert!(transition.is_complete(clock.elapsed()));
+    }
+
+    #[test]
+    fn zero_length_crossfade_is_immediately_complete() {
+        let clock = paused_clock();
+        let transition = Transition::new(clock.elapsed(), Duration::default());
+
+        assert_close(transition.incoming_weight(clock.elapsed()), 1.0);
+        assert!(transition.is_complete(clock.elapsed()));
+    }
+}
@@ -25,6 +25,7 @@ use std::collections::HashMap;
 use cast_iron::{
     context::Context as CastIronContext,
     coords,
+    element::Element,
     hex_directions,
 };
 
@@ -39,9 +40,16 @@ use mt_logger::{
     Level,
 };
 
-use crate::game_assets::{
-    colors,
-    hex_grid_cell::HexGridCell,
+use crate::{
+    game_assets::{
+        colors,
+        hex_grid_cell::HexGridCell,
+        terrain::Terrain,
+    },
+    game_managers::{
+        sort_cells,
+        visibility::VisibilityMap,
+    },
 };
 
 
@@ -58,6 +66,10 @@ const NEW_RING_START_DIRECTION: hex_directions::Side    = hex_directions::Side::
 /// First intra-ring direction in new hex ring
 const FIRST_INTRARING_DIRECTION: hex_directions::Side   = hex_directions::Side::North;
 
+/// Fog over cells seen before but not visible now, and over cells never seen
+const FOG_SEEN_COLOR:   ggez_gfx::Color = ggez_gfx::Color {r: 0.0, g: 0.0, b: 0.0, a: 0.5};
+const FOG_UNSEEN_COLOR: ggez_gfx::Color = ggez_gfx::Color {r: 0.0, g: 0.0, b: 0.0, a: 0.9};
+
 
 ///////////////////////////////////////////////////////////////////////////////
 //  Data Structures
@@ -66,6 +78,7 @@ const FIRST_INTRARING_DIRECTION: hex_directions::Side   = hex_directions::Side::
 pub struct WorldGridManager {
     radial_size:            usize,          // Maximum value for an axis of the hex grid
     base_grid_mesh:         ggez_gfx::Mesh, // Mesh for the base hex grid
+    fog_mesh:               Option<ggez_gfx::Mesh>, // Mesh for fog of war, if enabled
     hex_map:                HashMap::<coords::Position, HexGridCell>
 }
 
@@ -94,6 +107,7 @@ impl WorldGridManager {
                                crate::DEFAULT_LINE_WIDTH,
                                crate::DEFAULT_LINE_COLOR)
                                 .unwrap(),
+            fog_mesh:       None,
             hex_map:        Self::build_default_hex_cell_map(radial_size, ci_ctx, ggez_ctx),
         };
         world_grid_manager.update_base_mesh(ggez_ctx);
@@ -117,6 +131,34 @@ impl WorldGridManager {
     pub fn hex_map(&self) -> &HashMap::<coords::Position, HexGridCell> {
         &self.hex_map
     }
+
+    /// Returns the position of every cell, sorted so that iterating over them is repeatable
+    pub fn cell_positions(&self) -> Vec<coords::Position> {
+        let mut positions: Vec<coords::Position> = self.hex_map.keys().copied().collect();
+        sort_cells(&mut positions);
+
+        positions
+    }
+
+    /// Returns the terrain of the given cell, or None if it is off the grid
+    pub fn terrain_at(&self, cell_position: &coords::Position) -> Option<Terrain> {
+        self.hex_map.get(cell_position).map(HexGridCell::terrain)
+    }
+
+    /// Returns the cost of moving into the given cell, or None if it is impassable or off the grid
+    pub fn movement_cost(&self, cell_position: &coords::Position) -> Option<u32> {
+        self.terrain_at(cell_position).and_then(|terrain| terrain.movement_cost())
+    }
+
+    /// Returns true if the terrain of the given cell blocks line of sight
+    pub fn blocks_sight(&self, cell_position: &coords::Position) -> bool {
+        self.terrain_at(cell_position).map_or(false, |terrain| terrain.blocks_sight())
+    }
+
+    /// Returns the multiplier the given cell's terrain applies to weather of the given element
+    pub fn weather_modifier(&self, cell_position: &coords::Position, element: Element) -> f64 {
+        self.terrain_at(cell_position).map_or(1.0, |terrain| terrain.weather_modifier(element))
+    }
     
 
     /*  *  *  *  *  *  *  *\
@@ -138,6 +180,16 @@ impl WorldGridManager {
         }
     }
 
+    /// Sets the terrain of every cell from the given function, rebuilding the mesh once at the end
+    pub fn paint_terrain<F>(&mut self, terrain_for: F, ggez_ctx: &mut GgEzContext)
+    where F: Fn(&coords::Position) -> Terrain {
+        for (position, hex_cell) in self.hex_map.iter_mut() {
+            hex_cell.set_terrain(terrain_for(position));
+        }
+
+        self.update_base_mesh(ggez_ctx);
+    }
+
 
     /*  *  *  *  *  *  *  *\
      *  Utility Methods   *
@@ -148,6 +200,41 @@ impl WorldGridManager {
         ggez_gfx::draw(ggez_ctx, &self.base_grid_mesh, ggez_gfx::DrawParam::default()).unwrap();
     }
 
+    /// Draws fog of war over the grid and everything on it, if enabled
+    pub fn draw_fog(&self, ggez_ctx: &mut GgEzContext) {
+        if let Some(fog_mesh) = &self.fog_mesh {
+            ggez_gfx::draw(ggez_ctx, fog_mesh, ggez_gfx::DrawParam::default()).unwrap();
+        }
+    }
+
+    /// Rebuilds the fog of war from the given visibility, darkening cells never seen and dimming cells not
+    /// visible now. Passing None disables the fog.
+    pub fn update_fog(&mut self, visibility: Option<&VisibilityMap>, ggez_ctx: &mut GgEzContext) {
+        let visibility = match visibility {
+            Some(visibility) => visibility,
+            None => {
+                self.fog_mesh = None;
+                return;
+            }
+        };
+
+        let mut mesh_builder = ggez_gfx::MeshBuilder::new();
+        let mut has_fog = false;
+
+        for (position, hex_cell) in self.hex_map.iter() {
+            if visibility.is_visible(position) {
+                continue;
+            }
+
+            let fog_color = if visibility.was_seen(position) { FOG_SEEN_COLOR } else { FOG_UNSEEN_COLOR };
+            hex_cell.add_hex_fill_to_mesh(fog_color, &mut mesh_builder);
+            has_fog = true;
+        }
+
+        // Building an empty mesh fails, so a fully-visible grid has no fog mesh at all
+        self.fog_mesh = if has_fog { Some(mesh_builder.build(ggez_ctx).unwrap()) } else { None };
+    }
+
 
     /*  *  *  *  *  *  *  *\
      *  Helper Methods    *
@@ -157,7 +244,7 @@ impl WorldGridManager {
         let mut mesh_builder = ggez_gfx::MeshBuilder::new();
 
         for (_position, hex_cell) in self.hex_map.iter() {
-            hex_cell.add_to_mesh(colors::TRANSPARENT,crate::DEFAULT_LINE_COLOR, &mut mesh_builder);
+            hex_cell.add_to_mesh(colors::from_terrain(hex_cell.terrain()),crate::DEFAULT_LINE_COLOR, &mut mesh_builder);
         }
 
         self.base_grid_mesh = mesh_builder.build(ggez_ctx).unwrap();
@@ -19,6 +19,20 @@ Purpose:
 
     This includes ggez event-handling functions such as update() and draw().
 
+    Simulation updates are driven by the game clock, which can be paused (P),
+    single-stepped while paused (.), and slowed down or sped up (- and =).
+
+    Right-clicking an actor harvests from the resource beneath it, and H
+    toggles automatic harvesting by every actor. B toggles the AI overlay.
+    Left-clicking an actor selects it (or deselects it, if already selected),
+    and A then switches the selected actor between preset behavior sets, and
+    T moves it into the next faction.
+
+    F toggles fog of war, shown from one faction's point of view at a time,
+    and V switches which faction that is.
+
+    L toggles logging of every weather event, via a weather observer.
+
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
 use cast_iron::{
@@ -30,7 +44,10 @@ use ggez::{
     GameResult as GgEzGameResult,
     event as ggez_event,
     graphics as ggez_gfx,
-    input::mouse as ggez_mouse,
+    input::{
+        keyboard as ggez_keyboard,
+        mouse as ggez_mouse,
+    },
     mint as ggez_mint,
     timer as ggez_timer,
 };
@@ -45,11 +62,21 @@ use crate::{
         colors,
         hex_grid_cell::HexGridCell,
     },
+    game_clock::GameClock,
     game_managers::{
         DrawableMechanic,
-        actor_manager::ActorManager,
+        actor_ai,
+        actor_manager::{
+            self,
+            ActorManager,
+        },
+        map_generator::MapGenerator,
         obstacle_manager::ObstacleManager,
         resource_manager::ResourceManager,
+        weather_events::{
+            ObserverId,
+            WeatherEvent,
+        },
         weather_manager::WeatherManager,
         world_grid_manager::WorldGridManager,
     },
@@ -57,6 +84,17 @@ use crate::{
 };
 
 
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Factor by which the time scale changes for each key press
+const TIME_SCALE_STEP:      f64 = 2.0;
+
+/// Offset of the game clock status text from the bottom-left corner of the window
+const CLOCK_TEXT_OFFSET:    f32 = 10.0;
+
+
 ///////////////////////////////////////////////////////////////////////////////
 //  Data Structures
 ///////////////////////////////////////////////////////////////////////////////
@@ -64,6 +102,11 @@ use crate::{
 /// Primary Game Struct
 pub struct SandCastingGameState {
     initialized:        bool,               // Flag indicating if game has been initialized
+    clock:              GameClock,          // Simulation time, independent of wall time
+    focus_paused:       bool,               // Flag indicating the clock was paused by losing window focus
+    fog_faction:        Option<usize>,      // Faction whose fog of war is shown, if enabled
+    weather_log:        Option<ObserverId>, // Observer logging weather events, if enabled
+    map_generator:      MapGenerator,       // Seeded pipeline that generates the map on the first frame
     ci_ctx:             CastIronContext,    // CastIron engine context
     profiler:           profiler::Instance, // Instance of SandCasting performance profiler
     actor_manager:      ActorManager,       // Actor Manager instance
@@ -82,6 +125,7 @@ pub struct SandCastingGameState {
 impl SandCastingGameState {
     pub fn new(profiler_original: &profiler::Instance,
                ci_ctx: &CastIronContext,
+               seed: u64,
                ggez_ctx: &mut GgEzContext) -> Self {
         //NOTE: Load/create resources here: images, fonts, sounds, etc.
 
@@ -91,14 +135,25 @@ impl SandCastingGameState {
         // Clone context for use by submodules
         let ctx_clone = ci_ctx.clone();
 
+        // Seed the managers' RNGs so the run can be reproduced
+        let mut actor_manager = ActorManager::new(profiler_original, ggez_ctx);
+        actor_manager.set_seed(seed);
+        let mut weather_manager = WeatherManager::default(profiler_original, ci_ctx, ggez_ctx);
+        weather_manager.set_seed(seed);
+
         SandCastingGameState{
             initialized:        false,
+            clock:              GameClock::new(crate::DESIRED_FPS),
+            focus_paused:       false,
+            fog_faction:        None,
+            weather_log:        None,
+            map_generator:      MapGenerator::new(seed),
             ci_ctx:             ctx_clone,
             profiler:           profiler_clone,
-            actor_manager:      ActorManager::new(ggez_ctx),
-            obstacle_manager:   ObstacleManager::new(ggez_ctx),
-            resource_manager:   ResourceManager::new(ggez_ctx),
-            weather_manager:    WeatherManager::default(profiler_original, ci_ctx, ggez_ctx),
+            actor_manager:      actor_manager,
+            obstacle_manager:   ObstacleManager::new(profiler_original, ggez_ctx),
+            resource_manager:   ResourceManager::new(profiler_original, ggez_ctx),
+            weather_manager:    weather_manager,
             world_grid_manager: WorldGridManager::new(crate::DEFAULT_GRID_RADIUS, ci_ctx, ggez_ctx),
         }
     }
@@ -112,6 +167,14 @@ impl SandCastingGameState {
         self.initialized
     }
 
+    pub fn clock(&mut self) -> &mut GameClock {
+        &mut self.clock
+    }
+
+    pub fn map_generator(&mut self) -> &mut MapGenerator {
+        &mut self.map_generator
+    }
+
     pub fn actor_manager(&mut self) -> &mut ActorManager {
         &mut self.actor_manager
     }
@@ -138,27 +201,70 @@ impl SandCastingGameState {
     \*  *  *  *  *  *  *  */
 
     fn initialize(&mut self, ggez_ctx: &mut GgEzContext) {
-        // Create random resources
-        for _i in 0..3 {
-            self.resource_manager.add_rand_instance(&self.ci_ctx, ggez_ctx).unwrap();
-        }
+        // Start timing the stacked initialization phases
+        let mut init_timer = self.profiler.start_stack(ggez_ctx);
+
+        // Sample the heightmap and moisture map the rest of the map is generated from
+        let layers = self.map_generator.layers(&self.world_grid_manager);
+
+        // Generate terrain
+        self.map_generator.generate_terrain(&layers, &mut self.world_grid_manager, ggez_ctx);
+        self.obstacle_manager.set_impassable_terrain(&self.world_grid_manager);
+        mt_log!(Level::Info, "Terrain generated.");
+        init_timer.mark("Terrain", ggez_ctx);
+
+        // Place resources where their elements suit the land
+        self.map_generator.place_resources(&layers, &mut self.resource_manager, &self.ci_ctx, ggez_ctx);
         mt_log!(Level::Info, "Resources generated.");
+        init_timer.mark("Resources", ggez_ctx);
 
-        // Create random obstacles
-        for _i in 0..3 {
-            self.obstacle_manager.add_rand_instance(&self.ci_ctx, ggez_ctx).unwrap();
-        }
+        // Raise obstacles along the ridgelines
+        self.map_generator.place_ridges(&layers, &mut self.obstacle_manager, &self.ci_ctx, ggez_ctx);
         mt_log!(Level::Info, "Obstacles generated.");
-        
-        // Create random actors
-        for _i in 0..3 {
-            self.actor_manager.add_rand_instance(&self.ci_ctx, ggez_ctx).unwrap();
-        }
+        init_timer.mark("Obstacles", ggez_ctx);
+
+        // Spawn actors in open zones
+        self.map_generator.place_actors(&layers,
+                                        &self.world_grid_manager,
+                                        &self.obstacle_manager,
+                                        &mut self.actor_manager,
+                                        ggez_ctx);
         mt_log!(Level::Info, "Actors generated.");
+        init_timer.mark("Actors", ggez_ctx);
+
+        // Send stacked timings to profiler
+        self.profiler.send_stacked_init_time(init_timer).unwrap();
 
         mt_log!(Level::Info, "First-frame initialization complete.");
         self.initialized = true;
     }
+
+    /// Rebuilds the fog of war from the viewing faction's visibility, or clears it if disabled
+    fn refresh_fog(&mut self, ggez_ctx: &mut GgEzContext) {
+        let actor_manager = &self.actor_manager;
+        let visibility = self.fog_faction.and_then(|faction_idx| actor_manager.faction_visibility(faction_idx));
+        self.world_grid_manager.update_fog(visibility, ggez_ctx);
+    }
+
+    /// Draws the game clock's status in the bottom-left corner, when paused or scaled
+    fn draw_clock_status(&self, ggez_ctx: &mut GgEzContext) {
+        let status_str = if self.clock.paused() {
+            format!("PAUSED @ {:.2}s (. to step)", self.clock.elapsed().as_secs_f64())
+        }
+        else if (self.clock.time_scale() - 1.0).abs() > f64::EPSILON {
+            format!("x{} speed", self.clock.time_scale())
+        }
+        else {
+            return;
+        };
+
+        let (_window_x, window_y) = ggez_gfx::size(ggez_ctx);
+        let status_pos = ggez_mint::Point2 {x: CLOCK_TEXT_OFFSET,
+                                            y: window_y - crate::DEFAULT_TEXT_SIZE - CLOCK_TEXT_OFFSET};
+
+        let status_text = ggez_gfx::Text::new((status_str.as_str(), ggez_gfx::Font::default(), crate::DEFAULT_TEXT_SIZE));
+        ggez_gfx::draw(ggez_ctx, &status_text, (status_pos, 0.0, colors::YELLOW)).unwrap();
+    }
 }
 
 
@@ -173,16 +279,51 @@ impl ggez_event::EventHandler for SandCastingGameState {
             self.initialize(ggez_ctx);
         }
 
-        // Check if we've reached an update
-        while ggez_timer::check_update_time(ggez_ctx,crate::DESIRED_FPS) {
+        // Feed the wall time since the last frame to the game clock
+        self.clock.advance(ggez_timer::delta(ggez_ctx));
+
+        // Run a simulation update for each fixed step the clock has accumulated
+        while self.clock.next_step() {
+            // Start timing the stacked update phases, one per manager
+            let mut update_timer = self.profiler.start_stack(ggez_ctx);
+
             // Update weather
             mt_log!(Level::Trace, "Updating weather...");
-            self.weather_manager.update_weather(&self.ci_ctx, ggez_ctx);
+            self.weather_manager.update_weather(&self.clock, &self.world_grid_manager, &self.ci_ctx, ggez_ctx);
+            update_timer.mark("Weather", ggez_ctx);
+
+            // Update resources, which depend on the weather
+            mt_log!(Level::Trace, "Updating resources...");
+            self.resource_manager.update_resources(&self.clock, &self.weather_manager, &self.ci_ctx, ggez_ctx);
+            update_timer.mark("Resources", ggez_ctx);
+
+            // Update actors' automatic harvesting of the resources beneath them
+            self.actor_manager.update_harvesting(&self.clock, &mut self.resource_manager, ggez_ctx);
+            update_timer.mark("Harvesting", ggez_ctx);
+
+            // Let actors decide what to do next
+            self.actor_manager.update_ai(&self.clock,
+                                         &mut self.resource_manager,
+                                         &self.obstacle_manager,
+                                         &self.weather_manager,
+                                         &self.world_grid_manager,
+                                         &self.ci_ctx,
+                                         ggez_ctx);
+            update_timer.mark("ActorAI", ggez_ctx);
+
+            // Recompute what each faction can see, if anyone moved
+            if self.actor_manager.update_visibility(&self.obstacle_manager, &self.world_grid_manager, &self.ci_ctx) && self.fog_faction.is_some() {
+                self.refresh_fog(ggez_ctx);
+            }
+            update_timer.mark("Visibility", ggez_ctx);
 
-            // Update FPS
-            self.profiler.update_fps_stats(ggez_ctx).unwrap();
+            // Send stacked timings to profiler
+            self.profiler.send_stacked_update_time(update_timer).unwrap();
         }
 
+        // Update FPS, which follows wall time even while the game clock is paused
+        self.profiler.update_fps_stats(ggez_ctx).unwrap();
+
         Ok(())
     }
 
@@ -192,46 +333,178 @@ impl ggez_event::EventHandler for SandCastingGameState {
             self.profiler.send_frame_delta(ctx).unwrap();
         }
         
-        // Get draw start time and set up vec for stacked draw time
-        let start_time = ggez_timer::time_since_start(ctx);
-        let mut draw_timings = Vec::new();
+        // Start timing the stacked draw phases
+        let mut draw_timer = self.profiler.start_stack(ctx);
 
         ggez_gfx::clear(ctx, colors::BLACK);
-        draw_timings.push(profiler::StackedTime{label: String::from("Clear"), time: ggez_timer::time_since_start(ctx)});
+        draw_timer.mark("Clear", ctx);
         
         // Draw the weather HUD
         self.weather_manager.draw(ctx);
-        draw_timings.push(profiler::StackedTime{label: String::from("Weather"), time: ggez_timer::time_since_start(ctx)});
+        draw_timer.mark("Weather", ctx);
         
         // Draw the hex grid
         self.world_grid_manager.draw(ctx);
-        draw_timings.push(profiler::StackedTime{label: String::from("WorldGrid"), time: ggez_timer::time_since_start(ctx)});
+        draw_timer.mark("WorldGrid", ctx);
+
+        // Draw regional weather over the grid
+        self.weather_manager.draw_regional_overlay(ctx);
+        draw_timer.mark("WeatherOverlay", ctx);
 
         // Draw resources
         self.resource_manager.draw(ctx);
-        draw_timings.push(profiler::StackedTime{label: String::from("Resources"), time: ggez_timer::time_since_start(ctx)});
+        draw_timer.mark("Resources", ctx);
 
         // Draw obstacles
         self.obstacle_manager.draw(ctx);
-        draw_timings.push(profiler::StackedTime{label: String::from("Obstacles"), time: ggez_timer::time_since_start(ctx)});
+        draw_timer.mark("Obstacles", ctx);
 
         // Draw actors
         self.actor_manager.draw(ctx);
-        draw_timings.push(profiler::StackedTime{label: String::from("Actors"), time: ggez_timer::time_since_start(ctx)});
+        draw_timer.mark("Actors", ctx);
+
+        // Draw fog of war over the grid, if enabled
+        self.world_grid_manager.draw_fog(ctx);
+        draw_timer.mark("Fog", ctx);
+
+        // Draw weather effects over the grid
+        self.weather_manager.draw_effects(ctx);
+        draw_timer.mark("WeatherEffects", ctx);
+
+        // Draw actors' AI decisions, if enabled
+        self.actor_manager.draw_ai_overlay(ctx);
+        draw_timer.mark("AIOverlay", ctx);
 
         // Draw performance stats
         self.profiler.draw_fps_stats(ctx);
-        draw_timings.push(profiler::StackedTime{label: String::from("FPS"), time: ggez_timer::time_since_start(ctx)});
+        draw_timer.mark("FPS", ctx);
+
+        // Draw performance overlay
+        self.profiler.draw_overlay(ctx);
+        draw_timer.mark("Overlay", ctx);
+
+        // Draw game clock status, if it isn't running normally
+        self.draw_clock_status(ctx);
+        draw_timer.mark("Clock", ctx);
 
         let res = ggez_gfx::present(ctx);
-        draw_timings.push(profiler::StackedTime{label: String::from("Present"), time: ggez_timer::time_since_start(ctx)});
+        draw_timer.mark("Present", ctx);
 
         // Send stacked timings to profiler
-        self.profiler.send_stacked_draw_time(start_time, draw_timings).unwrap();
+        self.profiler.send_stacked_draw_time(draw_timer).unwrap();
         
         res
     }
 
+    fn key_down_event(&mut self,
+                      ggez_ctx: &mut GgEzContext,
+                      keycode: ggez_keyboard::KeyCode,
+                      _keymods: ggez_keyboard::KeyMods,
+                      _repeat: bool) {
+        match keycode {
+            // Preserve ggez's default quit-on-escape behavior
+            ggez_keyboard::KeyCode::Escape => {
+                ggez_event::quit(ggez_ctx);
+            },
+            ggez_keyboard::KeyCode::F3 => {
+                self.profiler.toggle_overlay();
+                mt_log!(Level::Debug, "Performance overlay toggled {}", if self.profiler.overlay_visible() {"on"} else {"off"});
+            },
+            ggez_keyboard::KeyCode::P => {
+                self.clock.toggle_pause();
+                self.focus_paused = false;
+                mt_log!(Level::Info, "Game clock {}", if self.clock.paused() {"paused"} else {"resumed"});
+            },
+            ggez_keyboard::KeyCode::B => {
+                self.actor_manager.toggle_ai_overlay();
+                mt_log!(Level::Debug, "AI overlay toggled {}", if self.actor_manager.ai_overlay_visible() {"on"} else {"off"});
+            },
+            ggez_keyboard::KeyCode::A => {
+                if let Some(actor_idx) = self.actor_manager.selected() {
+                    let spec = actor_ai::next_preset(self.actor_manager.behavior_spec(actor_idx).unwrap_or_default());
+                    match self.actor_manager.set_behaviors(actor_idx, spec) {
+                        Ok(()) => mt_log!(Level::Info, "Actor #{} behaviors set to '{}'", actor_idx, spec),
+                        Err(e) => mt_log!(Level::Error, "Failed to set actor #{} behaviors to '{}': {}", actor_idx, spec, e),
+                    }
+                }
+            },
+            ggez_keyboard::KeyCode::T => {
+                if let Some(actor_idx) = self.actor_manager.selected() {
+                    let faction_count = self.actor_manager.factions().len().max(1);
+                    let faction_idx = (self.actor_manager.faction(actor_idx).unwrap_or_default() + 1) % faction_count;
+                    self.actor_manager.set_faction(actor_idx, faction_idx, ggez_ctx);
+
+                    // Recompute visibility now, so the fog is right even while paused
+                    if self.actor_manager.update_visibility(&self.obstacle_manager, &self.world_grid_manager, &self.ci_ctx) && self.fog_faction.is_some() {
+                        self.refresh_fog(ggez_ctx);
+                    }
+
+                    if let Some(faction) = self.actor_manager.factions().get(faction_idx) {
+                        mt_log!(Level::Info, "Actor #{} moved to faction {}", actor_idx, faction.name());
+                    }
+                }
+            },
+            ggez_keyboard::KeyCode::F => {
+                self.fog_faction = if self.fog_faction.is_some() { None } else { Some(0) };
+                self.refresh_fog(ggez_ctx);
+                mt_log!(Level::Debug, "Fog of war toggled {}", if self.fog_faction.is_some() {"on"} else {"off"});
+            },
+            ggez_keyboard::KeyCode::V => {
+                if let Some(faction_idx) = self.fog_faction {
+                    let faction_idx = (faction_idx + 1) % self.actor_manager.factions().len().max(1);
+                    self.fog_faction = Some(faction_idx);
+                    self.refresh_fog(ggez_ctx);
+
+                    if let Some(faction) = self.actor_manager.factions().get(faction_idx) {
+                        mt_log!(Level::Info, "Showing fog of war for faction {}", faction.name());
+                    }
+                }
+            },
+            ggez_keyboard::KeyCode::L => {
+                self.weather_log = match self.weather_log.take() {
+                    Some(observer_id) => {
+                        self.weather_manager.unsubscribe(observer_id);
+                        None
+                    },
+                    None => Some(self.weather_manager.subscribe(Box::new(|event: &WeatherEvent| {
+                        mt_log!(Level::Info, "Weather event at {:.3}s: {:?}", event.at().as_secs_f64(), event);
+                    }))),
+                };
+                mt_log!(Level::Info, "Weather event logging {}", if self.weather_log.is_some() {"enabled"} else {"disabled"});
+            },
+            ggez_keyboard::KeyCode::H => {
+                self.actor_manager.set_auto_harvest(!self.actor_manager.auto_harvest());
+                mt_log!(Level::Info, "Automatic harvesting {}", if self.actor_manager.auto_harvest() {"enabled"} else {"disabled"});
+            },
+            ggez_keyboard::KeyCode::Period => {
+                self.clock.step();
+            },
+            ggez_keyboard::KeyCode::Minus => {
+                self.clock.set_time_scale(self.clock.time_scale() / TIME_SCALE_STEP);
+                mt_log!(Level::Info, "Game clock time scale set to {}", self.clock.time_scale());
+            },
+            ggez_keyboard::KeyCode::Equals => {
+                self.clock.set_time_scale(self.clock.time_scale() * TIME_SCALE_STEP);
+                mt_log!(Level::Info, "Game clock time scale set to {}", self.clock.time_scale());
+            },
+            _ => {}
+        }
+    }
+
+    fn focus_event(&mut self, _ggez_ctx: &mut GgEzContext, gained: bool) {
+        // Hold the simulation while the window is in the background, but don't override a manual pause
+        if !gained && !self.clock.paused() {
+            self.clock.pause();
+            self.focus_paused = true;
+            mt_log!(Level::Debug, "Game clock paused on losing focus");
+        }
+        else if gained && self.focus_paused {
+            self.clock.resume();
+            self.focus_paused = false;
+            mt_log!(Level::Debug, "Game clock resumed on regaining focus");
+        }
+    }
+
     fn mouse_button_down_event(&mut self, ggez_ctx: &mut GgEzContext, button: ggez_mouse::MouseButton, x: f32, y: f32) {
         // Pack up event coordinates
         let event_coords = ggez_mint::Point2 {x, y};
@@ -243,12 +516,33 @@ impl ggez_event::EventHandler for SandCastingGameState {
                 if let Ok(event_hex_pos) = HexGridCell::pixel_to_hex_coords(event_coords, &self.ci_ctx, ggez_ctx) {
                     mt_log!(Level::Debug, "Event ({:?}) occurred at position: {}", button, event_hex_pos);
 
-                    self.world_grid_manager.toggle_cell_highlight(&event_hex_pos, ggez_ctx).unwrap();
+                    // Select the actor in the clicked hex, or highlight the hex if there isn't one
+                    match self.actor_manager.actor_idx_at(&event_hex_pos) {
+                        Some(actor_idx) => {
+                            let selection = if self.actor_manager.selected() == Some(actor_idx) { None } else { Some(actor_idx) };
+                            self.actor_manager.select(selection, ggez_ctx);
+                        },
+                        None => self.world_grid_manager.toggle_cell_highlight(&event_hex_pos, ggez_ctx).unwrap(),
+                    }
                 }
                 else {
                     mt_log!(Level::Debug, "Event ({:?}) occurred outside hex grid at pixel coords ({}, {})", button, event_coords.x, event_coords.y);
                 }
             },
+            ggez_mouse::MouseButton::Right => {
+                // Harvest for the actor in the clicked hex, if there is one
+                if let Ok(event_hex_pos) = HexGridCell::pixel_to_hex_coords(event_coords, &self.ci_ctx, ggez_ctx) {
+                    if let Some(actor_idx) = self.actor_manager.actor_idx_at(&event_hex_pos) {
+                        match self.actor_manager.harvest(actor_idx,
+                                                         actor_manager::MANUAL_HARVEST_AMOUNT,
+                                                         &mut self.resource_manager,
+                                                         ggez_ctx) {
+                            Some((element, amount)) => mt_log!(Level::Info, "Harvested {:.2} {} at {}", amount, String::from(element), event_hex_pos),
+                            None => mt_log!(Level::Debug, "Nothing to harvest at {}", event_hex_pos),
+                        }
+                    }
+                }
+            },
             _ => {
                 mt_log!(Level::Warning, "Mouse Event ({:?}) unimplemented!", button);
             }
@@ -54,7 +54,13 @@ use mt_logger::{
 pub mod game_assets;
 use game_assets::colors;
 
+pub mod game_clock;
+
 pub mod game_managers;
+use game_managers::{
+    weather_script::WeatherScript,
+    weather_transition,
+};
 
 pub mod game_state;
 use game_state::SandCastingGameState;
@@ -120,14 +126,28 @@ fn main() {
         mt_new!(None, Level::Trace, OutputStream::Both);
     }
 
+    // Determine the seed for this run, generating one if not specified. It seeds map generation,
+    // regional weather, weather effects and the AI, but not cast_iron's own random weather and instances
+    let seed = match arg_value(&args, "-seed").map(|seed_str| seed_str.parse::<u64>()) {
+        Some(Ok(seed)) => seed,
+        Some(Err(e)) => {
+            mt_log!(Level::Error, "Invalid -seed value ({}), generating a random seed instead", e);
+            rand::random::<u64>()
+        },
+        None => rand::random::<u64>(),
+    };
+    mt_log!(Level::Info, "Using seed {}", seed);
+
+    // Describe the run for the profiler's records
+    let run_metadata = profiler::run_metadata::RunMetadata::new(
+        seed,
+        DEFAULT_GRID_RADIUS,
+        (DEFAULT_WINDOW_SIZE_X, DEFAULT_WINDOW_SIZE_Y),
+        args.clone()
+    );
+
     // Create profiler instance, or disable if required
-    let profiler_original;
-    if args.contains(&String::from("-profile")) {
-        profiler_original = profiler::Instance::default();
-    }
-    else {
-        profiler_original = profiler::Instance::disabled();
-    }
+    let profiler_original = create_profiler(&args, &run_metadata);
 
     // Create CastIron game context
     let ci_ctx = CastIronContextBuilder::default()
@@ -182,7 +202,79 @@ fn main() {
     mt_log!(Level::Info, "ggez context, event loop created.");
 
     // Use built context to create a GGEZ Event Handler instance
-    let mut sand_casting_game_state = SandCastingGameState::new(&profiler_original, &ci_ctx, &mut ggez_ctx);
+    let mut sand_casting_game_state = SandCastingGameState::new(&profiler_original, &ci_ctx, seed, &mut ggez_ctx);
+
+    // Override which map generation stages run if specified
+    if let Some(stages_str) = arg_value(&args, "-map-stages") {
+        if let Err(e) = sand_casting_game_state.map_generator().set_stages(stages_str) {
+            mt_log!(Level::Error, "Invalid -map-stages value ({}): {}", stages_str, e);
+        }
+    }
+
+    // Reject obstacles that would cut off part of the map if specified
+    if args.contains(&String::from("-keep-connected")) {
+        sand_casting_game_state.obstacle_manager().set_keep_connected(true);
+    }
+
+    // Override the crossfade between weather events if specified
+    if let Some(crossfade_str) = arg_value(&args, "-weather-crossfade") {
+        match crossfade_str.parse::<f64>() {
+            Ok(crossfade_secs) if (0.0..=weather_transition::MAX_CROSSFADE_SECS).contains(&crossfade_secs) => {
+                sand_casting_game_state.weather_manager().set_crossfade(std::time::Duration::from_secs_f64(crossfade_secs));
+            },
+            _ => mt_log!(Level::Error, "Invalid -weather-crossfade value ({}), expected from 0 to {} seconds",
+                         crossfade_str, weather_transition::MAX_CROSSFADE_SECS),
+        }
+    }
+
+    // Override the resource regeneration rate if specified
+    if let Some(regen_str) = arg_value(&args, "-resource-regen") {
+        match regen_str.parse::<f64>() {
+            Ok(regen_rate) if regen_rate.is_finite() && regen_rate >= 0.0 => {
+                sand_casting_game_state.resource_manager().set_regen_rate(regen_rate);
+            },
+            _ => mt_log!(Level::Error, "Invalid -resource-regen value ({}), expected a non-negative fraction of a full resource per second", regen_str),
+        }
+    }
+
+    // Override the rate at which actors harvest if specified
+    if let Some(harvest_str) = arg_value(&args, "-harvest-rate") {
+        match harvest_str.parse::<f64>() {
+            Ok(harvest_rate) if harvest_rate.is_finite() && harvest_rate >= 0.0 => {
+                sand_casting_game_state.actor_manager().set_harvest_rate(harvest_rate);
+            },
+            _ => mt_log!(Level::Error, "Invalid -harvest-rate value ({}), expected a non-negative fraction of a full resource per second", harvest_str),
+        }
+    }
+
+    // Override how far actors can see if specified
+    if let Some(sight_str) = arg_value(&args, "-sight-range") {
+        match sight_str.parse::<usize>() {
+            Ok(sight_range) => sand_casting_game_state.actor_manager().set_sight_range(sight_range),
+            Err(_e) => mt_log!(Level::Error, "Invalid -sight-range value ({}), expected a whole number of cells", sight_str),
+        }
+    }
+
+    // Override the behaviors given to actors if specified
+    if let Some(behaviors_str) = arg_value(&args, "-actor-behaviors") {
+        if let Err(e) = sand_casting_game_state.actor_manager().set_default_behaviors(behaviors_str) {
+            mt_log!(Level::Error, "Invalid -actor-behaviors value ({}): {}", behaviors_str, e);
+        }
+    }
+
+    // Play back a weather script if specified, instead of starting with random weather
+    if let Some(script_path) = arg_value(&args, "-weather-script") {
+        match WeatherScript::load(std::path::Path::new(script_path)) {
+            Ok(script) => {
+                mt_log!(Level::Info, "Playing weather script {} ({} events)", script_path, script.events().len());
+                sand_casting_game_state.weather_manager().play_script(script);
+            },
+            Err(e) => {
+                mt_log!(Level::Error, "{}, using random weather instead", e);
+                eprintln!("{}, using random weather instead", e);
+            }
+        }
+    }
 
     // Run the game!
     match ggez_event::run(&mut ggez_ctx, &mut ggez_event_loop, &mut sand_casting_game_state) {
@@ -190,6 +282,9 @@ fn main() {
         Err(e)  => mt_log!(Level::Error, "Error occurred: {}", e)
     }
 
+    // Let the profiler record everything already sent (and report any drops) before exiting
+    profiler_original.shutdown();
+
     // Flush all log messages before shutting down
     match mt_flush!() {
         // Ignore success case, and uninitialized logger
@@ -201,3 +296,93 @@ fn main() {
         }
     }
 }
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Creates a profiler instance with the sinks selected by the given arguments
+#[cfg(feature = "profiling")]
+fn create_profiler(args: &[String], run_metadata: &profiler::run_metadata::RunMetadata) -> profiler::Instance {
+    // Select profiler sinks based on arguments
+    let mut profiler_sinks: Vec<Box<dyn profiler::metrics_receiver::MetricsSink>> = Vec::new();
+    if args.contains(&String::from("-profile")) {
+        let metrics_dir = std::path::Path::new(arg_value(args, "-metrics-dir").map_or(profiler::file_sink::DEFAULT_METRICS_DIR, |dir| dir.as_str()));
+        let run_name = arg_value(args, "-run-name").map(|name| name.as_str());
+
+        // Failing to set up the files only disables file profiling, it shouldn't take the game down with it
+        match profiler::file_sink::FileSink::new(metrics_dir, run_name, run_metadata) {
+            Ok(file_sink) => {
+                mt_log!(Level::Info, "Recording metrics to {}", file_sink.run_dir().display());
+                profiler_sinks.push(Box::new(file_sink));
+            },
+            Err(e) => {
+                mt_log!(Level::Error, "Failed to set up metrics files, file profiling disabled: {}", e);
+                eprintln!("Failed to set up metrics files, file profiling disabled: {}", e);
+            },
+        }
+    }
+    if args.contains(&String::from("-profile-stream")) {
+        match arg_value(args, "-profile-stream") {
+            Some(address_str) => {
+                let address = profiler::socket_sink::StreamAddress::parse(address_str);
+                match profiler::socket_sink::SocketSink::bind(&address) {
+                    Ok(socket_sink) => {
+                        mt_log!(Level::Info, "Streaming metrics on {}", address);
+                        profiler_sinks.push(Box::new(socket_sink));
+                    },
+                    Err(e) => mt_log!(Level::Error, "Failed to bind metrics stream to {}: {}", address, e),
+                }
+            },
+            None => mt_log!(Level::Error, "-profile-stream requires an address, e.g. 127.0.0.1:9000 or unix:/tmp/sand_casting.sock"),
+        }
+    }
+
+    // Bound the metrics queue so a slow sink can't grow it without limit
+    let mut queue_config = profiler::metrics_queue::QueueConfig::default();
+    if let Some(capacity_str) = arg_value(args, "-profile-queue") {
+        match capacity_str.parse::<usize>() {
+            Ok(0) => mt_log!(Level::Error, "Invalid -profile-queue value (must be at least 1), using default capacity of {}", queue_config.capacity),
+            Ok(capacity) => queue_config.capacity = capacity,
+            Err(e) => mt_log!(Level::Error, "Invalid -profile-queue value ({}), using default capacity of {}", e, queue_config.capacity),
+        }
+    }
+    if let Some(policy_str) = arg_value(args, "-profile-overflow") {
+        match policy_str.parse::<profiler::metrics_queue::OverflowPolicy>() {
+            Ok(policy) => queue_config.policy = policy,
+            Err(e) => mt_log!(Level::Error, "{}, using default policy of {}", e, queue_config.policy),
+        }
+    }
+
+    // Create profiler instance (disabled if no sinks were selected)
+    let mut profiler = profiler::Instance::new(profiler_sinks, queue_config);
+
+    // Watch for frames exceeding the given budget (in ms)
+    if let Some(budget_str) = arg_value(args, "-frame-budget") {
+        match budget_str.parse::<f64>() {
+            Ok(budget_ms) if budget_ms > 0.0 => profiler.set_frame_budget(Some(budget_ms / 1000.0)),
+            _ => mt_log!(Level::Error, "Invalid -frame-budget value ({}), expected a positive number of milliseconds", budget_str),
+        }
+    }
+
+    profiler
+}
+
+/// Creates a (no-op) profiler instance, warning if profiling was requested
+#[cfg(not(feature = "profiling"))]
+fn create_profiler(args: &[String], _run_metadata: &profiler::run_metadata::RunMetadata) -> profiler::Instance {
+    if args.iter().any(|arg| arg == "-profile" || arg == "-profile-stream" || arg == "-frame-budget") {
+        mt_log!(Level::Warning, "Profiling requested, but sand_casting was built without the 'profiling' feature");
+        eprintln!("Profiling requested, but sand_casting was built without the 'profiling' feature");
+    }
+
+    profiler::Instance::disabled()
+}
+
+/// Returns the value following the given flag in the argument list, if any
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a String> {
+    let idx = args.iter().position(|arg| arg == flag)?;
+
+    args.get(idx + 1)
+}
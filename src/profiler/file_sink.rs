@@ -0,0 +1,167 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : profiler/file_sink.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module will provide a metrics sink that records each metric to its
+    own CSV file on disk.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    fs,
+    io::{
+        self,
+        prelude::*,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use crate::profiler::{
+    self,
+    metrics_format,
+    metrics_receiver::MetricsSink,
+    run_metadata::RunMetadata,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Default top-level directory under which each run's directory is created
+pub const DEFAULT_METRICS_DIR: &str = "metrics";
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+pub struct FileSink {
+    run_dir:    PathBuf,
+    files:      Vec<fs::File>,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl FileSink {
+    /// Fully-qualified constructor. Creates the run directory under the given metrics
+    /// directory, named after the run (or the start time if no name is given), and writes
+    /// the run metadata and empty metrics files to it. Refuses to reuse a run directory
+    /// that already holds anything, rather than overwrite an earlier run's data.
+    pub fn new(metrics_dir: &Path, run_name: Option<&str>, metadata: &RunMetadata) -> io::Result<Self> {
+        let run_name = match run_name {
+            Some(name) => String::from(name),
+            None => format!("{}", metadata.start_time().format("%F_%H_%M_%S%.3f")),
+        };
+
+        // Create directory for current run, along with any missing parents
+        let run_dir = metrics_dir.join(&run_name);
+        fs::create_dir_all(&run_dir).map_err(|e| Self::annotate(e, &run_dir))?;
+        if fs::read_dir(&run_dir).map_err(|e| Self::annotate(e, &run_dir))?.next().is_some() {
+            return Err(Self::annotate(io::Error::new(io::ErrorKind::AlreadyExists, "Run directory is not empty"), &run_dir));
+        }
+
+        // Record the run configuration alongside the data
+        metadata.write(&run_dir, &run_name).map_err(|e| Self::annotate(e, &run_dir))?;
+
+        let files = Self::create_files(&run_dir)?;
+
+        Ok(Self {run_dir, files})
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn run_dir(&self) -> &Path {
+        &self.run_dir
+    }
+
+    fn file_handle(&mut self, metric: &profiler::MetricContainer) -> &mut fs::File {
+        &mut self.files[usize::from(metric)]
+    }
+
+
+    /*  *  *  *  *  *  *
+     * Helper Methods  *
+     *  *  *  *  *  *  */
+
+    fn create_files(run_dir: &Path) -> io::Result<Vec<fs::File>> {
+        let mut files = Vec::new();
+
+        //OPT: *DESIGN* Would be cleaner if this were an iterator
+        // Create standard metrics files
+        for metric_idx in 0 .. profiler::MetricContainer::VARIANT_COUNT {
+            // Get the current metric's filename
+            let file_path = run_dir.join(profiler::MetricContainer::from(metric_idx).filename());
+
+            let file = fs::File::create(&file_path).map_err(|e| Self::annotate(e, &file_path))?;
+            files.push(file);
+        }
+
+        Ok(files)
+    }
+
+    /// Adds the offending path to an I/O error's message
+    fn annotate(err: io::Error, path: &Path) -> io::Error {
+        io::Error::new(err.kind(), format!("{} ({})", err, path.display()))
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl MetricsSink for FileSink {
+    fn record(&mut self, metric: &profiler::MetricContainer) {
+        // Format metric based on container type
+        let formatted_metric = match metric {
+            profiler::MetricContainer::AvgFps(timestamp, avg_fps) => {
+                metrics_format::format_f64_record(*timestamp, *avg_fps, 0)
+            }
+            profiler::MetricContainer::FrameDeltaTime(timestamp, delta) => {
+                metrics_format::format_f64_record(*timestamp, *delta, metrics_format::TIME_PRECISION)
+            },
+            profiler::MetricContainer::EventMarker(timestamp, event_label, attributes) => {
+                metrics_format::format_event_record(*timestamp, event_label, attributes)
+            },
+            profiler::MetricContainer::StackedDrawTime(timestamp, stacked_times) |
+            profiler::MetricContainer::StackedUpdateTime(timestamp, stacked_times) |
+            profiler::MetricContainer::StackedInitTime(timestamp, stacked_times) |
+            profiler::MetricContainer::MeshUpdateTime(timestamp, stacked_times) => {
+                let phases = profiler::phase_durations(*timestamp, stacked_times);
+                metrics_format::format_stacked_record(*timestamp, &phases, metrics_format::TIME_PRECISION)
+            },
+            profiler::MetricContainer::DroppedMetrics(timestamp, count) => {
+                metrics_format::format_f64_record(*timestamp, *count as f64, 0)
+            },
+            profiler::MetricContainer::SlowFrame(timestamp, record) => {
+                metrics_format::format_slow_frame_record(*timestamp, record)
+            },
+        };
+
+        // Write to the appropriate file
+        self.file_handle(metric).write_all(formatted_metric.as_bytes()).unwrap();
+    }
+}
@@ -0,0 +1,237 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : profiler/frame_stats.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module will provide data structures and functions for keeping a
+    rolling window of frame delta times and summarizing them.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::collections::VecDeque;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Default number of frame deltas kept in the rolling window
+pub const DEFAULT_FRAME_STATS_WINDOW: usize = 300;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Rolling window of the most recent frame delta times (in seconds)
+#[derive(Clone, Debug)]
+pub struct FrameStats {
+    capacity:   usize,
+    deltas:     VecDeque<f64>,
+}
+
+/// Summary statistics for a set of frame delta times (in seconds)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStatsSummary {
+    pub min:    f64,
+    pub p50:    f64,
+    pub p95:    f64,
+    pub p99:    f64,
+    pub max:    f64,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl FrameStats {
+    /// Fully-qualified constructor
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            deltas: VecDeque::with_capacity(capacity),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Returns the frame deltas in the window, oldest first
+    pub fn deltas(&self) -> &VecDeque<f64> {
+        &self.deltas
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Pushes a new frame delta onto the window, evicting the oldest if full
+    pub fn push(&mut self, delta: f64) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.deltas.len() == self.capacity {
+            self.deltas.pop_front();
+        }
+        self.deltas.push_back(delta);
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Computes min/p50/p95/p99/max over the current window
+    pub fn summary(&self) -> FrameStatsSummary {
+        let samples: Vec<f64> = self.deltas.iter().copied().collect();
+
+        FrameStatsSummary::from_samples(&samples)
+    }
+}
+
+
+impl FrameStatsSummary {
+    /// Computes summary statistics over an arbitrary set of samples
+    pub fn from_samples(samples: &[f64]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        Self {
+            min:    sorted[0],
+            p50:    percentile(&sorted, 50.0),
+            p95:    percentile(&sorted, 95.0),
+            p99:    percentile(&sorted, 99.0),
+            max:    sorted[sorted.len() - 1],
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the nearest-rank percentile of an already-sorted slice
+pub fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    let idx = rank.max(1).min(sorted.len()) - 1;
+
+    sorted[idx]
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_FRAME_STATS_WINDOW)
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_of_empty_slice_is_zero() {
+        assert_eq!(percentile(&[], 50.0), 0.0);
+        assert_eq!(FrameStatsSummary::from_samples(&[]), FrameStatsSummary::default());
+    }
+
+    #[test]
+    fn percentile_of_one_sample_is_that_sample() {
+        for pct in &[0.0, 1.0, 50.0, 99.0, 100.0] {
+            assert_eq!(percentile(&[0.016], *pct), 0.016);
+        }
+    }
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let sorted: Vec<f64> = (1..=10).map(f64::from).collect();
+
+        // p0 clamps to the first sample, and p100 is the last
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 100.0), 10.0);
+
+        // Ranks round up: 50% of 10 is exactly rank 5, 51% is rank 6
+        assert_eq!(percentile(&sorted, 50.0), 5.0);
+        assert_eq!(percentile(&sorted, 51.0), 6.0);
+        assert_eq!(percentile(&sorted, 95.0), 10.0);
+    }
+
+    #[test]
+    fn summary_sorts_samples() {
+        let summary = FrameStatsSummary::from_samples(&[0.030, 0.010, 0.020]);
+
+        assert_eq!(summary.min, 0.010);
+        assert_eq!(summary.p50, 0.020);
+        assert_eq!(summary.p99, 0.030);
+        assert_eq!(summary.max, 0.030);
+    }
+
+    #[test]
+    fn window_evicts_oldest_delta() {
+        let mut stats = FrameStats::new(3);
+        for delta in &[1.0, 2.0, 3.0, 4.0] {
+            stats.push(*delta);
+        }
+
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.deltas().iter().copied().collect::<Vec<_>>(), vec![2.0, 3.0, 4.0]);
+        assert_eq!(stats.summary().min, 2.0);
+        assert_eq!(stats.summary().max, 4.0);
+    }
+
+    #[test]
+    fn zero_capacity_window_stays_empty() {
+        let mut stats = FrameStats::new(0);
+        stats.push(1.0);
+
+        assert!(stats.is_empty());
+        assert_eq!(stats.summary(), FrameStatsSummary::default());
+    }
+}
@@ -0,0 +1,453 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : profiler/instance.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module provides the profiler Instance used when the 'profiling'
+    feature is enabled. Metrics are cached for display, and sent off to the
+    receiver thread if profiling was enabled at runtime.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use ggez::{
+    Context as GgEzContext,
+    graphics as ggez_gfx,
+    mint as ggez_mint,
+    timer as ggez_timer,
+};
+
+use mt_logger::{
+    mt_log,
+    Level,
+};
+
+use crate::{
+    game_assets::colors,
+    profiler::{
+        self,
+        MetricContainer,
+        ProfilerResult,
+        StackedTime,
+        frame_stats::FrameStats,
+        metrics_format::{
+            self,
+            EventAttribute,
+            EventRecord,
+            SlowFrameRecord,
+        },
+        metrics_queue::{
+            self,
+            OverflowPolicy,
+            QueueConfig,
+        },
+        metrics_receiver::{
+            MetricsReceiver,
+            MetricsSink,
+        },
+        metrics_sender::MetricsSender,
+        overlay,
+    },
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// How far back (in ms) event markers are kept for slow frame snapshots
+const EVENT_HISTORY_WINDOW_MS: u64 = 2000;
+
+/// Maximum number of event markers kept for slow frame snapshots
+const EVENT_HISTORY_CAPACITY: usize = 256;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Instance of the SandCasting profiler module
+#[derive(Clone)]
+pub struct Instance {
+    enabled:            bool,
+    overlay_visible:    bool,
+    frame_budget:       Option<f64>,
+    sender:             MetricsSender,
+    cached_metrics:     CachedMetrics,
+    // Shared between clones, so that events marked by any module show up in slow frame snapshots
+    event_history:      Arc<Mutex<VecDeque<EventRecord>>>,
+    // Shared between clones, so that whichever shuts the profiler down can wait on the receiver
+    receiver_thread:    Arc<Mutex<Option<thread::JoinHandle<()>>>>,
+}
+
+#[derive(Clone, Default)]
+struct CachedMetrics {
+    pub avg_fps:        f64,
+    pub peak_fps:       f64,
+    pub frame_stats:    FrameStats,
+    pub latest_phases:  Vec<(String, f64)>,
+}
+
+/// Collects a stack of labelled timestamps over the course of a frame
+pub struct StackTimer {
+    active:     bool,
+    start_time: Duration,
+    stack:      Vec<StackedTime>,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Instance {
+    /// Fully-qualified constructor. Spawns a receiver thread feeding the given sinks through
+    /// a queue bounded as configured, or returns a disabled instance if there are no sinks.
+    pub fn new(sinks: Vec<Box<dyn MetricsSink>>, queue_config: QueueConfig) -> Self {
+        if sinks.is_empty() {
+            return Self::disabled();
+        }
+
+        // Create the metrics data queue
+        let (metrics_tx, metrics_rx) = metrics_queue::bounded(queue_config);
+
+        //OPT: *PERFORMANCE* Would be better to set the receiver thread's priority as low as possible
+        // Initialize receiver struct, build and spawn thread
+        let mut metrics_receiver = MetricsReceiver::new(metrics_rx, sinks);
+        let receiver_thread = thread::Builder::new()
+            .name(String::from("metrics_receiver"))
+            .spawn(move || metrics_receiver.main())
+            .unwrap();
+
+        Self {
+            enabled:            true,
+            overlay_visible:    false,
+            frame_budget:       None,
+            sender:             MetricsSender::new(metrics_tx),
+            cached_metrics:     CachedMetrics::default(),
+            event_history:      Arc::new(Mutex::new(VecDeque::new())),
+            receiver_thread:    Arc::new(Mutex::new(Some(receiver_thread))),
+        }
+    }
+
+    //OPT: *DESIGN* Would be cool to make a Disablable trait
+    pub fn disabled() -> Self {
+        // Create dummy queue handles
+        let (dummy_tx, _dummy_rx) = metrics_queue::bounded(QueueConfig {capacity: 0, policy: OverflowPolicy::DropOldest});
+
+        // Initialize dummy sender struct
+        let dummy_sender = MetricsSender::new(dummy_tx);
+
+        Self {
+            enabled:            false,
+            overlay_visible:    false,
+            frame_budget:       None,
+            sender:             dummy_sender,
+            cached_metrics:     CachedMetrics::default(),
+            event_history:      Arc::new(Mutex::new(VecDeque::new())),
+            receiver_thread:    Arc::new(Mutex::new(None)),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn avg_fps(&self) -> f64 {
+        self.cached_metrics.avg_fps
+    }
+
+    pub fn peak_fps(&self) -> f64 {
+        self.cached_metrics.peak_fps
+    }
+
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.cached_metrics.frame_stats
+    }
+
+    pub fn overlay_visible(&self) -> bool {
+        self.overlay_visible
+    }
+
+    /// Returns the frame-time budget (in seconds), if one has been set
+    pub fn frame_budget(&self) -> Option<f64> {
+        self.frame_budget
+    }
+
+    /// Returns the number of metrics dropped because the receiver fell behind
+    pub fn dropped_metrics(&self) -> u64 {
+        self.sender.dropped_count()
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    pub fn toggle_overlay(&mut self) {
+        self.overlay_visible = !self.overlay_visible;
+    }
+
+    /// Sets the frame-time budget (in seconds). Frames exceeding it are logged and snapshotted.
+    /// Must be set before the instance is cloned out to other modules.
+    pub fn set_frame_budget(&mut self, frame_budget: Option<f64>) {
+        self.frame_budget = frame_budget;
+    }
+
+    /// Stops sending metrics from every clone, and waits for the receiver to record those already sent
+    pub fn shutdown(&self) {
+        self.sender.close();
+
+        if let Some(receiver_thread) = self.receiver_thread.lock().unwrap().take() {
+            if receiver_thread.join().is_err() {
+                eprintln!("MetricsReceiver thread panicked, some metrics may not have been recorded");
+            }
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    pub fn draw_fps_stats(&self, ggez_ctx: &mut GgEzContext) {
+        //OPT: *PERFORMANCE* "static" storage of these local variables would probably be quicker
+        // Draw avg. FPS
+        let avg_fps_pos = ggez_mint::Point2 {x: 0.0, y: 0.0};
+        let avg_fps_str = format!("Avg. FPS: {:.0}", self.cached_metrics.avg_fps);
+        let avg_fps_display = ggez_gfx::Text::new((avg_fps_str, ggez_gfx::Font::default(),crate::DEFAULT_TEXT_SIZE));
+        ggez_gfx::draw(ggez_ctx, &avg_fps_display, (avg_fps_pos, 0.0, colors::GREEN)).unwrap();
+
+        // Draw peak FPS
+        let peak_fps_pos = ggez_mint::Point2 {x: 0.0, y: 20.0};
+        let peak_fps_str = format!("Peak FPS: {:.0}", self.cached_metrics.peak_fps);
+        let peak_fps_display = ggez_gfx::Text::new((peak_fps_str, ggez_gfx::Font::default(),crate::DEFAULT_TEXT_SIZE));
+        ggez_gfx::draw(ggez_ctx, &peak_fps_display, (peak_fps_pos, 0.0, colors::GREEN)).unwrap();
+    }
+
+    /// Draws the live performance overlay, if it has been toggled on
+    pub fn draw_overlay(&self, ggez_ctx: &mut GgEzContext) {
+        if self.overlay_visible {
+            overlay::draw(&self.cached_metrics.frame_stats,
+                          &self.cached_metrics.latest_phases,
+                          self.dropped_metrics(),
+                          ggez_ctx);
+        }
+    }
+
+    pub fn update_fps_stats(&mut self, ggez_ctx: &GgEzContext) -> ProfilerResult {
+        // Get elapsed time
+        let elapsed_time = ggez_timer::time_since_start(ggez_ctx);
+
+        // Update cached avg. FPS
+        self.cached_metrics.avg_fps = ggez_timer::fps(ggez_ctx);
+
+        // Update cached peak FPS if appropriate
+        if self.cached_metrics.avg_fps > self.cached_metrics.peak_fps {
+            self.cached_metrics.peak_fps = self.cached_metrics.avg_fps;
+        }
+
+        if self.enabled {
+            // Pack up FPS in a container and send
+            let metric = MetricContainer::AvgFps(elapsed_time, self.cached_metrics.avg_fps);
+            self.sender.send_metric(metric)
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    pub fn send_frame_delta(&mut self, ggez_ctx: &GgEzContext) -> ProfilerResult {
+        // Get frame delta and convert to f64
+        let frame_delta = ggez_timer::delta(ggez_ctx).as_secs_f64();
+
+        // Update the rolling window of frame deltas
+        self.cached_metrics.frame_stats.push(frame_delta);
+
+        // Get elapsed time
+        let elapsed_time = ggez_timer::time_since_start(ggez_ctx);
+
+        if self.enabled {
+            // Pack up frame delta in a container and send
+            let metric = MetricContainer::FrameDeltaTime(elapsed_time, frame_delta);
+            self.sender.send_metric(metric)?;
+        }
+
+        // Raise the alarm if the frame ran over budget
+        match self.frame_budget {
+            Some(budget) if frame_delta > budget => self.alert_slow_frame(elapsed_time, frame_delta, budget, ggez_ctx),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn mark_event(&self, event_label: &str, ggez_ctx: &GgEzContext) -> ProfilerResult {
+        self.mark_event_with_attributes(event_label, Vec::new, ggez_ctx)
+    }
+
+    /// Marks an event along with attributes describing it, e.g. the element of a new weather event.
+    /// The attributes are only built if the event will be recorded.
+    pub fn mark_event_with_attributes<F>(&self,
+                                         event_label: &str,
+                                         attributes: F,
+                                         ggez_ctx: &GgEzContext) -> ProfilerResult
+    where F: FnOnce() -> Vec<EventAttribute> {
+        if self.enabled {
+            let attributes = attributes();
+
+            // Get elapsed time
+            let elapsed_time = ggez_timer::time_since_start(ggez_ctx);
+
+            // Remember the event in case an upcoming frame runs over budget
+            if self.frame_budget.is_some() {
+                self.remember_event(elapsed_time, event_label, &attributes);
+            }
+
+            // Pack up event label in a container and send
+            let metric = MetricContainer::EventMarker(elapsed_time, String::from(event_label), attributes);
+            self.sender.send_metric(metric)
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    /// Starts a stack timer for the current frame. The timer only records if its results will be used.
+    pub fn start_stack(&self, ggez_ctx: &GgEzContext) -> StackTimer {
+        let active = self.enabled || self.overlay_visible || self.frame_budget.is_some();
+
+        StackTimer {
+            active,
+            start_time: if active { ggez_timer::time_since_start(ggez_ctx) } else { Duration::default() },
+            stack:      Vec::new(),
+        }
+    }
+
+    pub fn send_stacked_draw_time(&mut self, stack_timer: StackTimer) -> ProfilerResult {
+        // Cache the per-phase durations for the overlay and slow frame alerts
+        if stack_timer.active && (self.overlay_visible || self.frame_budget.is_some()) {
+            self.cached_metrics.latest_phases = profiler::phase_durations(stack_timer.start_time, &stack_timer.stack);
+        }
+
+        self.send_stack(stack_timer, MetricContainer::StackedDrawTime)
+    }
+
+    pub fn send_stacked_update_time(&self, stack_timer: StackTimer) -> ProfilerResult {
+        self.send_stack(stack_timer, MetricContainer::StackedUpdateTime)
+    }
+
+    pub fn send_stacked_init_time(&self, stack_timer: StackTimer) -> ProfilerResult {
+        self.send_stack(stack_timer, MetricContainer::StackedInitTime)
+    }
+
+    pub fn send_mesh_update_time(&self, stack_timer: StackTimer) -> ProfilerResult {
+        self.send_stack(stack_timer, MetricContainer::MeshUpdateTime)
+    }
+
+
+    /*  *  *  *  *  *  *
+     * Helper Methods  *
+     *  *  *  *  *  *  */
+
+    /// Logs the phase breakdown of a frame that ran over budget and, if profiling, records a
+    /// marker for it along with a snapshot of the events leading up to it.
+    /// The cached phases were recorded by the previous draw, i.e. the frame that ran long.
+    fn alert_slow_frame(&self, elapsed_time: Duration, frame_delta: f64, budget: f64, ggez_ctx: &GgEzContext) -> ProfilerResult {
+        let phases_str: Vec<String> = self.cached_metrics.latest_phases.iter()
+            .map(|(label, time)| format!("{}: {:.3} ms", label, time * 1000.0))
+            .collect();
+        mt_log!(Level::Warning,
+                "Slow frame: {:.3} ms exceeded budget of {:.3} ms [{}]",
+                frame_delta * 1000.0,
+                budget * 1000.0,
+                phases_str.join(", "));
+
+        if !self.enabled {
+            return Ok(());
+        }
+
+        // Snapshot the history before the marker for this frame joins it
+        let elapsed_ms = elapsed_time.as_millis() as u64;
+        let record = SlowFrameRecord {
+            frame_delta,
+            budget,
+            phases:         self.cached_metrics.latest_phases.clone(),
+            recent_events:  self.event_history.lock().unwrap()
+                                .iter()
+                                .filter(|event| elapsed_ms.saturating_sub(event.timestamp_ms) <= EVENT_HISTORY_WINDOW_MS)
+                                .cloned()
+                                .collect(),
+        };
+
+        self.mark_event_with_attributes(metrics_format::SLOW_FRAME_EVENT,
+                                        || vec![EventAttribute::new("frame_ms", frame_delta * 1000.0),
+                                                EventAttribute::new("budget_ms", budget * 1000.0)],
+                                        ggez_ctx)?;
+        self.sender.send_metric(MetricContainer::SlowFrame(elapsed_time, record))
+    }
+
+    /// Adds an event to the shared history, discarding any that have aged out
+    fn remember_event(&self, elapsed_time: Duration, event_label: &str, attributes: &[EventAttribute]) {
+        let timestamp_ms = elapsed_time.as_millis() as u64;
+        let mut event_history = self.event_history.lock().unwrap();
+
+        event_history.push_back(EventRecord {
+            timestamp_ms,
+            label:      String::from(event_label),
+            attributes: attributes.to_vec(),
+        });
+
+        while event_history.len() > EVENT_HISTORY_CAPACITY ||
+              event_history.front().map_or(false, |event| timestamp_ms.saturating_sub(event.timestamp_ms) > EVENT_HISTORY_WINDOW_MS) {
+            event_history.pop_front();
+        }
+    }
+
+    /// Packs up the timer's stacked times into the given kind of container and sends it
+    fn send_stack(&self,
+                  stack_timer: StackTimer,
+                  container: fn(Duration, Vec<StackedTime>) -> MetricContainer) -> ProfilerResult {
+        if self.enabled && stack_timer.active {
+            self.sender.send_metric(container(stack_timer.start_time, stack_timer.stack))
+        }
+        else {
+            Ok(())
+        }
+    }
+}
+
+
+impl StackTimer {
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Marks the end of the phase with the given label
+    pub fn mark(&mut self, label: &'static str, ggez_ctx: &GgEzContext) {
+        if self.active {
+            self.stack.push(StackedTime {label, time: ggez_timer::time_since_start(ggez_ctx)});
+        }
+    }
+}
@@ -0,0 +1,158 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : profiler/instance_noop.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module provides the profiler Instance used when the 'profiling'
+    feature is disabled. It mirrors the API of the real Instance, but every
+    method is an inlined no-op so that call sites compile away entirely.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use ggez::Context as GgEzContext;
+
+use crate::profiler::{
+    ProfilerResult,
+    metrics_format::EventAttribute,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Zero-sized stand-in for the SandCasting profiler module
+#[derive(Clone, Default)]
+pub struct Instance;
+
+/// Zero-sized stand-in for the stack timer
+pub struct StackTimer;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Instance {
+    #[inline(always)]
+    pub fn disabled() -> Self {
+        Self
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    #[inline(always)]
+    pub fn overlay_visible(&self) -> bool {
+        false
+    }
+
+    #[inline(always)]
+    pub fn dropped_metrics(&self) -> u64 {
+        0
+    }
+
+    #[inline(always)]
+    pub fn frame_budget(&self) -> Option<f64> {
+        None
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    #[inline(always)]
+    pub fn toggle_overlay(&mut self) {}
+
+    #[inline(always)]
+    pub fn set_frame_budget(&mut self, _frame_budget: Option<f64>) {}
+
+    #[inline(always)]
+    pub fn shutdown(&self) {}
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    #[inline(always)]
+    pub fn draw_fps_stats(&self, _ggez_ctx: &mut GgEzContext) {}
+
+    #[inline(always)]
+    pub fn draw_overlay(&self, _ggez_ctx: &mut GgEzContext) {}
+
+    #[inline(always)]
+    pub fn update_fps_stats(&mut self, _ggez_ctx: &GgEzContext) -> ProfilerResult {
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn send_frame_delta(&mut self, _ggez_ctx: &GgEzContext) -> ProfilerResult {
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn mark_event(&self, _event_label: &str, _ggez_ctx: &GgEzContext) -> ProfilerResult {
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn mark_event_with_attributes<F>(&self,
+                                         _event_label: &str,
+                                         _attributes: F,
+                                         _ggez_ctx: &GgEzContext) -> ProfilerResult
+    where F: FnOnce() -> Vec<EventAttribute> {
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn start_stack(&self, _ggez_ctx: &GgEzContext) -> StackTimer {
+        StackTimer
+    }
+
+    #[inline(always)]
+    pub fn send_stacked_draw_time(&mut self, _stack_timer: StackTimer) -> ProfilerResult {
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn send_stacked_update_time(&self, _stack_timer: StackTimer) -> ProfilerResult {
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn send_stacked_init_time(&self, _stack_timer: StackTimer) -> ProfilerResult {
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn send_mesh_update_time(&self, _stack_timer: StackTimer) -> ProfilerResult {
+        Ok(())
+    }
+}
+
+
+impl StackTimer {
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    #[inline(always)]
+    pub fn mark(&mut self, _label: &'static str, _ggez_ctx: &GgEzContext) {}
+}
@@ -0,0 +1,659 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : profiler/metrics_format.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module defines the on-disk format of the metrics files, and provides
+    functions to both write and parse them.
+
+    NOTE: This module is shared between the game and the metrics tools, so it
+          must only depend on std.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    error::Error,
+    fmt,
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::Duration,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/* Filenames */
+pub const AVG_FPS_FILENAME:             &str = "avg_fps.csv";
+pub const FRAME_DELTA_FILENAME:         &str = "frame_delta.csv";
+pub const EVENT_MARKER_FILENAME:        &str = "event_marker.csv";
+pub const STACKED_DRAW_TIME_FILENAME:   &str = "stacked_draw_time.csv";
+pub const STACKED_UPDATE_TIME_FILENAME: &str = "stacked_update_time.csv";
+pub const STACKED_INIT_TIME_FILENAME:   &str = "stacked_init_time.csv";
+pub const MESH_UPDATE_TIME_FILENAME:    &str = "mesh_update_time.csv";
+pub const DROPPED_METRICS_FILENAME:     &str = "dropped_metrics.csv";
+pub const SLOW_FRAMES_FILENAME:         &str = "slow_frames.log";
+
+/* Delimiters */
+/// Separates one record from the next
+pub const RECORD_DELIMITER: char = ';';
+
+/// Separates the timestamp of a record from its payload
+pub const FIELD_DELIMITER:  char = ',';
+
+/// Separates the phases of a stacked record, or the label and attributes of an event record
+pub const STACK_DELIMITER:  char = '|';
+
+/// Separates the label of a stacked phase (or the key of an attribute) from its value
+pub const LABEL_DELIMITER:  char = '=';
+
+/// Separates the type tag of an attribute value from the value itself
+pub const TYPE_DELIMITER:   char = ':';
+
+/// Number of decimal places used for time values
+pub const TIME_PRECISION:   usize = 7;
+
+/* Event Spans */
+/// Suffix of an event label that opens a span
+pub const SPAN_START_SUFFIX: &str = "_START";
+
+/// Suffix of an event label that closes a span
+pub const SPAN_STOP_SUFFIX:  &str = "_STOP";
+
+/// Event marker recorded whenever a frame exceeds the frame-time budget
+pub const SLOW_FRAME_EVENT:  &str = "SLOW_FRAME";
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum MetricsFormatError {
+    Io(PathBuf, io::Error),
+    MalformedRecord(String),
+    MissingRunDir(PathBuf),
+}
+
+/// A timestamped numerical value
+#[derive(Clone, Debug, PartialEq)]
+pub struct F64Record {
+    pub timestamp_ms:   u64,
+    pub value:          f64,
+}
+
+/// A typed value attached to an event marker
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(String),
+}
+
+/// A named value attached to an event marker, e.g. the element of a new weather event
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventAttribute {
+    pub key:    String,
+    pub value:  AttributeValue,
+}
+
+/// A timestamped event label, with any attributes describing the event
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventRecord {
+    pub timestamp_ms:   u64,
+    pub label:          String,
+    pub attributes:     Vec<EventAttribute>,
+}
+
+/// A timestamped set of (label, seconds) phases
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackedRecord {
+    pub timestamp_ms:   u64,
+    pub phases:         Vec<(String, f64)>,
+}
+
+/// Context captured when a frame exceeds its time budget
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SlowFrameRecord {
+    /// Duration of the offending frame, in seconds
+    pub frame_delta:    f64,
+    /// Frame-time budget that was exceeded, in seconds
+    pub budget:         f64,
+    /// (label, seconds) draw phases of the offending frame
+    pub phases:         Vec<(String, f64)>,
+    /// Event markers leading up to the offending frame, oldest first
+    pub recent_events:  Vec<EventRecord>,
+}
+
+/// All metrics recorded in a single profiler run directory
+#[derive(Clone, Debug, Default)]
+pub struct MetricsRun {
+    pub path:                 PathBuf,
+    pub avg_fps:              Vec<F64Record>,
+    pub frame_deltas:         Vec<F64Record>,
+    pub events:               Vec<EventRecord>,
+    pub stacked_draw_times:   Vec<StackedRecord>,
+    pub stacked_update_times: Vec<StackedRecord>,
+    pub stacked_init_times:   Vec<StackedRecord>,
+    pub mesh_update_times:    Vec<StackedRecord>,
+    pub dropped_metrics:      Vec<F64Record>,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl EventAttribute {
+    /// Fully-qualified constructor
+    pub fn new<V: Into<AttributeValue>>(key: &str, value: V) -> Self {
+        Self {
+            key:    String::from(key),
+            value:  value.into(),
+        }
+    }
+}
+
+
+impl MetricsRun {
+    /// Loads all metrics files found in the given run directory.
+    /// Missing files are treated as empty, but the directory itself must exist.
+    pub fn load(run_dir: &Path) -> Result<Self, MetricsFormatError> {
+        if !run_dir.is_dir() {
+            return Err(MetricsFormatError::MissingRunDir(run_dir.to_path_buf()));
+        }
+
+        Ok(Self {
+            path:                 run_dir.to_path_buf(),
+            avg_fps:              parse_f64_records(&read_metrics_file(run_dir, AVG_FPS_FILENAME)?)?,
+            frame_deltas:         parse_f64_records(&read_metrics_file(run_dir, FRAME_DELTA_FILENAME)?)?,
+            events:               parse_event_records(&read_metrics_file(run_dir, EVENT_MARKER_FILENAME)?)?,
+            stacked_draw_times:   parse_stacked_records(&read_metrics_file(run_dir, STACKED_DRAW_TIME_FILENAME)?)?,
+            stacked_update_times: parse_stacked_records(&read_metrics_file(run_dir, STACKED_UPDATE_TIME_FILENAME)?)?,
+            stacked_init_times:   parse_stacked_records(&read_metrics_file(run_dir, STACKED_INIT_TIME_FILENAME)?)?,
+            mesh_update_times:    parse_stacked_records(&read_metrics_file(run_dir, MESH_UPDATE_TIME_FILENAME)?)?,
+            dropped_metrics:      parse_f64_records(&read_metrics_file(run_dir, DROPPED_METRICS_FILENAME)?)?,
+        })
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the name of the run, i.e. the name of its directory
+    pub fn name(&self) -> String {
+        match self.path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => self.path.display().to_string(),
+        }
+    }
+
+    /// Returns the frame deltas (in seconds), without timestamps
+    pub fn frame_delta_values(&self) -> Vec<f64> {
+        self.frame_deltas.iter().map(|record| record.value).collect()
+    }
+
+    /// Returns each kind of stacked timing recorded in the run, paired with a short name for it
+    pub fn stacks(&self) -> [(&'static str, &[StackedRecord]); 4] {
+        [
+            ("draw",    &self.stacked_draw_times),
+            ("update",  &self.stacked_update_times),
+            ("init",    &self.stacked_init_times),
+            ("mesh",    &self.mesh_update_times),
+        ]
+    }
+
+    /// Pairs up *_START/*_STOP event markers, returning the durations (in ms) of each span name,
+    /// in first-seen order. Unmatched markers are ignored.
+    pub fn event_span_durations(&self) -> Vec<(String, Vec<u64>)> {
+        event_spans(&self.events).0
+    }
+
+    /// Returns the total number of metrics dropped by the profiler's queue over the run.
+    /// Each record holds the running total, so the last one is the final count.
+    pub fn dropped_metric_count(&self) -> u64 {
+        match self.dropped_metrics.last() {
+            Some(record) => record.value as u64,
+            None => 0,
+        }
+    }
+
+    /// Returns the number of frames that exceeded the frame-time budget
+    pub fn slow_frame_count(&self) -> usize {
+        self.events.iter().filter(|event| event.label == SLOW_FRAME_EVENT).count()
+    }
+
+    /// Returns the time (in ms) spanned by the run's frame deltas
+    pub fn duration_ms(&self) -> u64 {
+        match (self.frame_deltas.first(), self.frame_deltas.last()) {
+            (Some(first), Some(last)) => last.timestamp_ms - first.timestamp_ms,
+            _ => 0,
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/*  *  *  *  *  *  *  *
+ *      Writing       *
+ *  *  *  *  *  *  *  */
+
+/// Formats a numerical value as a record
+pub fn format_f64_record(timestamp: Duration, value: f64, precision: usize) -> String {
+    format!(
+        "{timestamp}{field}{value:.precision$}{record}",
+        timestamp = timestamp.as_millis(),
+        field = FIELD_DELIMITER,
+        value = value,
+        precision = precision,
+        record = RECORD_DELIMITER
+    )
+}
+
+/// Formats an event label and its attributes as a record.
+/// Each attribute is written as key=tag:value, where the tag gives the value's type.
+pub fn format_event_record(timestamp: Duration, label: &str, attributes: &[EventAttribute]) -> String {
+    let mut payload = sanitize_label(label);
+    for attribute in attributes {
+        let (tag, value) = match &attribute.value {
+            AttributeValue::Int(value)   => ('i', value.to_string()),
+            AttributeValue::Float(value) => ('f', value.to_string()),
+            AttributeValue::Bool(value)  => ('b', value.to_string()),
+            AttributeValue::Text(value)  => ('s', sanitize_label(value)),
+        };
+
+        payload.push(STACK_DELIMITER);
+        payload.push_str(&format!("{}{}{}{}{}", sanitize_label(&attribute.key), LABEL_DELIMITER, tag, TYPE_DELIMITER, value));
+    }
+
+    format!(
+        "{timestamp}{field}{payload}{record}",
+        timestamp = timestamp.as_millis(),
+        field = FIELD_DELIMITER,
+        payload = payload,
+        record = RECORD_DELIMITER
+    )
+}
+
+/// Formats a set of (label, seconds) phases as a stacked record
+pub fn format_stacked_record(timestamp: Duration, phases: &[(String, f64)], precision: usize) -> String {
+    let formatted_phases: Vec<String> = phases.iter()
+        .map(|(label, time)| format!(
+            "{label}{delim}{time:.precision$}",
+            label = sanitize_label(label),
+            delim = LABEL_DELIMITER,
+            time = time,
+            precision = precision
+        ))
+        .collect();
+
+    format!(
+        "{timestamp}{field}{phases}{record}",
+        timestamp = timestamp.as_millis(),
+        field = FIELD_DELIMITER,
+        phases = formatted_phases.join(&STACK_DELIMITER.to_string()),
+        record = RECORD_DELIMITER
+    )
+}
+
+/// Formats a slow frame as a short, human-readable report section
+pub fn format_slow_frame_record(timestamp: Duration, record: &SlowFrameRecord) -> String {
+    let mut report = format!("Slow frame at {} ms: {:.3} ms (budget {:.3} ms, over by {:.3} ms)\n",
+                             timestamp.as_millis(),
+                             record.frame_delta * 1000.0,
+                             record.budget * 1000.0,
+                             (record.frame_delta - record.budget) * 1000.0);
+
+    report.push_str("  Phases:\n");
+    for (label, time) in &record.phases {
+        report.push_str(&format!("    {:<16} {:>9.3} ms\n", label, time * 1000.0));
+    }
+
+    report.push_str("  Recent events:\n");
+    for event in &record.recent_events {
+        if event.attributes.is_empty() {
+            report.push_str(&format!("    {:>9} ms  {}\n", event.timestamp_ms, event.label));
+        }
+        else {
+            report.push_str(&format!("    {:>9} ms  {} ({})\n", event.timestamp_ms, event.label, display_attributes(&event.attributes)));
+        }
+    }
+
+    let (spans, open_spans) = event_spans(&record.recent_events);
+    report.push_str("  Spans:\n");
+    for (name, durations) in &spans {
+        let durations_str: Vec<String> = durations.iter().map(|duration| format!("{} ms", duration)).collect();
+        report.push_str(&format!("    {:<16} {}\n", name, durations_str.join(", ")));
+    }
+    for (name, start_ms) in &open_spans {
+        report.push_str(&format!("    {:<16} open since {} ms\n", name, start_ms));
+    }
+
+    report.push('\n');
+    report
+}
+
+/// Formats attributes as a JSON object
+pub fn json_attributes(attributes: &[EventAttribute]) -> String {
+    let members: Vec<String> = attributes.iter()
+        .map(|attribute| {
+            let value = match &attribute.value {
+                AttributeValue::Int(value)   => value.to_string(),
+                AttributeValue::Float(value) => json_f64(*value),
+                AttributeValue::Bool(value)  => value.to_string(),
+                AttributeValue::Text(value)  => json_string(value),
+            };
+
+            format!("{}:{}", json_string(&attribute.key), value)
+        })
+        .collect();
+
+    format!("{{{}}}", members.join(","))
+}
+
+/// Formats attributes for display, e.g. "element=Fire, duration_s=4.2"
+pub fn display_attributes(attributes: &[EventAttribute]) -> String {
+    let formatted: Vec<String> = attributes.iter().map(|attribute| attribute.to_string()).collect();
+
+    formatted.join(", ")
+}
+
+/// Quotes and escapes a string for inclusion in a JSON document
+pub fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"'  => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+
+    escaped
+}
+
+/// Formats a float as a JSON number. JSON has no representation for NaN or infinity, so those become null.
+pub fn json_f64(value: f64) -> String {
+    if value.is_finite() {
+        format!("{}", value)
+    }
+    else {
+        String::from("null")
+    }
+}
+
+/// Replaces any characters reserved by the format
+pub fn sanitize_label(label: &str) -> String {
+    label.replace(|c: char| c == RECORD_DELIMITER ||
+                      c == FIELD_DELIMITER ||
+                      c == STACK_DELIMITER ||
+                      c == LABEL_DELIMITER,
+                  "_")
+}
+
+
+/*  *  *  *  *  *  *  *
+ *      Analysis      *
+ *  *  *  *  *  *  *  */
+
+/// Returns the labels of all phases in the given stacked records, in first-seen order
+pub fn stacked_phase_labels(records: &[StackedRecord]) -> Vec<String> {
+    let mut labels: Vec<String> = Vec::new();
+    for record in records {
+        for (label, _time) in &record.phases {
+            if !labels.contains(label) {
+                labels.push(label.clone());
+            }
+        }
+    }
+
+    labels
+}
+
+/// Returns the mean duration (in seconds) of each phase in the given stacked records
+pub fn stacked_phase_means(records: &[StackedRecord]) -> Vec<(String, f64)> {
+    stacked_phase_labels(records).into_iter().map(|label| {
+        let samples: Vec<f64> = records.iter()
+            .filter_map(|record| record.phases.iter().find(|(phase, _)| *phase == label).map(|(_, time)| *time))
+            .collect();
+
+        let mean = if samples.is_empty() { 0.0 } else { samples.iter().sum::<f64>() / samples.len() as f64 };
+        (label, mean)
+    }).collect()
+}
+
+
+/// Pairs up *_START/*_STOP event markers. Returns the durations (in ms) of each completed span name,
+/// in first-seen order, along with the (name, start ms) of any spans left open. Unmatched stops are ignored.
+pub fn event_spans(events: &[EventRecord]) -> (Vec<(String, Vec<u64>)>, Vec<(String, u64)>) {
+    let mut spans: Vec<(String, Vec<u64>)> = Vec::new();
+    let mut open_spans: Vec<(String, u64)> = Vec::new();
+
+    for event in events {
+        if let Some(name) = event.label.strip_suffix(SPAN_START_SUFFIX) {
+            open_spans.push((name.to_string(), event.timestamp_ms));
+        }
+        else if let Some(name) = event.label.strip_suffix(SPAN_STOP_SUFFIX) {
+            // Close the most recently opened span of the same name
+            if let Some(idx) = open_spans.iter().rposition(|(open_name, _)| open_name == name) {
+                let (_, start_ms) = open_spans.remove(idx);
+                let duration = event.timestamp_ms.saturating_sub(start_ms);
+
+                match spans.iter_mut().find(|(span_name, _)| span_name == name) {
+                    Some((_, durations)) => durations.push(duration),
+                    None => spans.push((name.to_string(), vec![duration])),
+                }
+            }
+        }
+    }
+
+    (spans, open_spans)
+}
+
+
+/*  *  *  *  *  *  *  *
+ *      Parsing       *
+ *  *  *  *  *  *  *  */
+
+pub fn parse_f64_records(data: &str) -> Result<Vec<F64Record>, MetricsFormatError> {
+    split_records(data).map(|(timestamp_ms, payload)| -> Result<F64Record, MetricsFormatError> {
+        let value = payload.parse::<f64>().map_err(|_e| MetricsFormatError::MalformedRecord(payload.to_string()))?;
+
+        Ok(F64Record {timestamp_ms: timestamp_ms?, value})
+    }).collect()
+}
+
+/// Parses event records. Records written without attributes (by older versions) have none.
+pub fn parse_event_records(data: &str) -> Result<Vec<EventRecord>, MetricsFormatError> {
+    split_records(data).map(|(timestamp_ms, payload)| -> Result<EventRecord, MetricsFormatError> {
+        let mut fields = payload.split(STACK_DELIMITER);
+        let label = fields.next().unwrap_or_default().to_string();
+        let attributes = fields.map(|field| parse_attribute(field).ok_or_else(|| MetricsFormatError::MalformedRecord(payload.to_string())))
+                               .collect::<Result<Vec<EventAttribute>, MetricsFormatError>>()?;
+
+        Ok(EventRecord {timestamp_ms: timestamp_ms?, label, attributes})
+    }).collect()
+}
+
+/// Parses stacked records. Phases written without labels (by older versions) are named by index.
+pub fn parse_stacked_records(data: &str) -> Result<Vec<StackedRecord>, MetricsFormatError> {
+    split_records(data).map(|(timestamp_ms, payload)| -> Result<StackedRecord, MetricsFormatError> {
+        let mut phases = Vec::new();
+        for (i, phase) in payload.split(STACK_DELIMITER).enumerate() {
+            let (label, value_str) = match phase.find(LABEL_DELIMITER) {
+                Some(idx) => (phase[..idx].to_string(), &phase[idx+1..]),
+                None => (format!("Phase {}", i), phase),
+            };
+            let value = value_str.parse::<f64>().map_err(|_e| MetricsFormatError::MalformedRecord(phase.to_string()))?;
+
+            phases.push((label, value));
+        }
+
+        Ok(StackedRecord {timestamp_ms: timestamp_ms?, phases})
+    }).collect()
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Reads a metrics file from an existing run directory into a string, treating a missing file as empty
+fn read_metrics_file(run_dir: &Path, filename: &str) -> Result<String, MetricsFormatError> {
+    let file_path = run_dir.join(filename);
+    match fs::read_to_string(&file_path) {
+        Ok(data) => Ok(data),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(MetricsFormatError::Io(file_path, e)),
+    }
+}
+
+/// Parses a single key=tag:value attribute
+fn parse_attribute(field: &str) -> Option<EventAttribute> {
+    let key_idx = field.find(LABEL_DELIMITER)?;
+    let (key, typed_value) = (&field[..key_idx], &field[key_idx+1..]);
+
+    let tag_idx = typed_value.find(TYPE_DELIMITER)?;
+    let (tag, value_str) = (&typed_value[..tag_idx], &typed_value[tag_idx+1..]);
+
+    let value = match tag {
+        "i" => AttributeValue::Int(value_str.parse().ok()?),
+        "f" => AttributeValue::Float(value_str.parse().ok()?),
+        "b" => AttributeValue::Bool(value_str.parse().ok()?),
+        "s" => AttributeValue::Text(value_str.to_string()),
+        _ => return None,
+    };
+
+    Some(EventAttribute {key: key.to_string(), value})
+}
+
+/// Splits raw file data into (timestamp, payload) pairs, skipping empty records
+fn split_records(data: &str) -> impl Iterator<Item = (Result<u64, MetricsFormatError>, &str)> {
+    data.split(RECORD_DELIMITER)
+        .map(|record| record.trim())
+        .filter(|record| !record.is_empty())
+        .map(|record| {
+            match record.find(FIELD_DELIMITER) {
+                Some(idx) => {
+                    let timestamp_str = &record[..idx];
+                    let timestamp = timestamp_str.parse::<u64>()
+                                        .map_err(|_e| MetricsFormatError::MalformedRecord(record.to_string()));
+                    (timestamp, &record[idx+1..])
+                },
+                None => (Err(MetricsFormatError::MalformedRecord(record.to_string())), ""),
+            }
+        })
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Error for MetricsFormatError {}
+
+impl fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttributeValue::Int(value)   => write!(f, "{}", value),
+            AttributeValue::Float(value) => write!(f, "{}", value),
+            AttributeValue::Bool(value)  => write!(f, "{}", value),
+            AttributeValue::Text(value)  => write!(f, "{}", value),
+        }
+    }
+}
+
+impl fmt::Display for EventAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}{}", self.key, LABEL_DELIMITER, self.value)
+    }
+}
+
+/*  *  *  *  *  *  *  *
+ *   AttributeValue   *
+ *  *  *  *  *  *  *  */
+impl From<i64> for AttributeValue {
+    fn from(src: i64) -> Self {
+        AttributeValue::Int(src)
+    }
+}
+impl From<i32> for AttributeValue {
+    fn from(src: i32) -> Self {
+        AttributeValue::Int(i64::from(src))
+    }
+}
+impl From<u32> for AttributeValue {
+    fn from(src: u32) -> Self {
+        AttributeValue::Int(i64::from(src))
+    }
+}
+impl From<usize> for AttributeValue {
+    fn from(src: usize) -> Self {
+        AttributeValue::Int(src as i64)
+    }
+}
+impl From<f64> for AttributeValue {
+    fn from(src: f64) -> Self {
+        AttributeValue::Float(src)
+    }
+}
+impl From<bool> for AttributeValue {
+    fn from(src: bool) -> Self {
+        AttributeValue::Bool(src)
+    }
+}
+impl From<String> for AttributeValue {
+    fn from(src: String) -> Self {
+        AttributeValue::Text(src)
+    }
+}
+impl From<&str> for AttributeValue {
+    fn from(src: &str) -> Self {
+        AttributeValue::Text(String::from(src))
+    }
+}
+
+impl fmt::Display for MetricsFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetricsFormatError::Io(path, e) => {
+                write!(f, "Failed to read metrics file {}: {}", path.display(), e)
+            },
+            MetricsFormatError::MalformedRecord(record) => {
+                write!(f, "Malformed metrics record '{}'", record)
+            },
+            MetricsFormatError::MissingRunDir(path) => {
+                write!(f, "Metrics run directory {} does not exist", path.display())
+            },
+        }
+    }
+}
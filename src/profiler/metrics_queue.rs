@@ -0,0 +1,376 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : profiler/metrics_queue.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module will provide a bounded, multi-producer single-consumer queue
+    for handing metrics off to the receiver thread.
+
+    When the queue is full, senders either block until there is room or
+    drop the oldest queued metric, depending on the configured policy.
+    Dropped metrics are counted so that the loss can be reported.
+
+    Any sender may close the queue, after which the receiver drains what is
+    left and stops, even while other senders are still alive.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{
+        Arc,
+        Condvar,
+        Mutex,
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        mpsc::SendError,
+    },
+};
+
+use crate::profiler::MetricContainer;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Default maximum number of metrics waiting on the receiver
+pub const DEFAULT_QUEUE_CAPACITY: usize = 16384;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Behavior of a sender when the queue is full
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverflowPolicy {
+    /// Wait for the receiver to make room
+    Block,
+    /// Discard the oldest queued metric to make room
+    DropOldest,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueueConfig {
+    pub capacity:   usize,
+    pub policy:     OverflowPolicy,
+}
+
+pub struct QueueSender {
+    shared: Arc<Shared>,
+}
+
+pub struct QueueReceiver {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    state:      Mutex<QueueState>,
+    not_empty:  Condvar,
+    not_full:   Condvar,
+    config:     QueueConfig,
+    dropped:    AtomicU64,
+}
+
+struct QueueState {
+    items:              VecDeque<MetricContainer>,
+    senders:            usize,
+    closed:             bool,
+    receiver_alive:     bool,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Creates a bounded queue, returning its sending and receiving halves.
+/// The capacity is clamped to at least 1, as a blocking sender could never fit a metric into an empty queue.
+pub fn bounded(config: QueueConfig) -> (QueueSender, QueueReceiver) {
+    let config = QueueConfig {
+        capacity:   config.capacity.max(1),
+        ..config
+    };
+
+    let shared = Arc::new(Shared {
+        state:      Mutex::new(QueueState {
+                        items:          VecDeque::new(),
+                        senders:        1,
+                        closed:         false,
+                        receiver_alive: true,
+                    }),
+        not_empty:  Condvar::new(),
+        not_full:   Condvar::new(),
+        config,
+        dropped:    AtomicU64::new(0),
+    });
+
+    (QueueSender {shared: Arc::clone(&shared)}, QueueReceiver {shared})
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl QueueSender {
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the number of metrics dropped due to a full queue
+    pub fn dropped_count(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Queues a metric, applying the overflow policy if the queue is full.
+    /// Fails if the queue has been closed or the receiver has gone away.
+    pub fn send(&self, metric: MetricContainer) -> Result<(), SendError<MetricContainer>> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        while !state.closed && state.receiver_alive && state.items.len() >= self.shared.config.capacity {
+            match self.shared.config.policy {
+                OverflowPolicy::Block => {
+                    state = self.shared.not_full.wait(state).unwrap();
+                },
+                OverflowPolicy::DropOldest => {
+                    state.items.pop_front();
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                },
+            }
+        }
+
+        if state.closed || !state.receiver_alive {
+            return Err(SendError(metric));
+        }
+
+        state.items.push_back(metric);
+        self.shared.not_empty.notify_one();
+
+        Ok(())
+    }
+
+    /// Closes the queue for every sender. The receiver still gets the metrics already queued.
+    pub fn close(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.closed = true;
+
+        // Wake the receiver so it can notice the queue has closed, and any senders blocked on a full queue
+        self.shared.not_empty.notify_all();
+        self.shared.not_full.notify_all();
+    }
+}
+
+
+impl QueueReceiver {
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the number of metrics dropped due to a full queue
+    pub fn dropped_count(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Blocks until a metric is available. Returns None once the queue is empty and
+    /// either all senders have hung up or the queue has been closed.
+    pub fn recv(&self) -> Option<MetricContainer> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        loop {
+            if let Some(metric) = state.items.pop_front() {
+                self.shared.not_full.notify_one();
+                return Some(metric);
+            }
+
+            if state.senders == 0 || state.closed {
+                return None;
+            }
+
+            state = self.shared.not_empty.wait(state).unwrap();
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Clone for QueueSender {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+
+        Self {shared: Arc::clone(&self.shared)}
+    }
+}
+
+impl Drop for QueueSender {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.senders -= 1;
+
+        // Wake the receiver so it can notice that everyone has hung up
+        if state.senders == 0 {
+            self.shared.not_empty.notify_all();
+        }
+    }
+}
+
+impl Drop for QueueReceiver {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.receiver_alive = false;
+        state.items.clear();
+
+        // Release any senders blocked on a full queue
+        self.shared.not_full.notify_all();
+    }
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity:   DEFAULT_QUEUE_CAPACITY,
+            policy:     OverflowPolicy::DropOldest,
+        }
+    }
+}
+
+impl fmt::Display for OverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OverflowPolicy::Block       => write!(f, "block"),
+            OverflowPolicy::DropOldest  => write!(f, "drop-oldest"),
+        }
+    }
+}
+
+impl std::str::FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "block"         => Ok(OverflowPolicy::Block),
+            "drop-oldest"   => Ok(OverflowPolicy::DropOldest),
+            _               => Err(format!("Invalid overflow policy '{}', expected 'block' or 'drop-oldest'", src)),
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        thread,
+        time::Duration,
+    };
+
+    fn metric(value: f64) -> MetricContainer {
+        MetricContainer::AvgFps(Duration::from_millis(value as u64), value)
+    }
+
+    fn value_of(metric: MetricContainer) -> f64 {
+        match metric {
+            MetricContainer::AvgFps(_timestamp, value) => value,
+            _ => panic!("unexpected metric type"),
+        }
+    }
+
+    #[test]
+    fn drop_oldest_keeps_newest_and_counts_drops() {
+        let (tx, rx) = bounded(QueueConfig {capacity: 2, policy: OverflowPolicy::DropOldest});
+        for value in 1..=5 {
+            tx.send(metric(f64::from(value))).unwrap();
+        }
+        drop(tx);
+
+        assert_eq!(rx.dropped_count(), 3);
+        assert_eq!(value_of(rx.recv().unwrap()), 4.0);
+        assert_eq!(value_of(rx.recv().unwrap()), 5.0);
+        assert!(rx.recv().is_none());
+    }
+
+    #[test]
+    fn block_waits_for_room_without_dropping() {
+        let (tx, rx) = bounded(QueueConfig {capacity: 1, policy: OverflowPolicy::Block});
+        tx.send(metric(1.0)).unwrap();
+
+        // The second send can only complete once the first metric has been received
+        let sender = thread::spawn(move || {
+            tx.send(metric(2.0)).unwrap();
+            tx.dropped_count()
+        });
+
+        assert_eq!(value_of(rx.recv().unwrap()), 1.0);
+        assert_eq!(value_of(rx.recv().unwrap()), 2.0);
+        assert_eq!(sender.join().unwrap(), 0);
+        assert!(rx.recv().is_none());
+    }
+
+    #[test]
+    fn zero_capacity_is_clamped_to_one() {
+        let (tx, rx) = bounded(QueueConfig {capacity: 0, policy: OverflowPolicy::DropOldest});
+        tx.send(metric(1.0)).unwrap();
+        tx.send(metric(2.0)).unwrap();
+
+        assert_eq!(tx.dropped_count(), 1);
+        assert_eq!(value_of(rx.recv().unwrap()), 2.0);
+    }
+
+    #[test]
+    fn send_fails_once_receiver_is_gone() {
+        let (tx, rx) = bounded(QueueConfig {capacity: 1, policy: OverflowPolicy::Block});
+        tx.send(metric(1.0)).unwrap();
+        drop(rx);
+
+        // Even a full, blocking queue must not hang
+        assert!(tx.send(metric(2.0)).is_err());
+    }
+
+    #[test]
+    fn close_drains_queue_while_senders_remain() {
+        let (tx, rx) = bounded(QueueConfig::default());
+        let other_tx = tx.clone();
+        tx.send(metric(1.0)).unwrap();
+        tx.close();
+
+        assert!(other_tx.send(metric(2.0)).is_err());
+        assert_eq!(value_of(rx.recv().unwrap()), 1.0);
+        assert!(rx.recv().is_none());
+    }
+}
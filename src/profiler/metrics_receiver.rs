@@ -20,16 +20,11 @@ Purpose:
 
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
-use std::{
-    fs,
-    io::prelude::*,
-    path::PathBuf,
-    sync::mpsc,
-    time::Duration,
+use crate::profiler::{
+    self,
+    metrics_queue::QueueReceiver,
 };
 
-use crate::profiler;
-
 use chrono::Local;
 
 
@@ -38,8 +33,20 @@ use chrono::Local;
 ///////////////////////////////////////////////////////////////////////////////
 
 pub struct MetricsReceiver {
-    metrics_rx: mpsc::Receiver<profiler::MetricContainer>,
-    files:      Vec<fs::File>,
+    metrics_rx:         QueueReceiver,
+    sinks:              Vec<Box<dyn MetricsSink>>,
+    reported_dropped:   u64,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Declarations
+///////////////////////////////////////////////////////////////////////////////
+
+/// A destination for received metrics, e.g. files on disk or a socket
+pub trait MetricsSink: Send {
+    /// Records a single metric
+    fn record(&mut self, metric: &profiler::MetricContainer);
 }
 
 
@@ -48,27 +55,16 @@ pub struct MetricsReceiver {
 ///////////////////////////////////////////////////////////////////////////////
 
 impl MetricsReceiver {
-    /// Generic constructor
-    pub fn new(metrics_rx: mpsc::Receiver<profiler::MetricContainer>) -> Self {
-        let mut files = Vec::new();
-        Self::create_files(&mut files);
-        
+    /// Fully-qualified constructor
+    pub fn new(metrics_rx: QueueReceiver, sinks: Vec<Box<dyn MetricsSink>>) -> Self {
         Self {
             metrics_rx,
-            files,
+            sinks,
+            reported_dropped: 0,
         }
     }
 
 
-    /*  *  *  *  *  *  *  *
-     *  Accessor Methods  *
-     *  *  *  *  *  *  *  */
-
-    fn file_handle(&mut self, metric: &profiler::MetricContainer) -> &mut fs::File {
-        &mut self.files[usize::from(metric)]
-    }
-     
-
     /*  *  *  *  *  *  *  *
      *  Utility Methods   *
      *  *  *  *  *  *  *  */
@@ -77,130 +73,36 @@ impl MetricsReceiver {
     pub fn main(&mut self) {
         println!("{}: Entered MetricsReceiver thread.", Local::now().format("%Y-%m-%d %T%.3f"));
 
-        loop {
-            // Check channel for metrics
-            if let Ok(metric_container) = self.metrics_rx.recv() {
-                // Get the appropriate file handle
-                let file_handle = self.file_handle(&metric_container);
-
-                // Handle metric based on container type
-                match metric_container {
-                    profiler::MetricContainer::AvgFps(timestamp, avg_fps) => {
-                        Self::add_f64_to_csv(timestamp, avg_fps, 0, file_handle);
-                    }
-                    profiler::MetricContainer::FrameDeltaTime(timestamp, delta) => {
-                        Self::add_f64_to_csv(timestamp, delta, 7, file_handle);
-                    },
-                    profiler::MetricContainer::EventMarker(timestamp, event_label) => {
-                        Self::add_string_to_csv(timestamp, event_label, file_handle);
-                    },
-                    profiler::MetricContainer::StackedDrawTime(timestamp, stacked_times) => {
-                        Self::add_stacked_times_to_csv(timestamp, stacked_times, 7, file_handle)
-                    }
-                };
-            }
-        }
-    }
-    
+        // Receive until all senders have hung up
+        while let Some(metric_container) = self.metrics_rx.recv() {
+            self.record(&metric_container);
 
-    /*  *  *  *  *  *  *
-     * Helper Methods  *
-     *  *  *  *  *  *  */
-
-    fn create_files(files: &mut Vec<fs::File>) {
-        let start_time = Local::now();
-        let metrics_tld = "metrics";
-        let metrics_cur = format!("{}", start_time.format("%F_%H_%M_%S%.3f"));
-
-        // Create top-level 'metrics' directory if necessary
-        let mut metrics_path_buf = PathBuf::from(metrics_tld);
-        if !metrics_path_buf.as_path().exists() {
-            match fs::create_dir(metrics_path_buf.as_path()) {
-                Ok(()) => (),
-                Err(e) => panic!("Failed to create top-level metrics directory. Error: {}", e),
+            // Record any losses since the last report, stamped with the time of the metric that followed them
+            let dropped = self.metrics_rx.dropped_count();
+            if dropped > self.reported_dropped {
+                self.reported_dropped = dropped;
+                self.record(&profiler::MetricContainer::DroppedMetrics(metric_container.timestamp(), dropped));
             }
         }
 
-        // Create directory for current run
-        metrics_path_buf.push(metrics_cur);
-        match fs::create_dir(metrics_path_buf.as_path()) {
-            Ok(()) => (),
-            Err(e) => panic!("Failed to create current-run metrics directory. Error: {}", e),
+        if self.reported_dropped > 0 {
+            println!("{}: MetricsReceiver dropped {} metrics due to a full queue.",
+                     Local::now().format("%Y-%m-%d %T%.3f"),
+                     self.reported_dropped);
         }
 
-        //OPT: *DESIGN* Would be cleaner if this were an iterator
-        // Create standard metrics files
-        for metric_idx in 0 .. profiler::MetricContainer::VARIANT_COUNT {
-            // Get the current metric's filename
-            let filename = profiler::MetricContainer::from(metric_idx).filename();
-
-            // Push onto the filepath buffer and create the file
-            metrics_path_buf.push(filename);
-            match fs::File::create(metrics_path_buf.as_path()) {
-                Ok(file) => files.push(file),
-                Err(err) => panic!("Failed to create metrics file at {}. Error: {}", metrics_path_buf.as_path().display(), err),
-            }
-
-            // Pop the filename off the path buffer for the next iteration
-            metrics_path_buf.pop();
-        }
+        println!("{}: Exiting MetricsReceiver thread.", Local::now().format("%Y-%m-%d %T%.3f"));
     }
 
-    fn add_f64_to_csv(timestamp: Duration,
-                      item: f64,
-                      precision: usize,
-                      csv_file: &mut fs::File) {
-        // Format item for writing
-        let item_formatted = format!(
-            "{timestamp},{item:.precision$};",
-            timestamp = timestamp.as_millis(),
-            item = item,
-            precision = precision
-        );
-
-        // Write to given file
-        csv_file.write_all(item_formatted.as_bytes()).unwrap();
-    }
 
-    fn add_string_to_csv(timestamp: Duration,
-                         label: String,
-                         csv_file: &mut fs::File) {
-        // Format label for writing
-        let label_formatted = format!(
-            "{timestamp},{label};",
-            timestamp = timestamp.as_millis(),
-            label = label
-        );
-
-        // Write to given file
-        csv_file.write_all(label_formatted.as_bytes()).unwrap();
-    }
+    /*  *  *  *  *  *  *
+     * Helper Methods  *
+     *  *  *  *  *  *  */
 
-    fn add_stacked_times_to_csv(timestamp: Duration,
-                                stacked_times: Vec<profiler::StackedTime>,
-                                precision: usize,
-                                csv_file: &mut fs::File) {
-        // Initialize formatted string
-        let mut formatted_stack = format!("{},", timestamp.as_millis());
-
-        // Add each time from the stack to the string
-        let mut prev_time = timestamp;
-        for element in stacked_times {
-            let formatted_element = format!(
-                "{elem:.precision$}|",
-                elem=(element.time - prev_time).as_secs_f64(),
-                precision = precision
-            );
-            formatted_stack = format!("{}{}", formatted_stack, formatted_element);
-
-            prev_time = element.time;
+    /// Hands the metric off to each sink in turn
+    fn record(&mut self, metric: &profiler::MetricContainer) {
+        for sink in self.sinks.iter_mut() {
+            sink.record(metric);
         }
-
-        // Remove trailing '|'
-        formatted_stack.pop();
-
-        // Add delimiter and write to file
-        formatted_stack.push(';');
-        csv_file.write_all(formatted_stack.as_bytes()).unwrap();
     }
 }
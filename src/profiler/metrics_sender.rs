@@ -22,7 +22,10 @@ Purpose:
 
 use std::sync::mpsc;
 
-use crate::profiler;
+use crate::profiler::{
+    self,
+    metrics_queue::QueueSender,
+};
 
 ///////////////////////////////////////////////////////////////////////////////
 //  Data Structures
@@ -30,7 +33,7 @@ use crate::profiler;
 
 #[derive(Clone)]
 pub struct MetricsSender {
-    metrics_tx: mpsc::Sender<profiler::MetricContainer>,
+    metrics_tx: QueueSender,
 }
 
 
@@ -40,19 +43,34 @@ pub struct MetricsSender {
 
 impl MetricsSender {
     /// Fully-qualified constructor
-    pub fn new(metrics_tx: mpsc::Sender<profiler::MetricContainer>) -> Self {
+    pub fn new(metrics_tx: QueueSender) -> Self {
         Self {metrics_tx}
     }
 
 
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the number of metrics dropped because the receiver fell behind
+    pub fn dropped_count(&self) -> u64 {
+        self.metrics_tx.dropped_count()
+    }
+
+
     /*  *  *  *  *  *  *  *
      *  Utility Methods   *
      *  *  *  *  *  *  *  */
-    
+
     /// Sends a metric to be recorded by the receiver
     pub fn send_metric(&self, metric: profiler::MetricContainer) -> Result<(), mpsc::SendError<profiler::MetricContainer>> {
         self.metrics_tx.send(metric)
     }
+
+    /// Stops all senders, letting the receiver finish once it has recorded what was already sent
+    pub fn close(&self) {
+        self.metrics_tx.close();
+    }
 }
 
 
@@ -22,21 +22,11 @@ Purpose:
 
 use std::{
     sync::mpsc,
-    thread,
     time::Duration,
 };
 
-use ggez::{
-    Context as GgEzContext,
-    graphics as ggez_gfx,
-    mint as ggez_mint,
-    timer as ggez_timer,
-};
-
 use variant_count::VariantCount;
 
-use crate::game_assets::colors;
-
 
 ///////////////////////////////////////////////////////////////////////////////
 //  Named Constants
@@ -51,49 +41,85 @@ pub const PLACEHOLDER_F64: f64 = 0.0;
 /// Placeholder for bound Strings
 pub const PLACEHOLDER_STRING: String = String::new();
 
+/// Placeholder for bound u64s
+pub const PLACEHOLDER_U64: u64 = 0;
+
+/// Placeholder for bound EventAttribute Vecs
+pub const PLACEHOLDER_ATTRIBUTES: Vec<metrics_format::EventAttribute> = Vec::new();
+
 /// Placeholder for bound Strings
 pub const PLACEHOLDER_STACKED_DRAW_VEC: Vec<StackedTime> = Vec::new();
 
+/// Placeholder for bound SlowFrameRecords
+pub const PLACEHOLDER_SLOW_FRAME: metrics_format::SlowFrameRecord = metrics_format::SlowFrameRecord {
+    frame_delta:    0.0,
+    budget:         0.0,
+    phases:         Vec::new(),
+    recent_events:  Vec::new(),
+};
+
 
 ///////////////////////////////////////////////////////////////////////////////
 //  Module Declarations
 ///////////////////////////////////////////////////////////////////////////////
 
+pub mod run_metadata;
+pub mod metrics_format;
+pub mod frame_stats;
+
+// Metrics are only collected and drawn when built with the 'profiling' feature.
+// Otherwise, the profiler API is provided by a no-op Instance that compiles away.
+#[cfg(feature = "profiling")]
+pub mod metrics_queue;
+#[cfg(feature = "profiling")]
 pub mod metrics_sender;
-use self::metrics_sender::MetricsSender;
+#[cfg(feature = "profiling")]
 pub mod metrics_receiver;
-use self::metrics_receiver::MetricsReceiver;
+#[cfg(feature = "profiling")]
+pub mod file_sink;
+#[cfg(feature = "profiling")]
+pub mod socket_sink;
+#[cfg(feature = "profiling")]
+pub mod overlay;
+
+#[cfg(feature = "profiling")]
+mod instance;
+#[cfg(not(feature = "profiling"))]
+#[path = "instance_noop.rs"]
+mod instance;
+pub use self::instance::{
+    Instance,
+    StackTimer,
+};
 
 
 ///////////////////////////////////////////////////////////////////////////////
 //  Data Structures
 ///////////////////////////////////////////////////////////////////////////////
 
-/// Instance of the SandCasting profiler module
-#[derive(Clone)]
-pub struct Instance {
-    enabled:        bool,
-    sender:         MetricsSender,
-    cached_metrics: CachedMetrics,
-}
-
-#[derive(Clone, Default)]
-struct CachedMetrics {
-    pub avg_fps:    f64,
-    pub peak_fps:   f64,
-}
+/// Result of handing a metric off to the receiver thread
+pub type ProfilerResult = Result<(), mpsc::SendError<MetricContainer>>;
 
 /// Enumeration for the various kinds of performance metrics that can be recorded.
 #[derive(VariantCount)]
 pub enum MetricContainer {
     AvgFps(Duration, f64),
     FrameDeltaTime(Duration, f64),
-    EventMarker(Duration, String),
+    EventMarker(Duration, String, Vec<metrics_format::EventAttribute>),
     StackedDrawTime(Duration, Vec<StackedTime>),
+    StackedUpdateTime(Duration, Vec<StackedTime>),
+    StackedInitTime(Duration, Vec<StackedTime>),
+    /// Mesh rebuilds, stacked with one phase named after the rebuilding manager
+    MeshUpdateTime(Duration, Vec<StackedTime>),
+    /// Running total of metrics dropped by the queue, recorded by the receiver whenever it changes
+    DroppedMetrics(Duration, u64),
+    /// Context captured when a frame exceeds the frame-time budget
+    SlowFrame(Duration, metrics_format::SlowFrameRecord),
 }
 
+#[derive(Clone)]
 pub struct StackedTime {
-    pub label: String,
+    pub label: &'static str,
     pub time: Duration,
 }
 
@@ -102,33 +128,25 @@ pub struct StackedTime {
 //  Object Implementations
 ///////////////////////////////////////////////////////////////////////////////
 
-impl Instance {
-    //OPT: *DESIGN* Would be cool to make a Disablable trait
-    pub fn disabled() -> Self {
-        // Create dummy channel handles
-        let (dummy_tx, _dummy_rx) = mpsc::channel::<MetricContainer>();
-        
-        // Initialize dummy sender struct
-        let dummy_sender = MetricsSender::new(dummy_tx);
-
-        Self {
-            enabled:        false,
-            sender:         dummy_sender,
-            cached_metrics: CachedMetrics::default(),
-        }
-    }
-
+impl MetricContainer {
 
     /*  *  *  *  *  *  *  *
      *  Accessor Methods  *
      *  *  *  *  *  *  *  */
-    
-    pub fn avg_fps(&self) -> f64 {
-        self.cached_metrics.avg_fps
-    }
 
-    pub fn peak_fps(&self) -> f64 {
-        self.cached_metrics.peak_fps
+    /// Returns the time since start at which the metric was recorded
+    pub fn timestamp(&self) -> Duration {
+        match self {
+            MetricContainer::AvgFps(timestamp, _val)               => *timestamp,
+            MetricContainer::FrameDeltaTime(timestamp, _val)       => *timestamp,
+            MetricContainer::EventMarker(timestamp, _label, _attr) => *timestamp,
+            MetricContainer::StackedDrawTime(timestamp, _vec)      => *timestamp,
+            MetricContainer::StackedUpdateTime(timestamp, _vec)    => *timestamp,
+            MetricContainer::StackedInitTime(timestamp, _vec)      => *timestamp,
+            MetricContainer::MeshUpdateTime(timestamp, _vec)       => *timestamp,
+            MetricContainer::DroppedMetrics(timestamp, _count)     => *timestamp,
+            MetricContainer::SlowFrame(timestamp, _record)         => *timestamp,
+        }
     }
 
 
@@ -136,102 +154,115 @@ impl Instance {
      *  Utility Methods   *
      *  *  *  *  *  *  *  */
 
-    pub fn draw_fps_stats(&self, ggez_ctx: &mut GgEzContext) {
-        //OPT: *PERFORMANCE* "static" storage of these local variables would probably be quicker
-        // Draw avg. FPS
-        let avg_fps_pos = ggez_mint::Point2 {x: 0.0, y: 0.0};
-        let avg_fps_str = format!("Avg. FPS: {:.0}", self.cached_metrics.avg_fps);
-        let avg_fps_display = ggez_gfx::Text::new((avg_fps_str, ggez_gfx::Font::default(),crate::DEFAULT_TEXT_SIZE));
-        ggez_gfx::draw(ggez_ctx, &avg_fps_display, (avg_fps_pos, 0.0, colors::GREEN)).unwrap();
-
-        // Draw peak FPS
-        let peak_fps_pos = ggez_mint::Point2 {x: 0.0, y: 20.0};
-        let peak_fps_str = format!("Peak FPS: {:.0}", self.cached_metrics.peak_fps);
-        let peak_fps_display = ggez_gfx::Text::new((peak_fps_str, ggez_gfx::Font::default(),crate::DEFAULT_TEXT_SIZE));
-        ggez_gfx::draw(ggez_ctx, &peak_fps_display, (peak_fps_pos, 0.0, colors::GREEN)).unwrap();
-    }
-
-    pub fn update_fps_stats(&mut self, ggez_ctx: &GgEzContext) -> Result<(), mpsc::SendError<MetricContainer>> {
-        // Get elapsed time
-        let elapsed_time = ggez_timer::time_since_start(ggez_ctx);
-        
-        // Update cached avg. FPS
-        self.cached_metrics.avg_fps = ggez_timer::fps(ggez_ctx);
-
-        // Update cached peak FPS if appropriate
-        if self.cached_metrics.avg_fps > self.cached_metrics.peak_fps {
-            self.cached_metrics.peak_fps = self.cached_metrics.avg_fps;
-        }
-            
-        if self.enabled {
-            // Pack up FPS in a container and send
-            let metric = MetricContainer::AvgFps(elapsed_time, self.cached_metrics.avg_fps);
-            self.sender.send_metric(metric)
-        }
-        else {
-            Ok(())
+    /// Returns the metric serialized as a single-line JSON object
+    pub fn to_json(&self) -> String {
+        match self {
+            MetricContainer::AvgFps(timestamp, avg_fps) => {
+                format!(r#"{{"metric":"avg_fps","timestamp_ms":{},"value":{}}}"#,
+                        timestamp.as_millis(),
+                        metrics_format::json_f64(*avg_fps))
+            },
+            MetricContainer::FrameDeltaTime(timestamp, delta) => {
+                format!(r#"{{"metric":"frame_delta","timestamp_ms":{},"value":{}}}"#,
+                        timestamp.as_millis(),
+                        metrics_format::json_f64(*delta))
+            },
+            MetricContainer::EventMarker(timestamp, label, attributes) => {
+                format!(r#"{{"metric":"event_marker","timestamp_ms":{},"label":{},"attributes":{}}}"#,
+                        timestamp.as_millis(),
+                        metrics_format::json_string(label),
+                        metrics_format::json_attributes(attributes))
+            },
+            MetricContainer::StackedDrawTime(timestamp, stacked_times) => {
+                stacked_json("stacked_draw_time", *timestamp, stacked_times)
+            },
+            MetricContainer::StackedUpdateTime(timestamp, stacked_times) => {
+                stacked_json("stacked_update_time", *timestamp, stacked_times)
+            },
+            MetricContainer::StackedInitTime(timestamp, stacked_times) => {
+                stacked_json("stacked_init_time", *timestamp, stacked_times)
+            },
+            MetricContainer::MeshUpdateTime(timestamp, stacked_times) => {
+                stacked_json("mesh_update_time", *timestamp, stacked_times)
+            },
+            MetricContainer::DroppedMetrics(timestamp, count) => {
+                format!(r#"{{"metric":"dropped_metrics","timestamp_ms":{},"value":{}}}"#,
+                        timestamp.as_millis(),
+                        count)
+            },
+            MetricContainer::SlowFrame(timestamp, record) => {
+                let phases: Vec<String> = record.phases.iter()
+                    .map(|(label, time)| format!(r#"{{"label":{},"time":{}}}"#,
+                                                 metrics_format::json_string(label),
+                                                 metrics_format::json_f64(*time)))
+                    .collect();
+                let events: Vec<String> = record.recent_events.iter()
+                    .map(|event| format!(r#"{{"timestamp_ms":{},"label":{},"attributes":{}}}"#,
+                                         event.timestamp_ms,
+                                         metrics_format::json_string(&event.label),
+                                         metrics_format::json_attributes(&event.attributes)))
+                    .collect();
+
+                format!(r#"{{"metric":"slow_frame","timestamp_ms":{},"frame_delta":{},"budget":{},"phases":[{}],"recent_events":[{}]}}"#,
+                        timestamp.as_millis(),
+                        metrics_format::json_f64(record.frame_delta),
+                        metrics_format::json_f64(record.budget),
+                        phases.join(","),
+                        events.join(","))
+            },
         }
     }
 
-    pub fn send_frame_delta(&self, ggez_ctx: &GgEzContext) -> Result<(), mpsc::SendError<MetricContainer>> {
-        if self.enabled {
-            // Get elapsed time
-            let elapsed_time = ggez_timer::time_since_start(ggez_ctx);
-
-            // Get frame delta and convert to f64
-            let frame_delta = ggez_timer::delta(ggez_ctx).as_secs_f64();
-
-            // Pack up frame delta in a container and send
-            let metric = MetricContainer::FrameDeltaTime(elapsed_time, frame_delta);
-            self.sender.send_metric(metric)
-        }
-        else {
-            Ok(())
+    /// Returns the filename that will store the metric's data
+    pub fn filename(&self) -> String {
+        match self {
+            MetricContainer::AvgFps(_dur, _val)               => String::from(metrics_format::AVG_FPS_FILENAME),
+            MetricContainer::FrameDeltaTime(_dur, _val)       => String::from(metrics_format::FRAME_DELTA_FILENAME),
+            MetricContainer::EventMarker(_dur, _label, _attr) => String::from(metrics_format::EVENT_MARKER_FILENAME),
+            MetricContainer::StackedDrawTime(_dur, _vec)      => String::from(metrics_format::STACKED_DRAW_TIME_FILENAME),
+            MetricContainer::StackedUpdateTime(_dur, _vec)    => String::from(metrics_format::STACKED_UPDATE_TIME_FILENAME),
+            MetricContainer::StackedInitTime(_dur, _vec)      => String::from(metrics_format::STACKED_INIT_TIME_FILENAME),
+            MetricContainer::MeshUpdateTime(_dur, _vec)       => String::from(metrics_format::MESH_UPDATE_TIME_FILENAME),
+            MetricContainer::DroppedMetrics(_dur, _count)     => String::from(metrics_format::DROPPED_METRICS_FILENAME),
+            MetricContainer::SlowFrame(_dur, _record)         => String::from(metrics_format::SLOW_FRAMES_FILENAME),
         }
     }
+}
 
-    pub fn mark_event(&self, event_label: String, ggez_ctx: &GgEzContext) -> Result<(), mpsc::SendError<MetricContainer>> {
-        if self.enabled {
-            // Get elapsed time
-            let elapsed_time = ggez_timer::time_since_start(ggez_ctx);
 
-            // Pack up event label in a container and send
-            let metric = MetricContainer::EventMarker(elapsed_time, event_label);
-            self.sender.send_metric(metric)
-        }
-        else {
-            Ok(())
-        }
-    }
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
 
-    pub fn send_stacked_draw_time(&self, start_time: Duration, stacked_times: Vec<StackedTime>) -> Result<(), mpsc::SendError<MetricContainer>> {
-        if self.enabled {
-            // Pack up stacked times into container and send
-            let metric = MetricContainer::StackedDrawTime(start_time, stacked_times);
-            self.sender.send_metric(metric)
-        }
-        else {
-            Ok(())
-        }
-    }
+/// Converts a stack of cumulative timestamps into (label, seconds) pairs for each phase
+pub fn phase_durations(start_time: Duration, stacked_times: &[StackedTime]) -> Vec<(String, f64)> {
+    let mut prev_time = start_time;
+
+    stacked_times.iter().map(|element| {
+        let phase = (String::from(element.label), (element.time - prev_time).as_secs_f64());
+        prev_time = element.time;
+        phase
+    }).collect()
 }
 
 
-impl MetricContainer {
-    
-    /*  *  *  *  *  *  *  *
-     *  Utility Methods   *
-     *  *  *  *  *  *  *  */
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
 
-    /// Returns the filename that will store the metric's data
-    pub fn filename(&self) -> String {
-        match self {
-            MetricContainer::AvgFps(_dur, _val)             => String::from("avg_fps.csv"),
-            MetricContainer::FrameDeltaTime(_dur, _val)     => String::from("frame_delta.csv"),
-            MetricContainer::EventMarker(_dur, _label)      => String::from("event_marker.csv"),
-            MetricContainer::StackedDrawTime(_dur, _vec)    => String::from("stacked_draw_time.csv")
-        }
-    }
+/// Serializes a stack of timestamps as a JSON object with the given metric name
+fn stacked_json(metric_name: &str, timestamp: Duration, stacked_times: &[StackedTime]) -> String {
+    let phases: Vec<String> = phase_durations(timestamp, stacked_times)
+        .iter()
+        .map(|(label, time)| format!(r#"{{"label":{},"time":{}}}"#,
+                                     metrics_format::json_string(label),
+                                     metrics_format::json_f64(*time)))
+        .collect();
+
+    format!(r#"{{"metric":"{}","timestamp_ms":{},"phases":[{}]}}"#,
+            metric_name,
+            timestamp.as_millis(),
+            phases.join(","))
 }
 
 
@@ -239,41 +270,21 @@ impl MetricContainer {
 //  Trait Implementations
 ///////////////////////////////////////////////////////////////////////////////
 
-/*  *  *  *  *  *  *  *
- *      Instance      *
- *  *  *  *  *  *  *  */
-impl Default for Instance {
-    fn default() -> Self {
-        // Create the metrics data channel
-        let (metrics_tx, metrics_rx) = mpsc::channel::<MetricContainer>();
-
-        //OPT: *PERFORMANCE* Would be better to set the receiver thread's priority as low as possible
-        // Initialize receiver struct, build and spawn thread
-        let mut metrics_receiver = MetricsReceiver::new(metrics_rx);
-        thread::Builder::new()
-            .name(String::from("metrics_receiver"))
-            .spawn(move || metrics_receiver.main())
-            .unwrap();
-
-        Self {
-            enabled:        true,
-            sender:         MetricsSender::new(metrics_tx),
-            cached_metrics: CachedMetrics::default(),
-        }
-    }
-}
-
-
 /*  *  *  *  *  *  *  *
  *  MetricContainer   *
  *  *  *  *  *  *  *  */
 impl From<&MetricContainer> for usize {
     fn from(src: &MetricContainer) -> Self {
         match src {
-            MetricContainer::AvgFps(_dur, _val)             => 0,
-            MetricContainer::FrameDeltaTime(_dur, _val)     => 1,
-            MetricContainer::EventMarker(_dur, _label)      => 2,
-            MetricContainer::StackedDrawTime(_dur, _vec)    => 3,
+            MetricContainer::AvgFps(_dur, _val)               => 0,
+            MetricContainer::FrameDeltaTime(_dur, _val)       => 1,
+            MetricContainer::EventMarker(_dur, _label, _attr) => 2,
+            MetricContainer::StackedDrawTime(_dur, _vec)      => 3,
+            MetricContainer::StackedUpdateTime(_dur, _vec)    => 4,
+            MetricContainer::StackedInitTime(_dur, _vec)      => 5,
+            MetricContainer::MeshUpdateTime(_dur, _vec)       => 6,
+            MetricContainer::DroppedMetrics(_dur, _count)     => 7,
+            MetricContainer::SlowFrame(_dur, _record)         => 8,
         }
     }
 }
@@ -282,8 +293,13 @@ impl From<usize> for MetricContainer {
         match src {
             0 => MetricContainer::AvgFps(PLACEHOLDER_DURATION, PLACEHOLDER_F64),
             1 => MetricContainer::FrameDeltaTime(PLACEHOLDER_DURATION, PLACEHOLDER_F64),
-            2 => MetricContainer::EventMarker(PLACEHOLDER_DURATION, PLACEHOLDER_STRING),
+            2 => MetricContainer::EventMarker(PLACEHOLDER_DURATION, PLACEHOLDER_STRING, PLACEHOLDER_ATTRIBUTES),
             3 => MetricContainer::StackedDrawTime(PLACEHOLDER_DURATION, PLACEHOLDER_STACKED_DRAW_VEC),
+            4 => MetricContainer::StackedUpdateTime(PLACEHOLDER_DURATION, PLACEHOLDER_STACKED_DRAW_VEC),
+            5 => MetricContainer::StackedInitTime(PLACEHOLDER_DURATION, PLACEHOLDER_STACKED_DRAW_VEC),
+            6 => MetricContainer::MeshUpdateTime(PLACEHOLDER_DURATION, PLACEHOLDER_STACKED_DRAW_VEC),
+            7 => MetricContainer::DroppedMetrics(PLACEHOLDER_DURATION, PLACEHOLDER_U64),
+            8 => MetricContainer::SlowFrame(PLACEHOLDER_DURATION, PLACEHOLDER_SLOW_FRAME),
             _ => panic!("Invalid value ({}) for usize -> MetricContainer conversion", src),
         }
     }
@@ -0,0 +1,182 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : profiler/overlay.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module will provide functions to draw a live performance overlay,
+    graphing recent frame times and the latest stacked draw phases.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use ggez::{
+    Context as GgEzContext,
+    graphics as ggez_gfx,
+    mint as ggez_mint,
+};
+
+use crate::{
+    game_assets::colors,
+    profiler::frame_stats::FrameStats,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+// Overlay frame placement and dimensions
+const OVERLAY_MARGIN:           f32 = 10.0;
+const OVERLAY_WIDTH:            f32 = 300.0;
+const GRAPH_HEIGHT:             f32 = 80.0;
+const STACK_BAR_HEIGHT:         f32 = 12.0;
+const STACK_LEGEND_LINE_HEIGHT: f32 = 14.0;
+const OVERLAY_TEXT_SIZE:        f32 = 12.0;
+
+// Line features
+const GRAPH_LINE_WIDTH:         f32 = 1.0;
+const BUDGET_LINE_WIDTH:        f32 = 1.0;
+
+/// Graph will always show at least this many seconds on its Y-axis
+const MIN_GRAPH_CEILING:        f64 = 1.0 / 30.0;
+
+/// Background fill of the overlay
+const OVERLAY_BG_COLOR: ggez_gfx::Color = ggez_gfx::Color {
+    r: 0.000,
+    g: 0.000,
+    b: 0.000,
+    a: 0.666,
+};
+
+/// Colors cycled through for each stacked phase
+const PHASE_COLORS: [ggez_gfx::Color; 8] = [
+    colors::RED,
+    colors::ORANGE,
+    colors::YELLOW,
+    colors::GREEN,
+    colors::CYAN,
+    colors::BLUE,
+    colors::VIOLET,
+    colors::MAGENTA,
+];
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Draws the performance overlay in the bottom-left corner of the window
+pub fn draw(frame_stats: &FrameStats, latest_phases: &[(String, f64)], dropped_metrics: u64, ggez_ctx: &mut GgEzContext) {
+    let (_window_x, window_y) = ggez_gfx::size(ggez_ctx);
+
+    // Size the overlay to fit the graph, the stacked bar, its legend and the dropped metrics count
+    let legend_height = (latest_phases.len() + 1) as f32 * STACK_LEGEND_LINE_HEIGHT;
+    let overlay_height = GRAPH_HEIGHT + STACK_BAR_HEIGHT + legend_height + 4.0*OVERLAY_MARGIN;
+    let origin = ggez_mint::Point2 {x: OVERLAY_MARGIN,
+                                    y: window_y - overlay_height - OVERLAY_MARGIN};
+
+    let mut mesh_builder = ggez_gfx::MeshBuilder::new();
+
+    // Background
+    mesh_builder.rectangle(ggez_gfx::DrawMode::fill(),
+                           ggez_gfx::Rect::new(origin.x, origin.y, OVERLAY_WIDTH, overlay_height),
+                           OVERLAY_BG_COLOR);
+
+    /* Frame-time Graph */
+    let graph_origin = ggez_mint::Point2 {x: origin.x + OVERLAY_MARGIN,
+                                          y: origin.y + OVERLAY_MARGIN};
+    let graph_width = OVERLAY_WIDTH - 2.0*OVERLAY_MARGIN;
+    let summary = frame_stats.summary();
+    let ceiling = summary.max.max(MIN_GRAPH_CEILING);
+
+    mesh_builder.rectangle(ggez_gfx::DrawMode::stroke(GRAPH_LINE_WIDTH),
+                           ggez_gfx::Rect::new(graph_origin.x, graph_origin.y, graph_width, GRAPH_HEIGHT),
+                           colors::DARKGREY);
+
+    // Draw a line marking the target frame budget
+    let budget = 1.0 / crate::DESIRED_FPS as f64;
+    let budget_y = graph_origin.y + GRAPH_HEIGHT - (budget / ceiling) as f32 * GRAPH_HEIGHT;
+    mesh_builder.line(&[ggez_mint::Point2 {x: graph_origin.x, y: budget_y},
+                        ggez_mint::Point2 {x: graph_origin.x + graph_width, y: budget_y}],
+                      BUDGET_LINE_WIDTH,
+                      colors::YELLOW).unwrap();
+
+    // Plot each delta in the window, oldest on the left
+    if frame_stats.len() > 1 {
+        let x_step = graph_width / (frame_stats.capacity().max(2) - 1) as f32;
+        let points: Vec<ggez_mint::Point2<f32>> = frame_stats.deltas()
+            .iter()
+            .enumerate()
+            .map(|(i, delta)| ggez_mint::Point2 {
+                x: graph_origin.x + i as f32 * x_step,
+                y: graph_origin.y + GRAPH_HEIGHT - (delta / ceiling) as f32 * GRAPH_HEIGHT,
+            })
+            .collect();
+
+        mesh_builder.line(&points, GRAPH_LINE_WIDTH, colors::GREEN).unwrap();
+    }
+
+    /* Stacked Phase Bar */
+    let bar_origin = ggez_mint::Point2 {x: graph_origin.x,
+                                        y: graph_origin.y + GRAPH_HEIGHT + OVERLAY_MARGIN};
+    let stack_total: f64 = latest_phases.iter().map(|(_label, time)| time).sum();
+    let mut bar_offset = 0.0;
+    if stack_total > 0.0 {
+        for (i, (_label, time)) in latest_phases.iter().enumerate() {
+            let segment_width = (time / stack_total) as f32 * graph_width;
+            if segment_width > 0.0 {
+                mesh_builder.rectangle(ggez_gfx::DrawMode::fill(),
+                                       ggez_gfx::Rect::new(bar_origin.x + bar_offset, bar_origin.y, segment_width, STACK_BAR_HEIGHT),
+                                       PHASE_COLORS[i % PHASE_COLORS.len()]);
+            }
+            bar_offset += segment_width;
+        }
+    }
+
+    let overlay_mesh = mesh_builder.build(ggez_ctx).unwrap();
+    ggez_gfx::draw(ggez_ctx, &overlay_mesh, ggez_gfx::DrawParam::default()).unwrap();
+
+    /* Text */
+    let summary_str = format!("Frame (ms) min {:.2}  p50 {:.2}  p95 {:.2}  p99 {:.2}  max {:.2}",
+                              summary.min * 1000.0,
+                              summary.p50 * 1000.0,
+                              summary.p95 * 1000.0,
+                              summary.p99 * 1000.0,
+                              summary.max * 1000.0);
+    let summary_pos = ggez_mint::Point2 {x: graph_origin.x + 2.0, y: graph_origin.y + 2.0};
+    draw_text(summary_str, summary_pos, colors::WHITE, ggez_ctx);
+
+    // Legend for the stacked bar, one phase per line
+    let mut legend_pos = ggez_mint::Point2 {x: bar_origin.x,
+                                            y: bar_origin.y + STACK_BAR_HEIGHT + OVERLAY_MARGIN / 2.0};
+    for (i, (label, time)) in latest_phases.iter().enumerate() {
+        let legend_str = format!("{}: {:.3} ms", label, time * 1000.0);
+        draw_text(legend_str, legend_pos, PHASE_COLORS[i % PHASE_COLORS.len()], ggez_ctx);
+        legend_pos.y += STACK_LEGEND_LINE_HEIGHT;
+    }
+
+    // Highlight any metrics lost to a full queue
+    let dropped_color = if dropped_metrics > 0 { colors::RED } else { colors::DARKGREY };
+    draw_text(format!("Dropped metrics: {}", dropped_metrics), legend_pos, dropped_color, ggez_ctx);
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+fn draw_text(text: String, pos: ggez_mint::Point2<f32>, color: ggez_gfx::Color, ggez_ctx: &mut GgEzContext) {
+    let text_display = ggez_gfx::Text::new((text, ggez_gfx::Font::default(), OVERLAY_TEXT_SIZE));
+    ggez_gfx::draw(ggez_ctx, &text_display, (pos, 0.0, color)).unwrap();
+}
@@ -0,0 +1,132 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : profiler/run_metadata.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module will provide data structures and functions describing the
+    configuration of a profiled run, to be recorded alongside its metrics.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    fs,
+    io,
+    path::Path,
+};
+
+use chrono::{
+    DateTime,
+    Local,
+};
+
+use crate::profiler::metrics_format;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Name of the metadata file written to each run directory
+pub const RUN_METADATA_FILENAME: &str = "run.json";
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug)]
+pub struct RunMetadata {
+    seed:           u64,                    // Seeds the game's own RNGs; cast_iron's random weather and instances stay unseeded
+    grid_radius:    usize,
+    window_size:    (f32, f32),
+    crate_version:  String,
+    args:           Vec<String>,
+    start_time:     DateTime<Local>,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl RunMetadata {
+    /// Fully-qualified constructor. The start time is taken to be now.
+    pub fn new(seed: u64, grid_radius: usize, window_size: (f32, f32), args: Vec<String>) -> Self {
+        Self {
+            seed,
+            grid_radius,
+            window_size,
+            crate_version:  String::from(env!("CARGO_PKG_VERSION")),
+            args,
+            start_time:     Local::now(),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn grid_radius(&self) -> usize {
+        self.grid_radius
+    }
+
+    pub fn window_size(&self) -> (f32, f32) {
+        self.window_size
+    }
+
+    pub fn crate_version(&self) -> &str {
+        &self.crate_version
+    }
+
+    pub fn args(&self) -> &Vec<String> {
+        &self.args
+    }
+
+    pub fn start_time(&self) -> DateTime<Local> {
+        self.start_time
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Utility Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Returns the metadata serialized as a JSON object, under the given run name
+    pub fn to_json(&self, run_name: &str) -> String {
+        let args: Vec<String> = self.args.iter().map(|arg| metrics_format::json_string(arg)).collect();
+
+        format!(
+            "{{\n  \"run_name\": {},\n  \"seed\": {},\n  \"grid_radius\": {},\n  \"window_size\": [{}, {}],\n  \"crate_version\": {},\n  \"args\": [{}],\n  \"start_time\": {}\n}}\n",
+            metrics_format::json_string(run_name),
+            self.seed,
+            self.grid_radius,
+            metrics_format::json_f64(self.window_size.0 as f64),
+            metrics_format::json_f64(self.window_size.1 as f64),
+            metrics_format::json_string(&self.crate_version),
+            args.join(", "),
+            metrics_format::json_string(&self.start_time.to_rfc3339())
+        )
+    }
+
+    /// Writes the metadata to the run directory
+    pub fn write(&self, run_dir: &Path, run_name: &str) -> io::Result<()> {
+        fs::write(run_dir.join(RUN_METADATA_FILENAME), self.to_json(run_name))
+    }
+}
@@ -0,0 +1,240 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : profiler/socket_sink.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    This module will provide a metrics sink that streams each metric as
+    newline-delimited JSON to any clients connected to a local socket.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    fmt,
+    io::{
+        self,
+        prelude::*,
+    },
+    net::{
+        TcpListener,
+        TcpStream,
+    },
+    path::PathBuf,
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::os::unix::{
+    fs::FileTypeExt,
+    net::{
+        UnixListener,
+        UnixStream,
+    },
+};
+
+use chrono::Local;
+
+use crate::profiler::{
+    self,
+    metrics_receiver::MetricsSink,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Prefix marking a stream address as a Unix domain socket path
+const UNIX_ADDRESS_PREFIX: &str = "unix:";
+
+/// Longest a write to a client may block before the client is considered to have fallen behind and is dropped,
+/// so that one stalled client can't hold up the other sinks (and, through the queue, the game thread)
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_millis(50);
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Local address that the socket sink listens on
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamAddress {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+pub struct SocketSink {
+    listener:   Listener,
+    clients:    Vec<Box<dyn Write + Send>>,
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl StreamAddress {
+    /// Parses an address of the form "host:port" or "unix:/path/to/socket"
+    pub fn parse(address: &str) -> Self {
+        if let Some(path) = address.strip_prefix(UNIX_ADDRESS_PREFIX) {
+            StreamAddress::Unix(PathBuf::from(path))
+        }
+        else {
+            StreamAddress::Tcp(String::from(address))
+        }
+    }
+}
+
+
+impl SocketSink {
+    /// Binds a non-blocking listener at the given address. Clients may connect at any time.
+    pub fn bind(address: &StreamAddress) -> io::Result<Self> {
+        let listener = match address {
+            StreamAddress::Tcp(addr) => {
+                let tcp_listener = TcpListener::bind(addr)?;
+                tcp_listener.set_nonblocking(true)?;
+                Listener::Tcp(tcp_listener)
+            },
+            #[cfg(unix)]
+            StreamAddress::Unix(path) => {
+                // Clear out a stale socket from a previous run, but never anything else
+                match std::fs::symlink_metadata(path) {
+                    Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+                    Ok(_metadata) => {
+                        return Err(io::Error::new(io::ErrorKind::AlreadyExists,
+                                                  format!("{} exists and is not a socket", path.display())));
+                    },
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+                    Err(e) => return Err(e),
+                }
+                let unix_listener = UnixListener::bind(path)?;
+                unix_listener.set_nonblocking(true)?;
+                Listener::Unix(unix_listener, path.clone())
+            },
+            #[cfg(not(unix))]
+            StreamAddress::Unix(_path) => {
+                return Err(io::Error::new(io::ErrorKind::Other, "Unix domain sockets are not supported on this platform"));
+            },
+        };
+
+        Ok(Self {
+            listener,
+            clients: Vec::new(),
+        })
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Helper Methods    *
+     *  *  *  *  *  *  *  */
+
+    /// Accepts any clients waiting to connect
+    fn accept_pending(&mut self) {
+        loop {
+            let accepted: io::Result<Box<dyn Write + Send>> = match &self.listener {
+                Listener::Tcp(listener) => listener.accept().and_then(|(stream, _addr)| Self::prepare_tcp(stream)),
+                #[cfg(unix)]
+                Listener::Unix(listener, _path) => listener.accept().and_then(|(stream, _addr)| Self::prepare_unix(stream)),
+            };
+
+            match accepted {
+                Ok(client) => {
+                    println!("{}: Metrics stream client connected.", Local::now().format("%Y-%m-%d %T%.3f"));
+                    self.clients.push(client);
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    eprintln!("{}: Failed to accept metrics stream client: {}", Local::now().format("%Y-%m-%d %T%.3f"), e);
+                    break;
+                },
+            }
+        }
+    }
+
+    fn prepare_tcp(stream: TcpStream) -> io::Result<Box<dyn Write + Send>> {
+        // Clients are written to from the receiver thread, so short blocking writes are acceptable
+        stream.set_nonblocking(false)?;
+        stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT))?;
+        stream.set_nodelay(true)?;
+        Ok(Box::new(stream))
+    }
+
+    #[cfg(unix)]
+    fn prepare_unix(stream: UnixStream) -> io::Result<Box<dyn Write + Send>> {
+        stream.set_nonblocking(false)?;
+        stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT))?;
+        Ok(Box::new(stream))
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl MetricsSink for SocketSink {
+    fn record(&mut self, metric: &profiler::MetricContainer) {
+        self.accept_pending();
+        if self.clients.is_empty() {
+            return;
+        }
+
+        let mut line = metric.to_json();
+        line.push('\n');
+
+        // Write to each client, dropping any that have hung up or fallen behind.
+        // A timed-out write may have sent part of the line, so the client's stream can't be resumed either way.
+        let mut i = 0;
+        while i < self.clients.len() {
+            match self.clients[i].write_all(line.as_bytes()) {
+                Ok(()) => i += 1,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+                    println!("{}: Metrics stream client fell behind, disconnecting.", Local::now().format("%Y-%m-%d %T%.3f"));
+                    self.clients.remove(i);
+                },
+                Err(_e) => {
+                    println!("{}: Metrics stream client disconnected.", Local::now().format("%Y-%m-%d %T%.3f"));
+                    self.clients.remove(i);
+                },
+            }
+        }
+    }
+}
+
+impl Drop for SocketSink {
+    fn drop(&mut self) {
+        // Clean up the socket file, since Unix listeners don't do so themselves
+        #[cfg(unix)]
+        {
+            if let Listener::Unix(_listener, path) = &self.listener {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+impl fmt::Display for StreamAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamAddress::Tcp(addr) => write!(f, "{}", addr),
+            StreamAddress::Unix(path) => write!(f, "{}{}", UNIX_ADDRESS_PREFIX, path.display()),
+        }
+    }
+}